@@ -1,9 +1,20 @@
-pub mod utils;
 pub mod cli;
 pub mod commands;
-pub mod github;
+pub mod constraint;
+pub mod database;
 pub mod dist;
+pub mod doctor;
+pub mod error;
+pub mod github;
+pub mod history;
+pub mod hook;
+pub mod manifest;
 pub mod package;
-pub mod database;
+pub mod pe;
+pub mod persist;
+pub mod pin;
 pub mod senget_manager;
-pub mod error;
+pub mod shim;
+pub mod shortcut;
+pub mod transaction;
+pub mod utils;
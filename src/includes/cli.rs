@@ -1,15 +1,22 @@
 //!Parses passed commands and arguments
-use crate::{includes::{
-    commands::{
-        clear_cached_distributables, download_package, export_packages, import_packages,
-        install_package, list_packages, purge_packages, run_package, search_repos, show_package,
-        uninstall_package, update_handler, Statics,
+use crate::{
+    eprintln_pretty,
+    includes::{
+        commands::{
+            add_shortcut, adopt_package, clear_cached_distributables, diagnose, download_package,
+            export_packages, import_packages, install_package, list_packages, list_shortcuts,
+            move_package, pin_package, purge_packages, rebuild_shims, remove_shortcut,
+            restore_database, rollback_package, run_package, search_repos, set_hook, show_history,
+            show_package, switch_package, uninstall_package, unpin_package, update_handler,
+            verify_packages, which_command, ListColumn, ListSort, Statics,
+        },
+        database::PackageDatabase,
+        dist::{Binary, DistType, InstallOptions},
+        error::SengetErrors,
+        hook::{HookKind, Hooks},
+        utils::{absolute_path, DESCRIPTION, EXPORTED_PACKAGES_FILENAME, NAME, VERSION},
     },
-    database::PackageDatabase,
-    dist::DistType,
-    error::SengetErrors,
-    utils::{DESCRIPTION, EXPORTED_PACKAGES_FILENAME, NAME, VERSION},
-}, eprintln_pretty};
+};
 use clap::builder::EnumValueParser;
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use std::path::PathBuf;
@@ -19,7 +26,7 @@ pub fn parse_commands() -> Command {
     let version_arg = Arg::new("version")
         .short('v')
         .long("version")
-        .help("Version of the package")
+        .help("Version of the package or a constraint e.g., ^1.4, ~2.0 or \">=3, <4\", can also be passed as name@version")
         .default_value("latest");
     let path_arg = |help: &str| {
         Arg::new("path")
@@ -41,13 +48,37 @@ pub fn parse_commands() -> Command {
         .short('d')
         .long("dist")
         .help("Distributable type to download, when set to exe an installer may instead\nbe downloaded cause sometimes installers get falsely identified as exe.\nexe means Standalone executable.");
-    let list_command = Command::new("list").about("List installed packages");
+    let list_command = Command::new("list")
+        .about("List installed packages")
+        .arg(flag_arg(
+            "Show the type, size, install and update dates and source asset too",
+            "long",
+            'l',
+        ))
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .short('s')
+                .value_parser(EnumValueParser::<ListSort>::new())
+                .help("Sort the packages instead of listing them in install order"),
+        )
+        .arg(
+            Arg::new("columns")
+                .long("columns")
+                .short('c')
+                .value_parser(EnumValueParser::<ListColumn>::new())
+                .value_delimiter(',')
+                .num_args(1..)
+                .help("Comma separated columns to show instead of the default ones"),
+        );
     let purge_command = Command::new("purge")
         .about("Remove packages that were uninstalled outside senget from the package database");
     let clear_cache_command = Command::new("clear-cache").about("Clear cached distributables");
     let run_command = Command::new("run")
         .about("Run a package")
-        .arg(&name_arg)
+        .arg(Arg::new("name").required(true).help(
+            "Name of the package, use name@version to run a version that's installed side by side",
+        ))
         .arg(flag_arg(
             "Exit immediately after starting the package",
             "no-wait",
@@ -72,6 +103,11 @@ pub fn parse_commands() -> Command {
         .arg(&name_arg)
         .arg(&force_flag_arg(
             "Remove the package from the package database even if automatic uninstallation fails",
+        ))
+        .arg(flag_arg(
+            "Also delete the persisted files and folders of the package",
+            "purge",
+            'p',
         ));
     let install_command = Command::new("install")
         .about("Install a package")
@@ -82,8 +118,212 @@ pub fn parse_commands() -> Command {
             "Don't create a startmenu shortcut file after installing, only works in exe and zip distributables",
             "no-shortcut",
             'n',
+        ))
+        .arg(
+            Arg::new("installer-args")
+                .long("installer-args")
+                .allow_hyphen_values(true)
+                .help("Arguments to pass to the installer instead of its silent switches, only works in installer distributables"),
+        )
+        .arg(flag_arg(
+            "Run the installer with its UI instead of silently, only works in installer distributables",
+            "interactive",
+            'i',
+        ))
+        .arg(
+            Arg::new("location")
+                .short('l')
+                .long("location")
+                .help("Folder to install the package into, ignored by installers that don't support custom locations"),
+        )
+        .arg(
+            Arg::new("exe")
+                .long("exe")
+                .help("Main executable of the package relative to the installation folder, only works in zip distributables.\nUse it when the detected one is wrong, otherwise you're asked to pick when it's ambiguous"),
+        )
+        .arg(
+            Arg::new("bin")
+                .long("bin")
+                .action(ArgAction::Append)
+                .help("Executable to expose as a command in the format relpath[=alias], relative to the installation folder.\nCan be passed multiple times, by default the executables in the root or bin folder are exposed"),
+        )
+        .arg(
+            Arg::new("persist")
+                .long("persist")
+                .action(ArgAction::Append)
+                .help("File or folder relative to the installation folder to keep across updates and uninstalls e.g., config.\nCan be passed multiple times, only works in exe and zip distributables"),
+        )
+        .arg(
+            Arg::new("post-install")
+                .long("post-install")
+                .help("Command to run after installing the package e.g., \"tool --init\".\nThe package's folder and version are passed through the SENGET_PKG_DIR and SENGET_PKG_VERSION environment variables"),
+        )
+        .arg(
+            Arg::new("post-update")
+                .long("post-update")
+                .help("Command to run after updating the package"),
+        )
+        .arg(
+            Arg::new("pre-uninstall")
+                .long("pre-uninstall")
+                .help("Command to run before uninstalling the package"),
+        )
+        .arg(flag_arg(
+            "Install the version alongside the already installed one, only works in exe and zip distributables",
+            "side-by-side",
+            's',
         ));
+    let switch_command = Command::new("switch")
+        .about("Switch to another version of a package that's installed side by side")
+        .arg(&name_arg)
+        .arg(
+            Arg::new("version")
+                .required(true)
+                .help("Version to switch to"),
+        );
+    let rollback_command = Command::new("rollback")
+        .about("Reinstall the version a package was last updated from")
+        .arg(&name_arg);
+    let pin_command = Command::new("pin")
+        .about("Keep \"update all\" from updating a package past its installed version or a version constraint")
+        .arg(&name_arg)
+        .arg(
+            Arg::new("constraint")
+                .help("Versions to allow e.g., \">=1.2, <2\" or \"1.2\" for every 1.2.x version, the installed version is kept if not passed"),
+        );
+    let unpin_command = Command::new("unpin")
+        .about("Let \"update all\" update a pinned package again")
+        .arg(&name_arg);
+    let move_command = Command::new("move")
+        .about("Move an installed package into a different folder, only works in exe and zip distributables")
+        .arg(&name_arg)
+        .arg(
+            Arg::new("location")
+                .required(true)
+                .help("Folder to move the package into"),
+        );
 
+    let verify_command = Command::new("verify")
+        .about("Report missing, modified and extra files in installed packages")
+        .arg(Arg::new("name").help("Name of the package, verifies every package if not passed"));
+    let doctor_command = Command::new("doctor")
+        .about(
+            "Find and repair problems with the installed packages, shims, shortcut files and Path",
+        )
+        .arg(flag_arg(
+            "Repair the problems that can be repaired",
+            "fix",
+            'f',
+        ));
+    let history_command =
+        Command::new("history")
+            .about("Show the installs, updates and uninstalls senget carried out")
+            .arg(Arg::new("name").help("Name of the package, shows every package if not passed"))
+            .arg(Arg::new("since").long("since").help(
+                "Only show entries since a date like 2024-01-31 or a duration like 7d or 12h",
+            ));
+    let which_command = Command::new("which")
+        .about("Show which package provides a command")
+        .arg(
+            Arg::new("command")
+                .required(true)
+                .help("Name of the command"),
+        );
+    let hook_kind_arg = Arg::new("kind")
+        .value_parser(EnumValueParser::<HookKind>::new())
+        .required(true)
+        .help("When the hook runs");
+    let hook_command = Command::new("hook")
+        .about("Manage the commands a package runs after installing or updating and before uninstalling")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("set")
+                .about("Set a hook of a package")
+                .arg(&name_arg)
+                .arg(&hook_kind_arg)
+                .arg(
+                    Arg::new("command")
+                        .required(true)
+                        .allow_hyphen_values(true)
+                        .help("Command to run, the package's folder and version are passed through the SENGET_PKG_DIR and SENGET_PKG_VERSION environment variables"),
+                ),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a hook of a package")
+                .arg(&name_arg)
+                .arg(&hook_kind_arg),
+        );
+    let shim_command = Command::new("shim")
+        .about("Manage the launcher shims for package executables")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("rebuild").about("Recreate the shims for every installed package"),
+        );
+    let db_command = Command::new("db")
+        .about("Manage the database of installed packages")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("restore")
+                .about("Restore the database to how it was before its last change"),
+        );
+    let desktop_flag_arg = flag_arg("Use the desktop instead of the startmenu", "desktop", 'd');
+    let shortcut_label_arg = Arg::new("label")
+        .long("name")
+        .help("Name of the shortcut file, defaults to the name of the package");
+    let shortcut_command = Command::new("shortcut")
+        .about("Manage the shortcut files of a package")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Create a shortcut file for the executable of a package")
+                .arg(&name_arg)
+                .arg(&desktop_flag_arg)
+                .arg(&shortcut_label_arg)
+                .arg(
+                    Arg::new("args")
+                        .long("args")
+                        .allow_hyphen_values(true)
+                        .help("Arguments the shortcut file passes to the executable"),
+                ),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a shortcut file senget made for a package")
+                .arg(&name_arg)
+                .arg(&desktop_flag_arg)
+                .arg(&shortcut_label_arg),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the shortcut files of a package")
+                .arg(&name_arg),
+        );
+    let adopt_command = Command::new("adopt")
+        .about("Let senget manage a package that was unpacked by hand")
+        .arg(
+            Arg::new("name")
+                .help("Full name of the package e.g., owner/repo")
+                .required(true),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .short('p')
+                .required(true)
+                .help("Folder the package was unpacked into"),
+        )
+        .arg(
+            Arg::new("version")
+                .long("version")
+                .short('v')
+                .help("Version of the package, taken from the folder name if not passed"),
+        )
+        .arg(
+            Arg::new("exe")
+                .long("exe")
+                .help("Main executable relative to the folder, detected if not passed"),
+        );
     let download_command = Command::new("download")
         .about("Download the distributable for a package")
         .arg(&name_arg)
@@ -113,17 +353,32 @@ pub fn parse_commands() -> Command {
             Arg::new("version")
                 .short('v')
                 .long("version")
-                .help("Version to update/downgrade to")
+                .help("Version to update/downgrade to or a new constraint e.g., ^1.4, can also be passed as name@version")
                 .default_value("latest"),
-        );
+        )
+        .arg(&dist_type_arg);
 
     Command::new(NAME)
         .version(VERSION)
         .about(DESCRIPTION)
         .subcommand(show_command)
         .subcommand(install_command)
+        .subcommand(adopt_command)
         .subcommand(update_command)
         .subcommand(uninstall_command)
+        .subcommand(move_command)
+        .subcommand(switch_command)
+        .subcommand(rollback_command)
+        .subcommand(pin_command)
+        .subcommand(unpin_command)
+        .subcommand(shim_command)
+        .subcommand(history_command)
+        .subcommand(db_command)
+        .subcommand(shortcut_command)
+        .subcommand(hook_command)
+        .subcommand(which_command)
+        .subcommand(verify_command)
+        .subcommand(doctor_command)
         .subcommand(download_command)
         .subcommand(list_command)
         .subcommand(search_command)
@@ -146,10 +401,52 @@ fn get_flag(id: &str, arg_match: &ArgMatches) -> bool {
 fn get_version(arg_match: &ArgMatches) -> &str {
     get_string_value("version", arg_match)
 }
+/// The version can also be passed with the name e.g., foo@^1.4
+fn get_name_and_version(arg_match: &ArgMatches) -> (&str, &str) {
+    let name = get_name(arg_match);
+    match name.split_once('@') {
+        Some((name, version)) => (name, version),
+        None => (name, get_version(arg_match)),
+    }
+}
 fn get_path(arg_match: &ArgMatches) -> PathBuf {
     PathBuf::from(get_string_value("path", arg_match))
 }
 
+fn get_location(arg_match: &ArgMatches) -> Option<PathBuf> {
+    arg_match
+        .get_one::<String>("location")
+        .map(|l| absolute_path(&PathBuf::from(l)))
+}
+
+fn get_install_options(arg_match: &ArgMatches) -> InstallOptions {
+    InstallOptions {
+        create_shortcut_file: !get_flag("no-shortcut", arg_match),
+        installer_args: arg_match.get_one::<String>("installer-args").cloned(),
+        interactive: get_flag("interactive", arg_match),
+        binaries: get_string_vector("bin", arg_match)
+            .into_iter()
+            .map(|b| Binary::parse(b))
+            .collect(),
+        executable: arg_match.get_one::<String>("exe").map(PathBuf::from),
+        persist: get_string_vector("persist", arg_match)
+            .into_iter()
+            .map(PathBuf::from)
+            .collect(),
+        location: get_location(arg_match),
+        constraint: None,
+        hooks: Hooks {
+            post_install: arg_match.get_one::<String>("post-install").cloned(),
+            post_update: arg_match.get_one::<String>("post-update").cloned(),
+            pre_uninstall: arg_match.get_one::<String>("pre-uninstall").cloned(),
+        },
+    }
+}
+
+fn get_hook_kind(arg_match: &ArgMatches) -> HookKind {
+    *arg_match.get_one::<HookKind>("kind").unwrap()
+}
+
 fn get_dist_type(arg_match: &ArgMatches) -> Option<&DistType> {
     arg_match.get_one("dist")
 }
@@ -166,11 +463,20 @@ pub async fn match_commands(
     statics: &Statics,
 ) -> Result<(), SengetErrors> {
     match commands.get_matches().subcommand() {
-        Some(("list", _)) => {
-            list_packages(db)?;
-            Ok(())
-        }
-        Some(("purge", _)) => purge_packages(db),
+        Some(("list", arg_match)) => list_packages(
+            get_flag("long", arg_match),
+            arg_match.get_one::<ListSort>("sort").copied(),
+            arg_match
+                .get_many::<ListColumn>("columns")
+                .map(|columns| columns.copied().collect()),
+            db,
+        ),
+        Some(("purge", _)) => purge_packages(
+            db,
+            &statics.shims_folder_path,
+            &statics.kept_dists_folder_path,
+            &statics.history,
+        ),
         Some(("clear-cache", _)) => clear_cached_distributables(&statics.dists_folder_path),
         Some(("run", arg_match)) => run_package(
             get_name(arg_match),
@@ -184,13 +490,103 @@ pub async fn match_commands(
         Some(("uninstall", arg_match)) => uninstall_package(
             get_name(arg_match),
             get_flag("force", arg_match),
+            get_flag("purge", arg_match),
+            db,
+            statics,
+        ),
+        Some(("move", arg_match)) => move_package(
+            get_name(arg_match),
+            &get_location(arg_match).unwrap(),
+            &statics.startmenu_folders.appdata,
+            &statics.shims_folder_path,
+            &statics.history,
+            db,
+        ),
+        Some(("switch", arg_match)) => switch_package(
+            get_name(arg_match),
+            get_version(arg_match),
             &statics.startmenu_folders.appdata,
+            &statics.shims_folder_path,
+            &statics.history,
             db,
         ),
+        Some(("history", arg_match)) => show_history(
+            arg_match.get_one::<String>("name").map(|n| n.as_str()),
+            arg_match.get_one::<String>("since").map(|s| s.as_str()),
+            &statics.history,
+        ),
+        Some(("adopt", arg_match)) => {
+            adopt_package(
+                get_name(arg_match),
+                &absolute_path(&get_path(arg_match)),
+                arg_match.get_one::<String>("version").map(|v| v.as_str()),
+                arg_match.get_one::<String>("exe").map(PathBuf::from),
+                db,
+                statics,
+            )
+            .await
+        }
+        Some(("doctor", arg_match)) => diagnose(get_flag("fix", arg_match), db, statics),
+        Some(("verify", arg_match)) => {
+            verify_packages(arg_match.get_one::<String>("name").map(|n| n.as_str()), db)
+        }
+        Some(("pin", arg_match)) => pin_package(
+            get_name(arg_match),
+            arg_match
+                .get_one::<String>("constraint")
+                .map(|c| c.as_str()),
+            db,
+        ),
+        Some(("unpin", arg_match)) => unpin_package(get_name(arg_match), db),
+        Some(("which", arg_match)) => which_command(get_string_value("command", arg_match), db),
+        Some(("shim", arg_match)) => match arg_match.subcommand() {
+            Some(("rebuild", _)) => rebuild_shims(&statics.shims_folder_path, db),
+            // clap requires a subcommand
+            _ => Ok(()),
+        },
+        Some(("db", arg_match)) => match arg_match.subcommand() {
+            Some(("restore", _)) => restore_database(db),
+            // clap requires a subcommand
+            _ => Ok(()),
+        },
+        Some(("hook", arg_match)) => match arg_match.subcommand() {
+            Some(("set", arg_match)) => set_hook(
+                get_name(arg_match),
+                get_hook_kind(arg_match),
+                Some(get_string_value("command", arg_match)),
+                db,
+            ),
+            Some(("remove", arg_match)) => {
+                set_hook(get_name(arg_match), get_hook_kind(arg_match), None, db)
+            }
+            // clap requires a subcommand
+            _ => Ok(()),
+        },
+        Some(("shortcut", arg_match)) => match arg_match.subcommand() {
+            Some(("add", arg_match)) => add_shortcut(
+                get_name(arg_match),
+                get_flag("desktop", arg_match),
+                arg_match.get_one::<String>("label").map(|l| l.as_str()),
+                arg_match.get_one::<String>("args").map(|a| a.as_str()),
+                db,
+                statics,
+            ),
+            Some(("remove", arg_match)) => remove_shortcut(
+                get_name(arg_match),
+                get_flag("desktop", arg_match),
+                arg_match.get_one::<String>("label").map(|l| l.as_str()),
+                db,
+                statics,
+            ),
+            Some(("list", arg_match)) => list_shortcuts(get_name(arg_match), db, statics),
+            // clap requires a subcommand
+            _ => Ok(()),
+        },
         Some(("download", arg_match)) => {
+            let (name, version) = get_name_and_version(arg_match);
             download_package(
-                get_name(arg_match),
-                get_version(arg_match),
+                name,
+                version,
                 &statics.client,
                 &statics.version_regex,
                 &get_path(arg_match),
@@ -200,19 +596,30 @@ pub async fn match_commands(
         }
 
         Some(("install", arg_match)) => {
+            let (name, version) = get_name_and_version(arg_match);
             install_package(
-                get_name(arg_match),
-                get_version(arg_match),
+                name,
+                version,
                 &get_dist_type(arg_match).cloned(),
-                !get_flag("no-shortcut", arg_match),
+                &get_install_options(arg_match),
+                get_flag("side-by-side", arg_match),
                 db,
                 statics,
             )
             .await
         }
         Some(("update", arg_match)) => {
-            update_handler(get_name(arg_match), get_version(arg_match), db, statics).await
+            let (name, version) = get_name_and_version(arg_match);
+            update_handler(
+                name,
+                version,
+                &get_dist_type(arg_match).cloned(),
+                db,
+                statics,
+            )
+            .await
         }
+        Some(("rollback", arg_match)) => rollback_package(get_name(arg_match), db, statics).await,
         Some(("import", arg_match)) => {
             import_packages(
                 &get_path(arg_match),
@@ -1,30 +1,57 @@
 //!Exposes command endpoints
 
-use crate::{includes::{
-    database::PackageDatabase,
-    dist::Dist,
-    dist::{DistType, InstallerDist, StartmenuFolders},
-    error::SengetErrors,
-    error::{
-        check_for_other_errors, AlreadyUptoDateError, ExportFileNotFoundError,
-        FailedToUninstallError, NoExecutableError, NoInstalledPackageError, NoPackageError,
-        NoValidDistError, PackageAlreadyInstalledError, VersionAlreadyInstalledError,
+use crate::{
+    eprintln_pretty,
+    includes::{
+        constraint::VersionConstraint,
+        database::PackageDatabase,
+        dist::Dist,
+        dist::{
+            DistPreference, DistType, InstallOptions, InstallerDist, StartmenuFolders, ZipDist,
+        },
+        doctor::{Doctor, ProblemStatus},
+        error::SengetErrors,
+        error::{
+            check_for_other_errors, AdoptFolderInUseError, AdoptFolderNotFoundError,
+            AlreadyUptoDateError, DidYouMeanError, ExportFileNotFoundError, FailedToUninstallError,
+            LocationNotEmptyError, NoExecutableError, NoInstalledPackageError,
+            NoInstalledVersionError, NoPackageError, NoPackageProvidesCommandError,
+            NoPreviousVersionError, NoValidDistError, PackageAlreadyInstalledError,
+            PackageNotPinnedError, ShortcutNotFoundError, UnknownAdoptedVersionError,
+            VersionAlreadyInstalledError, VersionOutsideConstraintError,
+        },
+        github::{self, api::Repo},
+        history::{
+            current_timestamp, format_timestamp, parse_since, History, HistoryAction, HistoryEntry,
+        },
+        hook::HookKind,
+        package::ExportedPackage,
+        package::Package,
+        persist::{generate_persist_folder_path, PersistStore},
+        pin::Pin,
+        shim::{self, generate_shims_folder_path, Shim},
+        shortcut::{
+            find_shortcut_files, generate_desktop_folder_path, read_shortcut, ShortcutFile,
+        },
+        transaction::Transaction,
+        utils::{
+            closest_name, folder_size, format_size, loading_animation, pick_by_name, setup_client,
+            FolderItems, PathStr,
+        },
+        utils::{DEBUG, IBYTES_TO_MBS_DIVISOR},
     },
-    github::{self, api::Repo},
-    package::ExportedPackage,
-    package::Package,
-    utils::{loading_animation, setup_client, FolderItems, PathStr},
-    utils::{DEBUG, IBYTES_TO_MBS_DIVISOR},
-}, success_println_pretty, eprintln_pretty};
+    success_println_pretty,
+};
+use clap::ValueEnum;
 use regex::Regex;
 use reqwest::Client;
 use std::{
-    fs::{self, DirEntry, File},
+    cmp::Reverse,
+    fs::{self, File},
     io::{self, Write},
-    os::windows::fs::MetadataExt,
+    iter,
     path::{Path, PathBuf},
     process::Command,
-    u64,
 };
 use winreg::RegKey;
 pub struct Statics {
@@ -32,7 +59,12 @@ pub struct Statics {
     pub version_regex: Regex,
     pub packages_folder_path: PathBuf,
     pub dists_folder_path: PathBuf,
+    pub kept_dists_folder_path: PathBuf,
+    pub shims_folder_path: PathBuf,
+    pub persist_folder_path: PathBuf,
     pub startmenu_folders: StartmenuFolders,
+    pub desktop_folder_path: PathBuf,
+    pub history: History,
     pub user_uninstall_reg_key: RegKey,
     pub machine_uninstall_reg_key: RegKey,
 }
@@ -41,31 +73,144 @@ impl Statics {
     pub fn new(config_dir: &Path) -> Result<Statics, SengetErrors> {
         let client = setup_client()?;
         let dists_folder_path = Dist::generate_dists_folder_path(config_dir)?;
+        let kept_dists_folder_path = Dist::generate_kept_dists_folder_path(config_dir)?;
         let startmenu_folders = InstallerDist::generate_startmenu_paths();
-        let packages_folder_path =
-            Dist::generate_packages_folder_path(config_dir)?;
+        let desktop_folder_path = generate_desktop_folder_path();
+        let packages_folder_path = Dist::generate_packages_folder_path(config_dir)?;
+        let shims_folder_path = generate_shims_folder_path(config_dir)?;
+        let persist_folder_path = generate_persist_folder_path(config_dir)?;
         let user_uninstall_reg_key = InstallerDist::generate_user_uninstall_reg_key()?;
         let machine_uninstall_reg_key = InstallerDist::generate_machine_uninstall_reg_key()?;
         let version_regex = github::api::Repo::generate_version_regex();
+        let history = History::new(config_dir);
         Ok(Statics {
             client,
             version_regex,
             packages_folder_path,
             dists_folder_path,
+            kept_dists_folder_path,
+            shims_folder_path,
+            persist_folder_path,
             startmenu_folders,
+            desktop_folder_path,
+            history,
             user_uninstall_reg_key,
             machine_uninstall_reg_key,
         })
     }
 }
 
-async fn find_repo(name: &str, client: &Client) -> Result<Option<Repo>, SengetErrors> {
-    let name_lower = name.to_lowercase();
-    let found_repo = github::api::search(name, client)
-        .await?
-        .into_iter()
-        .find(|r| r.name.to_lowercase() == name_lower || r.full_name.to_lowercase() == name_lower);
-    Ok(found_repo)
+#[cfg(test)]
+impl Statics {
+    /// Every folder is inside the config folder, the uninstall registry keys are never opened
+    pub fn test_fixture(config_dir: &Path) -> Statics {
+        use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+        let startmenu_folder = config_dir.join("Start Menu");
+        let statics = Statics {
+            client: Client::new(),
+            version_regex: Repo::generate_version_regex(),
+            packages_folder_path: config_dir.join("packages"),
+            dists_folder_path: config_dir.join("distributables"),
+            kept_dists_folder_path: config_dir.join("kept_distributables"),
+            shims_folder_path: config_dir.join("shims"),
+            persist_folder_path: config_dir.join("persist"),
+            startmenu_folders: StartmenuFolders {
+                appdata: startmenu_folder.join("AppData"),
+                programdata: startmenu_folder.join("ProgramData"),
+            },
+            desktop_folder_path: config_dir.join("Desktop"),
+            history: History::new(config_dir),
+            user_uninstall_reg_key: RegKey::predef(HKEY_CURRENT_USER),
+            machine_uninstall_reg_key: RegKey::predef(HKEY_LOCAL_MACHINE),
+        };
+        let folders = [
+            &statics.packages_folder_path,
+            &statics.dists_folder_path,
+            &statics.kept_dists_folder_path,
+            &statics.shims_folder_path,
+            &statics.persist_folder_path,
+            &statics.startmenu_folders.appdata,
+            &statics.startmenu_folders.programdata,
+            &statics.desktop_folder_path,
+        ];
+        for folder in folders {
+            fs::create_dir_all(folder).unwrap();
+        }
+        statics
+    }
+}
+
+/// The error for a name no package has, suggesting the closest candidate if it looks like a typo
+fn package_not_found_error<'a, I>(name: &str, candidates: I, installed: bool) -> SengetErrors
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    match (closest_name(name, candidates), installed) {
+        (Some(suggestion), _) => DidYouMeanError {
+            installed,
+            suggestion: suggestion.to_owned(),
+        }
+        .into(),
+        (None, true) => NoInstalledPackageError.into(),
+        (None, false) => NoPackageError.into(),
+    }
+}
+
+fn no_installed_package_error(name: &str, db: &PackageDatabase) -> SengetErrors {
+    let packages = db.fetch_all_packages().unwrap_or_default();
+    let candidates = packages.iter().map(|p| match name.contains('/') {
+        true => p.repo.full_name.as_str(),
+        false => p.repo.name.as_str(),
+    });
+    package_not_found_error(name, candidates, true)
+}
+
+/// Only called once the database is saved, so a step that fails just leaves files behind for the
+/// doctor rather than failing a command that went through
+fn commit_transaction(transaction: Transaction) {
+    if let Err(err) = transaction.commit() {
+        eprintln_pretty!(
+            "Couldn't clean up after the changes, run senget doctor --fix to finish: {}",
+            err
+        );
+    }
+}
+
+/// The package is already saved by the time its distributable is kept, so failing to keep it only
+/// means rolling back downloads it again
+fn keep_dist(package: &Package, downloaded_dist_path: &Path, statics: &Statics) {
+    if let Err(err) = package.keep_dist(downloaded_dist_path, &statics.kept_dists_folder_path) {
+        eprintln_pretty!(
+            "Couldn't keep the distributable of {} for rolling back: {}",
+            package.repo.name,
+            err
+        );
+    }
+}
+
+/// Only pre-uninstall hooks can stop a command, the package is already installed or updated by the
+/// time a post hook runs
+fn run_post_hook(kind: HookKind, package: &Package) {
+    if let Err(err) = package.hooks.run(kind, package) {
+        eprintln_pretty!("{}: {:?}", package.repo.name, err);
+    }
+}
+
+async fn find_repo(name: &str, client: &Client) -> Result<Repo, SengetErrors> {
+    // Search may not rank the repo in its first results so full names are looked up directly
+    if name.contains('/') {
+        return match github::api::get_repo(name, client).await? {
+            Some(repo) => Ok(repo),
+            None => Err(NoPackageError.into()),
+        };
+    }
+    let repos = github::api::search(name, client).await?;
+    let not_found_error =
+        package_not_found_error(name, repos.iter().map(|r| r.name.as_str()), false);
+    match pick_by_name(name, repos, |r| (&r.name, &r.full_name))? {
+        Some(repo) => Ok(repo),
+        None => Err(not_found_error),
+    }
 }
 
 pub async fn show_package(
@@ -75,37 +220,25 @@ pub async fn show_package(
 ) -> Result<(), SengetErrors> {
     match db.find_package(name)? {
         Some(package) => Ok(println!("{}", package)),
-        None => match find_repo(name, client).await? {
-            Some(repo) => Ok(println!("{}", repo)),
-            None => Err(NoPackageError.into()),
-        },
+        None => Ok(println!("{}", find_repo(name, client).await?)),
     }
 }
 
+/// The distributables kept for rolling back are in their own folder so they stay
 pub fn clear_cached_distributables(dists_folder_path: &Path) -> Result<(), SengetErrors> {
-    let calc_size = |prev_size: u64, d: DirEntry| -> Result<u64, io::Error> {
-        let p = d.path();
-        if !p.is_file() {
-            return Ok(prev_size);
-        };
-        let s = p.metadata()?.file_size();
-        fs::remove_file(&p)?;
-        Ok(prev_size + s)
-    };
-    let size = dists_folder_path
-        .folder_items()?
-        .into_iter()
-        .try_fold(0, calc_size)?;
+    let mut size = 0;
+    for item in dists_folder_path.folder_items()? {
+        let path = item.path();
+        if path.is_file() {
+            size += item.metadata()?.len();
+            fs::remove_file(&path)?;
+        }
+    }
     success_println_pretty!("Cleared {} MBs", size / IBYTES_TO_MBS_DIVISOR);
     Ok(())
 }
 pub fn validate_cache_folder_size(dists_folder_path: &Path) -> Result<(), SengetErrors> {
-    let size: u64 = dists_folder_path
-        .folder_items()?
-        .iter()
-        .filter(|f| f.path().is_file())
-        .flat_map(|f| f.metadata().map(|m| m.file_size()))
-        .sum();
+    let size = folder_size(dists_folder_path)?;
     let size_mbs = size / IBYTES_TO_MBS_DIVISOR;
     if size_mbs >= 100 && !DEBUG {
         println!(
@@ -115,7 +248,12 @@ pub fn validate_cache_folder_size(dists_folder_path: &Path) -> Result<(), Senget
     }
     Ok(())
 }
-pub fn purge_packages(db: &PackageDatabase) -> Result<(), SengetErrors> {
+pub fn purge_packages(
+    db: &PackageDatabase,
+    shims_folder: &Path,
+    kept_dists_folder: &Path,
+    history: &History,
+) -> Result<(), SengetErrors> {
     let to_remove: Vec<Package> = db
         .fetch_all_packages()?
         .iter()
@@ -132,7 +270,16 @@ pub fn purge_packages(db: &PackageDatabase) -> Result<(), SengetErrors> {
         return Ok(println!("No packages to purge"));
     }
     for p in to_remove {
+        for shim in Shim::from_install_info(&p.install_info, shims_folder) {
+            shim.remove()?;
+        }
         db.remove_package(&p)?;
+        p.remove_kept_dists(kept_dists_folder)?;
+        history.record(&HistoryEntry::new(
+            HistoryAction::Purge,
+            &p.repo.name,
+            Some(&p.version),
+        ))?;
         success_println_pretty!("Purged {}", p.repo.name);
     }
     Ok(())
@@ -143,26 +290,62 @@ async fn update_all_packages(
     db: &PackageDatabase,
     statics: &Statics,
 ) -> Result<(), SengetErrors> {
-    let mut errored_packages: Vec<Vec<String>> = Vec::new();
+    let mut summary_rows: Vec<Vec<String>> = Vec::new();
     for p in db.fetch_all_packages()? {
-        if let Err(err) = update_package(&p.repo.name, version, db, statics).await {
+        let pinned_version = match p.pin.as_ref() {
+            Some(pin) => {
+                let versions = match pin.constraint {
+                    Some(_) => p
+                        .repo
+                        .get_versions(&statics.client, &statics.version_regex, |v| {
+                            pin.allows(v, p.constraint.as_ref())
+                        })
+                        .await
+                        .map_err(SengetErrors::from),
+                    None => Ok(Vec::new()),
+                };
+                match versions.map(|v| pin.pick_update(&p.version, &v, p.constraint.as_ref())) {
+                    Ok(Some(pinned_version)) => Some(pinned_version),
+                    Ok(None) => {
+                        summary_rows.push(vec![
+                            p.repo.name,
+                            "Skipped".to_owned(),
+                            format!("Pinned to {}", pin),
+                        ]);
+                        continue;
+                    }
+                    Err(err) => {
+                        summary_rows.push(vec![
+                            p.repo.name,
+                            "Failed".to_owned(),
+                            format!("{:?}", check_for_other_errors(err)),
+                        ]);
+                        continue;
+                    }
+                }
+            }
+            None => None,
+        };
+        let version = pinned_version.as_deref().unwrap_or(version);
+        if let Err(err) = update_package(&p.repo.name, version, &None, db, statics).await {
             match err {
                 SengetErrors::AlreadyUptoDateError(_) => continue,
                 SengetErrors::VersionAlreadyInstalledError(_) => continue,
-                _ => errored_packages.push(vec![
+                _ => summary_rows.push(vec![
                     p.repo.name,
+                    "Failed".to_owned(),
                     format!("{:?}", check_for_other_errors(err)),
                 ]),
             }
         }
     }
-    match errored_packages.is_empty() {
+    match summary_rows.is_empty() {
         true => success_println_pretty!("Updated all the necessary packages."),
         false => println!(
-            "Errors encountered updating the following packages:\n{}",
+            "The following packages weren't updated:\n{}",
             generate_table_string(
-                &vec!["Name".to_owned(), "Error".to_owned()],
-                &errored_packages
+                &vec!["Name".to_owned(), "Status".to_owned(), "Reason".to_owned()],
+                &summary_rows
             )
         ),
     }
@@ -197,43 +380,69 @@ async fn internal_download_package(
     version_regex: &Regex,
     dists_folder_path: &Path,
 ) -> Result<(Repo, Dist, PathBuf), SengetErrors> {
-    match find_repo(name, client).await? {
-        Some(repo) => {
-            let dist = match version {
-                "latest" => {
-                    repo.get_latest_dist(client, version_regex, preferred_dist_type)
-                        .await?
-                }
-                version => {
-                    repo.get_dist(client, version, version_regex, preferred_dist_type)
-                        .await?
-                }
-            };
-            match dist {
-                Some(mut dist) => {
-                    let dist_path = dist.download(client, dists_folder_path).await?;
-                    if let Dist::Exe(exe_dist) = dist {
-                        dist = exe_dist.check_if_is_actually_installer(&dist_path)?;
-                    }
-                    Ok((repo, dist, dist_path))
-                }
-                None => Err(NoValidDistError.into()),
+    let constraint = VersionConstraint::from_version_arg(version)?;
+    let repo = find_repo(name, client).await?;
+    let dist_preference = DistPreference::from(preferred_dist_type.clone());
+    let dist = match (version, constraint) {
+        (_, Some(constraint)) => {
+            repo.get_constrained_dist(client, &constraint, version_regex, &dist_preference)
+                .await?
+        }
+        ("latest", None) => {
+            repo.get_latest_dist(client, version_regex, &dist_preference)
+                .await?
+        }
+        (version, None) => {
+            repo.get_dist(client, version, version_regex, &dist_preference)
+                .await?
+        }
+    };
+    match dist {
+        Some(mut dist) => {
+            let dist_path = dist.download(client, dists_folder_path).await?;
+            if let Dist::Exe(exe_dist) = dist {
+                dist = exe_dist.check_if_is_actually_installer(&dist_path)?;
             }
+            Ok((repo, dist, dist_path))
         }
-        None => Err(NoPackageError.into()),
+        None => Err(NoValidDistError.into()),
     }
 }
 pub async fn install_package(
     name: &str,
     version: &str,
     preferred_dist_type: &Option<DistType>,
-    create_shortcut_file: bool,
+    install_options: &InstallOptions,
+    side_by_side: bool,
     db: &PackageDatabase,
     statics: &Statics,
 ) -> Result<(), SengetErrors> {
     match db.find_package(name)? {
-        Some(_) => Err(PackageAlreadyInstalledError.into()),
+        Some(package) => match side_by_side {
+            true => {
+                install_side_by_side_version(
+                    package,
+                    version,
+                    preferred_dist_type,
+                    install_options,
+                    db,
+                    statics,
+                )
+                .await
+            }
+            false => Err(PackageAlreadyInstalledError.into()),
+        },
         None => {
+            // Same as moving, the package shouldn't end up mixed in with someone else's files
+            if let Some(location) = install_options.location.as_ref() {
+                if location.is_dir() && !location.folder_items()?.is_empty() {
+                    return Err(LocationNotEmptyError.into());
+                }
+            }
+            let constraint = match install_options.constraint.clone() {
+                Some(constraint) => Some(constraint),
+                None => VersionConstraint::from_version_arg(version)?,
+            };
             let (repo, dist, downloaded_package_path) = internal_download_package(
                 name,
                 version,
@@ -243,46 +452,333 @@ pub async fn install_package(
                 &statics.dists_folder_path,
             )
             .await?;
-            let task = || {
-                dist.install(
-                    &downloaded_package_path,
-                    &statics.packages_folder_path,
-                    create_shortcut_file,
-                    &statics.startmenu_folders,
-                    &statics.user_uninstall_reg_key,
-                    &statics.machine_uninstall_reg_key,
-                )
+            let source = dist.source(&downloaded_package_path)?;
+            let history_entry = HistoryEntry::new(HistoryAction::Install, &repo.name, None)
+                .with_dist(&dist, &source);
+            let install = || -> Result<Package, SengetErrors> {
+                let task = || {
+                    dist.install(
+                        &downloaded_package_path,
+                        &statics.packages_folder_path,
+                        install_options,
+                        &statics.startmenu_folders,
+                        &statics.user_uninstall_reg_key,
+                        &statics.machine_uninstall_reg_key,
+                    )
+                };
+                let (mut install_info, mut transaction) =
+                    loading_animation(format!("Installing {}.. .", repo.name), task)?;
+                if install_info.dist_type == DistType::Zip && install_info.executable_path.is_none()
+                {
+                    println!(
+                        "No executable found, installed {} as a data-only package.",
+                        repo.name
+                    );
+                }
+                PersistStore::new(&statics.persist_folder_path, &repo.name)
+                    .link_into(&mut install_info)?;
+                install_info.source = Some(source);
+                let mut package = Package::new(dist.version().to_owned(), repo, install_info);
+                package.installed_at = Some(current_timestamp());
+                package.refresh_size();
+                package.constraint = constraint;
+                package.hooks = install_options.hooks.clone();
+                package.replace_shims(None, &statics.shims_folder_path, &mut transaction)?;
+                // If saving fails the transaction is dropped which rolls back the installation
+                db.add_package(package.clone())?;
+                commit_transaction(transaction);
+                Ok(package)
             };
-            let install_info = loading_animation(format!("Installing {}.. .", repo.name), task)?;
-            let package_name = repo.name.clone();
-            let package = Package::new(dist.version().to_owned(), repo, install_info);
-            db.add_package(package)?;
-            success_println_pretty!("Installed {}.", package_name);
+            let result = install();
+            statics
+                .history
+                .record(&history_entry.with_outcome(&result))?;
+            let package = result?;
+            keep_dist(&package, &downloaded_package_path, statics);
+            success_println_pretty!("Installed {}.", package.repo.name);
+            run_post_hook(HookKind::PostInstall, &package);
             Ok(())
         }
     }
 }
 
+async fn install_side_by_side_version(
+    package: Package,
+    version: &str,
+    preferred_dist_type: &Option<DistType>,
+    install_options: &InstallOptions,
+    db: &PackageDatabase,
+    statics: &Statics,
+) -> Result<(), SengetErrors> {
+    let package = migrate_package(
+        package,
+        &statics.startmenu_folders.appdata,
+        &statics.shims_folder_path,
+        db,
+    )?;
+    let dist = match package
+        .get_dist(
+            version,
+            preferred_dist_type,
+            &statics.client,
+            &statics.version_regex,
+        )
+        .await?
+    {
+        Some(dist) => dist,
+        None => return Err(NoValidDistError.into()),
+    };
+    if package.version_install_info(dist.version()).is_some() {
+        return Err(VersionAlreadyInstalledError.into());
+    }
+    let dist_path = dist
+        .download(&statics.client, &statics.dists_folder_path)
+        .await?;
+    let installed_version = dist.version().to_owned();
+    let source = dist.source(&dist_path)?;
+    let history_entry = HistoryEntry::new(HistoryAction::Install, &package.repo.name, None)
+        .with_dist(&dist, &source);
+    let install = || -> Result<(), SengetErrors> {
+        let task = || {
+            package.install_side_by_side(
+                dist,
+                &dist_path,
+                &statics.packages_folder_path,
+                install_options,
+            )
+        };
+        let (mut new_package, transaction) = loading_animation(
+            format!("Installing {} {}.. .", package.repo.name, installed_version),
+            task,
+        )?;
+        if let Some(installed_version) = new_package.other_versions.last_mut() {
+            PersistStore::new(&statics.persist_folder_path, &package.repo.name)
+                .link_into(&mut installed_version.install_info)?;
+            installed_version.install_info.source = Some(source);
+        }
+        new_package.refresh_size();
+        db.update_package(&package, new_package)?;
+        commit_transaction(transaction);
+        Ok(())
+    };
+    let result = install();
+    statics
+        .history
+        .record(&history_entry.with_outcome(&result))?;
+    result?;
+    // Only the active version can be rolled back so there's nothing to keep it for
+    if !DEBUG {
+        fs::remove_file(&dist_path)?;
+    }
+    success_println_pretty!(
+        "Installed {} {} side by side with {}.",
+        package.repo.name,
+        installed_version,
+        package.version
+    );
+    Ok(())
+}
+
+/// Registers a folder the user unpacked by hand as a zip package without downloading anything,
+/// the version is taken from the folder name if it isn't given
+pub async fn adopt_package(
+    name: &str,
+    folder: &Path,
+    version: Option<&str>,
+    executable: Option<PathBuf>,
+    db: &PackageDatabase,
+    statics: &Statics,
+) -> Result<(), SengetErrors> {
+    if !folder.is_dir() {
+        return Err(AdoptFolderNotFoundError.into());
+    }
+    if db.find_package(name)?.is_some() {
+        return Err(PackageAlreadyInstalledError.into());
+    }
+    check_adoptable_folder(
+        folder,
+        &db.fetch_all_packages()?,
+        &statics.packages_folder_path,
+    )?;
+    let version = adopted_version(version, folder, &statics.version_regex)?;
+    let repo = find_repo(name, &statics.client).await?;
+    let history_entry = HistoryEntry {
+        to_version: Some(version.clone()),
+        dist_type: Some(DistType::Zip),
+        ..HistoryEntry::new(HistoryAction::Adopt, &repo.name, None)
+    };
+    let install_options = InstallOptions {
+        executable,
+        ..InstallOptions::default()
+    };
+    let adopt = || -> Result<Package, SengetErrors> {
+        let task = || ZipDist::adopt(&repo.name, folder, &install_options);
+        let install_info = loading_animation(format!("Adopting {}.. .", repo.name), task)?;
+        if install_info.executable_path.is_none() {
+            println!(
+                "No executable found, adopted {} as a data-only package.",
+                repo.name
+            );
+        }
+        let mut package = Package::new(version, repo, install_info);
+        package.installed_at = Some(current_timestamp());
+        package.refresh_size();
+        let mut transaction = Transaction::new();
+        package.replace_shims(None, &statics.shims_folder_path, &mut transaction)?;
+        db.add_package(package.clone())?;
+        commit_transaction(transaction);
+        Ok(package)
+    };
+    let result = adopt();
+    statics
+        .history
+        .record(&history_entry.with_outcome(&result))?;
+    let package = result?;
+    success_println_pretty!("Adopted {} {}.", package.repo.name, package.version);
+    Ok(())
+}
+
+/// Uninstalling either package would delete the other's files if a folder senget already
+/// manages was adopted
+fn check_adoptable_folder(
+    folder: &Path,
+    packages: &[Package],
+    packages_folder_path: &Path,
+) -> Result<(), AdoptFolderInUseError> {
+    // The folder may have been passed with .. in it
+    let canonicalize = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let folder = canonicalize(folder);
+    let mut used_folders = packages.iter().flat_map(|p| {
+        iter::once(&p.install_info)
+            .chain(p.other_versions.iter().map(|v| &v.install_info))
+            .flat_map(|ii| [ii.installation_folder.clone(), ii.location.clone()])
+            .chain(iter::once(p.package_folder()))
+            .flatten()
+    });
+    let is_used = used_folders.any(|used_folder| {
+        let used_folder = canonicalize(&used_folder);
+        folder.starts_with(&used_folder) || used_folder.starts_with(&folder)
+    });
+    match is_used || folder.starts_with(canonicalize(packages_folder_path)) {
+        true => Err(AdoptFolderInUseError),
+        false => Ok(()),
+    }
+}
+
+/// The version passed with --version or else the one in the folder name, parsed the same way as
+/// release versions so updates compare it with them
+fn adopted_version(
+    version: Option<&str>,
+    folder: &Path,
+    version_regex: &Regex,
+) -> Result<String, UnknownAdoptedVersionError> {
+    let folder_name = folder.file_name().unwrap_or_default().to_string_lossy();
+    let version = version.unwrap_or(&folder_name);
+    Repo::parse_version(version, version_regex)
+        .map(str::to_owned)
+        .ok_or(UnknownAdoptedVersionError)
+}
+
+/// Moves packages installed before every version got its own subfolder into one
+fn migrate_package(
+    package: Package,
+    startmenu_appdata_folder: &Path,
+    shims_folder: &Path,
+    db: &PackageDatabase,
+) -> Result<Package, SengetErrors> {
+    if !package.needs_versioned_folder_migration() {
+        return Ok(package);
+    }
+    let (migrated_package, transaction) =
+        package.migrate_to_versioned_folder(startmenu_appdata_folder, shims_folder)?;
+    db.update_package(&package, migrated_package.clone())?;
+    commit_transaction(transaction);
+    Ok(migrated_package)
+}
+
+pub fn switch_package(
+    name: &str,
+    version: &str,
+    startmenu_appdata_folder: &Path,
+    shims_folder: &Path,
+    history: &History,
+    db: &PackageDatabase,
+) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) => {
+            if package.version == version {
+                return Err(VersionAlreadyInstalledError.into());
+            }
+            let package = migrate_package(package, startmenu_appdata_folder, shims_folder, db)?;
+            let switch = || -> Result<(), SengetErrors> {
+                let (switched_package, transaction) =
+                    package.switch_version(version, startmenu_appdata_folder, shims_folder)?;
+                db.update_package(&package, switched_package)?;
+                commit_transaction(transaction);
+                Ok(())
+            };
+            let result = switch();
+            let mut history_entry = HistoryEntry::new(
+                HistoryAction::Switch,
+                &package.repo.name,
+                Some(&package.version),
+            )
+            .with_outcome(&result);
+            history_entry.to_version = Some(version.to_owned());
+            history.record(&history_entry)?;
+            result?;
+            success_println_pretty!("Switched {} to {}", package.repo.name, version);
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
 pub fn uninstall_package(
     name: &str,
     force: bool,
-    startmenu_appdata_folder: &Path,
+    purge: bool,
     db: &PackageDatabase,
+    statics: &Statics,
 ) -> Result<(), SengetErrors> {
     match db.find_package(name)? {
         Some(package) => {
+            if let Err(err) = package.hooks.run(HookKind::PreUninstall, &package) {
+                if !force {
+                    return Err(err.into());
+                }
+                eprintln_pretty!("{:?}", err);
+            }
+            let persist_store = PersistStore::new(&statics.persist_folder_path, &package.repo.name);
             let task = || -> Result<(), SengetErrors> {
-                if !package.uninstall(startmenu_appdata_folder)? {
+                persist_store.save(&package.install_info)?;
+                if !package.uninstall(
+                    &statics.packages_folder_path,
+                    &statics.startmenu_folders.appdata,
+                    &statics.shims_folder_path,
+                )? {
                     return Err(FailedToUninstallError.into());
                 }
                 Ok(())
             };
-            let success =
-                loading_animation(format!("Uninstalling {}", package.repo.name), task).is_ok();
+            let result = loading_animation(format!("Uninstalling {}", package.repo.name), task);
+            let mut history_entry = HistoryEntry::new(
+                HistoryAction::Uninstall,
+                &package.repo.name,
+                Some(&package.version),
+            )
+            .with_outcome(&result);
+            history_entry.dist_type = Some(package.install_info.dist_type.clone());
+            statics.history.record(&history_entry)?;
+            let success = result.is_ok();
             if !(success || force) {
                 return Err(FailedToUninstallError.into());
             }
             db.remove_package(&package)?;
+            package.remove_kept_dists(&statics.kept_dists_folder_path)?;
+            if purge {
+                persist_store.purge()?;
+            }
             if success {
                 success_println_pretty!("Uninstalled {}.", package.repo.name);
             } else {
@@ -290,39 +786,76 @@ pub fn uninstall_package(
             }
             Ok(())
         }
-        None => Err(NoInstalledPackageError.into()),
+        None => Err(no_installed_package_error(name, db)),
     }
 }
 
-// FIXME: Fix updating into a different distributable e.g., from Exe to Installer
 pub async fn update_handler(
     name: &str,
     version: &str,
+    preferred_dist_type: &Option<DistType>,
     db: &PackageDatabase,
     statics: &Statics,
 ) -> Result<(), SengetErrors> {
     match name == "all" {
         true => update_all_packages("latest", db, statics).await,
-        false => update_package(name, version, db, statics).await,
+        false => update_package(name, version, preferred_dist_type, db, statics).await,
     }
 }
 
 async fn update_package(
     name: &str,
     version: &str,
+    preferred_dist_type: &Option<DistType>,
     db: &PackageDatabase,
     statics: &Statics,
 ) -> Result<(), SengetErrors> {
     match db.find_package(name)? {
         Some(old_package) => {
+            let old_package = migrate_package(
+                old_package,
+                &statics.startmenu_folders.appdata,
+                &statics.shims_folder_path,
+                db,
+            )?;
+            let new_constraint = VersionConstraint::from_version_arg(version)?;
+            if let (None, Some(constraint), false) = (
+                &new_constraint,
+                &old_package.constraint,
+                version == "latest",
+            ) {
+                if !constraint.allows(version) {
+                    return Err(VersionOutsideConstraintError.into());
+                }
+            }
             match old_package
-                .get_dist(version, &statics.client, &statics.version_regex)
+                .get_dist(
+                    version,
+                    preferred_dist_type,
+                    &statics.client,
+                    &statics.version_regex,
+                )
                 .await?
             {
-                Some(dist) => match old_package.version == dist.version() {
-                    true => match version == "latest" {
-                        true => Err(AlreadyUptoDateError.into()),
-                        false => Err(VersionAlreadyInstalledError.into()),
+                Some(mut dist) => match old_package.version == dist.version()
+                    && old_package.install_info.dist_type == dist.dist_type()
+                {
+                    true => match (version == "latest", new_constraint) {
+                        (true, _) => Err(AlreadyUptoDateError.into()),
+                        (false, Some(constraint))
+                            if old_package.constraint.as_ref() != Some(&constraint) =>
+                        {
+                            let mut constrained_package = old_package.clone();
+                            constrained_package.constraint = Some(constraint.clone());
+                            db.update_package(&old_package, constrained_package)?;
+                            success_println_pretty!(
+                                "Constrained {} to {}",
+                                old_package.repo.name,
+                                constraint
+                            );
+                            Ok(())
+                        }
+                        (false, _) => Err(VersionAlreadyInstalledError.into()),
                     },
                     false => {
                         println!(
@@ -334,50 +867,689 @@ async fn update_package(
                         let dist_path = dist
                             .download(&statics.client, &statics.dists_folder_path)
                             .await?;
-                        let task = || {
-                            old_package.install_updated_version(
-                                dist,
-                                &dist_path,
-                                &statics.packages_folder_path,
-                                &statics.startmenu_folders,
-                                &statics.user_uninstall_reg_key,
-                                &statics.machine_uninstall_reg_key,
-                            )
+                        if let Dist::Exe(exe_dist) = dist {
+                            dist = exe_dist.check_if_is_actually_installer(&dist_path)?;
+                        }
+                        if old_package.install_info.dist_type != dist.dist_type() {
+                            println!(
+                                "Switching {} from {:?} to {:?} distributable",
+                                old_package.repo.name,
+                                old_package.install_info.dist_type,
+                                dist.dist_type()
+                            );
+                        }
+                        let source = dist.source(&dist_path)?;
+                        let history_entry = HistoryEntry::new(
+                            HistoryAction::Update,
+                            &old_package.repo.name,
+                            Some(&old_package.version),
+                        )
+                        .with_dist(&dist, &source);
+                        let update = || -> Result<Package, SengetErrors> {
+                            let persist_store = PersistStore::new(
+                                &statics.persist_folder_path,
+                                &old_package.repo.name,
+                            );
+                            persist_store.save(&old_package.install_info)?;
+                            let task = || {
+                                old_package.install_updated_version(
+                                    dist,
+                                    &dist_path,
+                                    &statics.packages_folder_path,
+                                    &statics.startmenu_folders,
+                                    &statics.user_uninstall_reg_key,
+                                    &statics.machine_uninstall_reg_key,
+                                )
+                            };
+                            let (mut new_package, mut transaction) = loading_animation(
+                                format!("Updating {}.. .", old_package.repo.name),
+                                task,
+                            )?;
+                            persist_store.link_into(&mut new_package.install_info)?;
+                            new_package.install_info.source = Some(source);
+                            new_package.updated_at = Some(current_timestamp());
+                            new_package.refresh_size();
+                            if new_constraint.is_some() {
+                                new_package.constraint = new_constraint;
+                            }
+                            new_package.replace_shims(
+                                Some(&old_package),
+                                &statics.shims_folder_path,
+                                &mut transaction,
+                            )?;
+                            db.update_package(&old_package, new_package.clone())?;
+                            commit_transaction(transaction);
+                            Ok(new_package)
                         };
-                        let new_package = loading_animation(
-                            format!("Updating {}.. .", old_package.repo.name),
-                            task,
-                        )?;
-                        db.update_package(&old_package, new_package)?;
+                        let result = update();
+                        statics
+                            .history
+                            .record(&history_entry.with_outcome(&result))?;
+                        let new_package = result?;
+                        keep_dist(&new_package, &dist_path, statics);
+                        run_post_hook(HookKind::PostUpdate, &new_package);
                         Ok(())
                     }
                 },
                 None => Err(NoValidDistError.into()),
             }
         }
-        None => Err(NoInstalledPackageError.into()),
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+pub async fn rollback_package(
+    name: &str,
+    db: &PackageDatabase,
+    statics: &Statics,
+) -> Result<(), SengetErrors> {
+    let package = match db.find_package(name)? {
+        Some(package) => migrate_package(
+            package,
+            &statics.startmenu_folders.appdata,
+            &statics.shims_folder_path,
+            db,
+        )?,
+        None => return Err(no_installed_package_error(name, db)),
+    };
+    let previous_version = match package.previous_versions.last() {
+        Some(previous_version) => previous_version.clone(),
+        None => return Err(NoPreviousVersionError.into()),
+    };
+    let previous_dist_type = previous_version.install_info.dist_type.clone();
+    let mut dist = match previous_version.install_info.source.as_ref() {
+        Some(source) => Dist::from_source(
+            &package.repo.name,
+            &previous_version.version,
+            previous_dist_type,
+            source,
+        ),
+        // Versions installed before their distributable was recorded
+        None => match package
+            .get_dist(
+                &previous_version.version,
+                &Some(previous_dist_type),
+                &statics.client,
+                &statics.version_regex,
+            )
+            .await?
+        {
+            Some(dist) => dist,
+            None => return Err(NoValidDistError.into()),
+        },
+    };
+    let dist_path = match package.kept_previous_dist_path(&statics.kept_dists_folder_path) {
+        Some(kept_dist_path) => kept_dist_path,
+        None => {
+            dist.download(&statics.client, &statics.dists_folder_path)
+                .await?
+        }
+    };
+    if let Dist::Exe(exe_dist) = dist {
+        dist = exe_dist.check_if_is_actually_installer(&dist_path)?;
     }
+    println!(
+        "Rolling back {} from {} --> {}",
+        package.repo.name, package.version, previous_version.version
+    );
+    let source = dist.source(&dist_path)?;
+    let history_entry = HistoryEntry::new(
+        HistoryAction::Rollback,
+        &package.repo.name,
+        Some(&package.version),
+    )
+    .with_dist(&dist, &source);
+    let rollback = || -> Result<Package, SengetErrors> {
+        let persist_store = PersistStore::new(&statics.persist_folder_path, &package.repo.name);
+        persist_store.save(&package.install_info)?;
+        let task = || {
+            package.install_previous_version(
+                dist,
+                &dist_path,
+                &statics.packages_folder_path,
+                &statics.startmenu_folders,
+                &statics.user_uninstall_reg_key,
+                &statics.machine_uninstall_reg_key,
+            )
+        };
+        let (mut rolled_back_package, mut transaction) =
+            loading_animation(format!("Rolling back {}.. .", package.repo.name), task)?;
+        persist_store.link_into(&mut rolled_back_package.install_info)?;
+        rolled_back_package.install_info.source = Some(source);
+        rolled_back_package.updated_at = Some(current_timestamp());
+        rolled_back_package.refresh_size();
+        rolled_back_package.replace_shims(
+            Some(&package),
+            &statics.shims_folder_path,
+            &mut transaction,
+        )?;
+        db.update_package(&package, rolled_back_package.clone())?;
+        commit_transaction(transaction);
+        Ok(rolled_back_package)
+    };
+    let result = rollback();
+    statics
+        .history
+        .record(&history_entry.with_outcome(&result))?;
+    let rolled_back_package = result?;
+    keep_dist(&rolled_back_package, &dist_path, statics);
+    success_println_pretty!(
+        "Rolled back {} to {}",
+        package.repo.name,
+        previous_version.version
+    );
+    Ok(())
 }
 
-pub fn list_packages(db: &PackageDatabase) -> Result<(), SengetErrors> {
+pub fn move_package(
+    name: &str,
+    new_location: &Path,
+    startmenu_appdata_folder: &Path,
+    shims_folder: &Path,
+    history: &History,
+    db: &PackageDatabase,
+) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) => {
+            let relocate = || -> Result<(), SengetErrors> {
+                let task =
+                    || package.relocate(new_location, startmenu_appdata_folder, shims_folder);
+                let (moved_package, transaction) =
+                    loading_animation(format!("Moving {}.. .", package.repo.name), task)?;
+                db.update_package(&package, moved_package)?;
+                commit_transaction(transaction);
+                Ok(())
+            };
+            let result = relocate();
+            let history_entry = HistoryEntry::new(
+                HistoryAction::Move,
+                &package.repo.name,
+                Some(&package.version),
+            )
+            .with_outcome(&result);
+            history.record(&history_entry)?;
+            result?;
+            success_println_pretty!(
+                "Moved {} to {}",
+                package.repo.name,
+                new_location.path_str()?
+            );
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+pub fn rebuild_shims(shims_folder: &Path, db: &PackageDatabase) -> Result<(), SengetErrors> {
     let packages = db.fetch_all_packages()?;
-    let rows = packages
-        .iter()
-        .map(|p| {
-            let path = p
-                .install_info
-                .installation_folder
-                .as_ref()
-                .map(|p| p.path_str().unwrap_or_default())
-                .unwrap_or_default();
-            vec![p.repo.name.clone(), p.version.clone(), path.clone()]
+    let task = || shim::rebuild_shims(&packages, shims_folder);
+    let created = loading_animation("Rebuilding shims.. .".to_owned(), task)?;
+    success_println_pretty!("Rebuilt {} shims in {}", created, shims_folder.path_str()?);
+    Ok(())
+}
+
+pub fn show_history(
+    name: Option<&str>,
+    since: Option<&str>,
+    history: &History,
+) -> Result<(), SengetErrors> {
+    let since = since
+        .map(|s| parse_since(s, current_timestamp()))
+        .transpose()?;
+    let entries = history.entries(name, since)?;
+    if entries.is_empty() {
+        println!("No history found");
+        return Ok(());
+    }
+    let rows = entries
+        .into_iter()
+        .map(|e| {
+            vec![
+                format_timestamp(e.timestamp),
+                e.action.to_string(),
+                e.package,
+                e.from_version.unwrap_or_default(),
+                e.to_version.unwrap_or_default(),
+                e.dist_type.map(|d| format!("{:?}", d)).unwrap_or_default(),
+                e.outcome.to_string(),
+            ]
         })
         .collect();
-    let column_headers = vec![
-        "Name".to_owned(),
-        "Version".to_owned(),
-        "Installation Folder".to_owned(),
+    let column_headers = [
+        "Time (UTC)",
+        "Action",
+        "Name",
+        "From",
+        "To",
+        "Type",
+        "Outcome",
+    ]
+    .map(|h| h.to_owned())
+    .to_vec();
+    println!("{}", generate_table_string(&column_headers, &rows));
+    Ok(())
+}
+
+pub fn restore_database(db: &PackageDatabase) -> Result<(), SengetErrors> {
+    db.restore_backup()?;
+    success_println_pretty!("Restored the package database from its backup");
+    Ok(())
+}
+
+pub fn verify_packages(name: Option<&str>, db: &PackageDatabase) -> Result<(), SengetErrors> {
+    let packages = match name {
+        Some(name) => match db.find_package(name)? {
+            Some(package) => vec![package],
+            None => return Err(no_installed_package_error(name, db)),
+        },
+        None => db.fetch_all_packages()?,
+    };
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for package in packages.iter() {
+        let (manifest, folder) = match (
+            package.install_info.manifest.as_ref(),
+            package.install_info.installation_folder.as_ref(),
+        ) {
+            (Some(manifest), Some(folder)) => (manifest, folder),
+            _ => {
+                if name.is_some() {
+                    println!(
+                        "No file manifest recorded for {}, only exe and zip packages installed by this version of senget have one.",
+                        package.repo.name
+                    );
+                }
+                continue;
+            }
+        };
+        let task = || manifest.verify(folder, &package.install_info.persist);
+        let report = loading_animation(format!("Verifying {}.. .", package.repo.name), task)?;
+        for (status, paths) in [
+            ("Missing", report.missing),
+            ("Modified", report.modified),
+            ("Extra", report.extra),
+        ] {
+            for path in paths {
+                rows.push(vec![
+                    package.repo.name.clone(),
+                    status.to_owned(),
+                    path.display().to_string(),
+                ]);
+            }
+        }
+    }
+    match rows.is_empty() {
+        true => success_println_pretty!("No problems found."),
+        false => println!(
+            "{}",
+            generate_table_string(
+                &vec!["Name".to_owned(), "Status".to_owned(), "File".to_owned()],
+                &rows
+            )
+        ),
+    }
+    Ok(())
+}
+
+/// Reports what's out of sync between the package database and the system, fix also repairs
+/// what it can
+pub fn diagnose(fix: bool, db: &PackageDatabase, statics: &Statics) -> Result<(), SengetErrors> {
+    let task = || Doctor::new(fix, db, statics).examine();
+    let problems = loading_animation("Examining packages.. .".to_owned(), task)?;
+    if problems.is_empty() {
+        success_println_pretty!("No problems found.");
+        return Ok(());
+    }
+    let rows = problems
+        .iter()
+        .map(|p| vec![p.name.clone(), p.description.clone(), p.status.to_string()])
+        .collect();
+    println!(
+        "{}",
+        generate_table_string(
+            &vec!["Name".to_owned(), "Problem".to_owned(), "Status".to_owned()],
+            &rows
+        )
+    );
+    if problems.iter().any(|p| p.status == ProblemStatus::Fixable) {
+        println!("Run \"senget doctor --fix\" to repair the fixable ones");
+    }
+    Ok(())
+}
+
+pub fn set_hook(
+    name: &str,
+    kind: HookKind,
+    command: Option<&str>,
+    db: &PackageDatabase,
+) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) => {
+            let mut updated_package = package.clone();
+            updated_package.hooks.set(kind, command.map(String::from));
+            db.update_package(&package, updated_package)?;
+            match command {
+                Some(_) => {
+                    success_println_pretty!("Set the {} hook of {}", kind, package.repo.name)
+                }
+                None => {
+                    success_println_pretty!("Removed the {} hook of {}", kind, package.repo.name)
+                }
+            }
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+pub fn pin_package(
+    name: &str,
+    constraint: Option<&str>,
+    db: &PackageDatabase,
+) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) => {
+            let pin = Pin::new(constraint)?;
+            let mut updated_package = package.clone();
+            updated_package.pin = Some(pin.clone());
+            db.update_package(&package, updated_package)?;
+            success_println_pretty!("Pinned {} to {}", package.repo.name, pin);
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+pub fn unpin_package(name: &str, db: &PackageDatabase) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) if package.pin.is_some() => {
+            let mut updated_package = package.clone();
+            updated_package.pin = None;
+            db.update_package(&package, updated_package)?;
+            success_println_pretty!("Unpinned {}", package.repo.name);
+            Ok(())
+        }
+        Some(_) => Err(PackageNotPinnedError.into()),
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+fn shortcut_folder(desktop: bool, statics: &Statics) -> &Path {
+    match desktop {
+        true => &statics.desktop_folder_path,
+        false => &statics.startmenu_folders.appdata,
+    }
+}
+
+pub fn add_shortcut(
+    name: &str,
+    desktop: bool,
+    label: Option<&str>,
+    arguments: Option<&str>,
+    db: &PackageDatabase,
+    statics: &Statics,
+) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) => {
+            let executable_path = match package.install_info.executable_path.as_ref() {
+                Some(ep) => ep,
+                None => return Err(NoExecutableError.into()),
+            };
+            let shortcut = ShortcutFile::new(
+                label.unwrap_or(&package.repo.name),
+                shortcut_folder(desktop, statics),
+                arguments.map(String::from),
+            );
+            shortcut.create(executable_path)?;
+            let mut shortcuts = package.shortcut_files(&statics.startmenu_folders.appdata);
+            shortcuts.retain(|s| s.path != shortcut.path);
+            shortcuts.push(shortcut.clone());
+            let mut updated_package = package.clone();
+            updated_package.install_info.shortcuts = shortcuts;
+            db.update_package(&package, updated_package)?;
+            success_println_pretty!("Created {}", shortcut.path.path_str()?);
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+pub fn remove_shortcut(
+    name: &str,
+    desktop: bool,
+    label: Option<&str>,
+    db: &PackageDatabase,
+    statics: &Statics,
+) -> Result<(), SengetErrors> {
+    match db.find_package(name)? {
+        Some(package) => {
+            let startmenu_appdata_folder = &statics.startmenu_folders.appdata;
+            let shortcut_path = ShortcutFile::new(
+                label.unwrap_or(&package.repo.name),
+                shortcut_folder(desktop, statics),
+                None,
+            )
+            .path;
+            let mut shortcuts = package.shortcut_files(startmenu_appdata_folder);
+            match shortcuts.iter().position(|s| s.path == shortcut_path) {
+                Some(idx) => shortcuts.remove(idx).remove()?,
+                None => return Err(ShortcutNotFoundError.into()),
+            }
+            let mut updated_package = package.clone();
+            updated_package.install_info.shortcuts = shortcuts;
+            let default_shortcut_path =
+                ShortcutFile::new(&package.repo.name, startmenu_appdata_folder, None).path;
+            if shortcut_path == default_shortcut_path {
+                // So updates don't create it again
+                updated_package.install_info.create_shortcut_file = false;
+            }
+            db.update_package(&package, updated_package)?;
+            success_println_pretty!("Removed {}", shortcut_path.path_str()?);
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
+    }
+}
+
+/// Lists the shortcut files senget made for the package along with the ones its installer or
+/// the user made that point into its installation folder
+pub fn list_shortcuts(
+    name: &str,
+    db: &PackageDatabase,
+    statics: &Statics,
+) -> Result<(), SengetErrors> {
+    let package = match db.find_package(name)? {
+        Some(package) => package,
+        None => return Err(no_installed_package_error(name, db)),
+    };
+    let recorded_paths = package
+        .shortcut_files(&statics.startmenu_folders.appdata)
+        .into_iter()
+        .map(|s| s.path)
+        .collect::<Vec<PathBuf>>();
+    let task = || -> Result<Vec<PathBuf>, io::Error> {
+        let mut shortcut_paths = recorded_paths.clone();
+        let installation_folder = match package.install_info.installation_folder.as_ref() {
+            Some(folder) => folder,
+            None => return Ok(shortcut_paths),
+        };
+        for folder in [
+            &statics.startmenu_folders.appdata,
+            &statics.startmenu_folders.programdata,
+            &statics.desktop_folder_path,
+        ] {
+            for path in find_shortcut_files(folder)? {
+                let points_into_package =
+                    read_shortcut(&path).is_some_and(|s| s.target.starts_with(installation_folder));
+                if points_into_package && !shortcut_paths.contains(&path) {
+                    shortcut_paths.push(path);
+                }
+            }
+        }
+        Ok(shortcut_paths)
+    };
+    let shortcut_paths = loading_animation("Finding shortcut files.. .".to_owned(), task)?;
+    let rows = shortcut_paths
+        .iter()
+        .filter(|p| p.is_file())
+        .map(|path| {
+            let shortcut = read_shortcut(path);
+            vec![
+                path.display().to_string(),
+                shortcut
+                    .as_ref()
+                    .map(|s| s.target.display().to_string())
+                    .unwrap_or_default(),
+                shortcut.and_then(|s| s.arguments).unwrap_or_default(),
+                match recorded_paths.contains(path) {
+                    true => "Yes".to_owned(),
+                    false => "No".to_owned(),
+                },
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+    match rows.is_empty() {
+        true => println!("No shortcut files found for {}", package.repo.name),
+        false => {
+            let column_headers = vec![
+                "Shortcut File".to_owned(),
+                "Target".to_owned(),
+                "Arguments".to_owned(),
+                "Made By Senget".to_owned(),
+            ];
+            println!("{}", generate_table_string(&column_headers, &rows));
+        }
+    }
+    Ok(())
+}
+
+pub fn which_command(command: &str, db: &PackageDatabase) -> Result<(), SengetErrors> {
+    let command_lower = command.to_lowercase();
+    for package in db.fetch_all_packages()? {
+        let binary = package
+            .install_info
+            .exposed_binaries()
+            .into_iter()
+            .find(|(name, _)| name.to_lowercase() == command_lower);
+        if let Some((name, path)) = binary {
+            println!(
+                "{} is provided by {} {}\n{}",
+                name,
+                package.repo.name,
+                package.version,
+                path.display()
+            );
+            return Ok(());
+        }
+    }
+    Err(NoPackageProvidesCommandError.into())
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListColumn {
+    Name,
+    Version,
+    Type,
+    Folder,
+    Pin,
+    Size,
+    Installed,
+    Updated,
+    Asset,
+    Url,
+    Sha256,
+}
+
+impl ListColumn {
+    const DEFAULT: [ListColumn; 4] = [
+        ListColumn::Name,
+        ListColumn::Version,
+        ListColumn::Folder,
+        ListColumn::Pin,
+    ];
+    const LONG: [ListColumn; 9] = [
+        ListColumn::Name,
+        ListColumn::Version,
+        ListColumn::Type,
+        ListColumn::Size,
+        ListColumn::Installed,
+        ListColumn::Updated,
+        ListColumn::Folder,
+        ListColumn::Pin,
+        ListColumn::Asset,
     ];
+
+    fn header(self) -> &'static str {
+        match self {
+            ListColumn::Name => "Name",
+            ListColumn::Version => "Version",
+            ListColumn::Type => "Type",
+            ListColumn::Folder => "Installation Folder",
+            ListColumn::Pin => "Pinned To",
+            ListColumn::Size => "Size",
+            ListColumn::Installed => "Installed At (UTC)",
+            ListColumn::Updated => "Updated At (UTC)",
+            ListColumn::Asset => "Source Asset",
+            ListColumn::Url => "Asset URL",
+            ListColumn::Sha256 => "Sha256",
+        }
+    }
+
+    fn cell(self, package: &Package) -> String {
+        let source = package.install_info.source.as_ref();
+        match self {
+            ListColumn::Name => Some(package.repo.name.clone()),
+            ListColumn::Version => Some(package.version.clone()),
+            ListColumn::Type => Some(format!("{:?}", package.install_info.dist_type)),
+            ListColumn::Folder => Some(package.installation_folder_str()),
+            ListColumn::Pin => package.pin.as_ref().map(|p| p.to_string()),
+            ListColumn::Size => package.size.map(format_size),
+            ListColumn::Installed => package.installed_at.map(format_timestamp),
+            ListColumn::Updated => package.updated_at.map(format_timestamp),
+            ListColumn::Asset => source.map(|s| s.file_title.clone()),
+            ListColumn::Url => source.map(|s| s.download_url.clone()),
+            ListColumn::Sha256 => source.and_then(|s| s.sha256.clone()),
+        }
+        .unwrap_or_default()
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSort {
+    Name,
+    /// Largest first
+    Size,
+    /// Most recently installed or updated first
+    Date,
+}
+
+/// Packages are listed in the order they were installed unless a sort is given
+pub fn list_packages(
+    long: bool,
+    sort: Option<ListSort>,
+    columns: Option<Vec<ListColumn>>,
+    db: &PackageDatabase,
+) -> Result<(), SengetErrors> {
+    let mut packages = db.fetch_all_packages()?;
+    if packages.is_empty() {
+        println!("No packages installed");
+        return Ok(());
+    }
+    match sort {
+        Some(ListSort::Name) => packages.sort_by_key(|p| p.repo.name.to_lowercase()),
+        Some(ListSort::Size) => packages.sort_by_key(|p| Reverse(p.size)),
+        Some(ListSort::Date) => packages.sort_by_key(|p| Reverse(p.updated_at.or(p.installed_at))),
+        None => {}
+    }
+    let columns = match (columns, long) {
+        (Some(columns), _) => columns,
+        (None, true) => ListColumn::LONG.to_vec(),
+        (None, false) => ListColumn::DEFAULT.to_vec(),
+    };
+    let rows = packages
+        .iter()
+        .map(|p| columns.iter().map(|c| c.cell(p)).collect())
+        .collect();
+    let column_headers = columns.iter().map(|c| c.header().to_owned()).collect();
     println!("{}", generate_table_string(&column_headers, &rows));
     Ok(())
 }
@@ -390,10 +1562,9 @@ pub fn generate_table_string(column_headers: &Vec<String>, rows: &Vec<Vec<String
     let max_length_per_column = (0..number_of_columns)
         .map(|column_idx| {
             (0..number_of_rows)
-                .map(|row_idx: usize| &rows[row_idx][column_idx])
-                .max_by_key(|item| item.len())
-                .unwrap()
-                .len()
+                .map(|row_idx: usize| rows[row_idx][column_idx].len())
+                .max()
+                .unwrap_or_default()
         })
         .collect::<Vec<usize>>();
     // Update the obtained max lengths if any of the ones in the  column headers is longer
@@ -403,7 +1574,7 @@ pub fn generate_table_string(column_headers: &Vec<String>, rows: &Vec<Vec<String
         .map(|(str, max_len)| str.len().max(*max_len))
         .collect::<Vec<usize>>();
     // Format a row of data
-    let last_idx = number_of_columns - 1;
+    let last_idx = number_of_columns.saturating_sub(1);
     let format_row = |row: &Vec<String>| {
         row.iter()
             .enumerate()
@@ -452,7 +1623,10 @@ pub fn export_packages(export_file_path: &Path, db: &PackageDatabase) -> Result<
             });
     let json_string = serde_json::to_string_pretty(&exported_packages)?;
     File::create(export_file_path)?.write_all(json_string.as_bytes())?;
-    Ok(success_println_pretty!("Exported at {}", export_file_path.path_str()?))
+    Ok(success_println_pretty!(
+        "Exported at {}",
+        export_file_path.path_str()?
+    ))
 }
 
 pub async fn import_packages(
@@ -468,16 +1642,22 @@ pub async fn import_packages(
     let packages_str = fs::read_to_string(export_file_path)?;
     let packages: Vec<ExportedPackage> = serde_json::from_str(&packages_str)?;
     for p in packages {
-        let version = if ignore_versions {
-            "latest"
-        } else {
-            &p.version
+        let constraint_string = p.constraint.as_ref().map(|c| c.to_string());
+        let version = match (ignore_versions, constraint_string.as_deref()) {
+            (true, Some(constraint)) => constraint,
+            (true, None) => "latest",
+            (false, _) => &p.version,
         };
         if let Err(err) = install_package(
             &p.full_name,
             version,
             &Some(p.preferred_dist_type),
-            p.create_shortcut_file,
+            &InstallOptions {
+                create_shortcut_file: p.create_shortcut_file,
+                constraint: p.constraint.clone(),
+                ..Default::default()
+            },
+            false,
             db,
             statics,
         )
@@ -487,6 +1667,7 @@ pub async fn import_packages(
                 SengetErrors::PackageAlreadyInstalledError(_) => continue,
                 _ => errored_packages.push(vec![
                     p.full_name,
+                    "Failed".to_owned(),
                     format!("{:?}", check_for_other_errors(err)),
                 ]),
             }
@@ -497,7 +1678,7 @@ pub async fn import_packages(
         false => eprintln_pretty!(
             "Errors encountered importing the following packages:{}",
             generate_table_string(
-                &vec!["Name".to_owned(), "Error".to_owned()],
+                &vec!["Name".to_owned(), "Status".to_owned(), "Error".to_owned()],
                 &errored_packages
             )
         ),
@@ -511,25 +1692,173 @@ pub fn run_package(
     args: &Vec<&String>,
     db: &PackageDatabase,
 ) -> Result<(), SengetErrors> {
+    let (name, version) = split_name_and_version(name);
     match db.find_package(name)? {
-        Some(p) => match &p.install_info.executable_path {
-            Some(ep) => {
-                if !ep.is_file() {
-                    return Err(NoExecutableError.into());
-                }
-                println!("Starting {}.. .", p.repo.name);
-                let mut command = Command::new(ep);
-                command.args(args);
-                if no_wait {
-                    command.spawn()?;
-                } else {
-                    command.status()?;
-                };
-                Ok(())
+        Some(p) => {
+            let ep = run_executable_path(&p, version)?;
+            if !ep.is_file() {
+                return Err(NoExecutableError.into());
             }
-            None => Err(NoExecutableError.into()),
-        },
-        None => Err(NoInstalledPackageError.into()),
+            println!("Starting {}.. .", p.repo.name);
+            let mut command = Command::new(ep);
+            command.args(args);
+            if no_wait {
+                command.spawn()?;
+            } else {
+                command.status()?;
+            };
+            Ok(())
+        }
+        None => Err(no_installed_package_error(name, db)),
     }
 }
 
+/// The version can be passed with the name e.g., foo@1.2.0 to run a version installed side by
+/// side
+fn split_name_and_version(name: &str) -> (&str, Option<&str>) {
+    match name.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (name, None),
+    }
+}
+
+/// Executable of the active version, or of the given version if it's installed side by side
+fn run_executable_path<'a>(
+    package: &'a Package,
+    version: Option<&str>,
+) -> Result<&'a Path, SengetErrors> {
+    version
+        .map_or(Some(&package.install_info), |v| {
+            package.version_install_info(v)
+        })
+        .ok_or(NoInstalledVersionError)?
+        .executable_path
+        .as_deref()
+        .ok_or(NoExecutableError.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::{
+        dist::InstallInfo, package::InstalledVersion, utils::create_test_folder,
+    };
+
+    #[test]
+    fn splits_the_version_off_the_name_to_run() {
+        assert_eq!(split_name_and_version("senpwai"), ("senpwai", None));
+        assert_eq!(
+            split_name_and_version("senpwai@2.1.0"),
+            ("senpwai", Some("2.1.0"))
+        );
+        assert_eq!(
+            split_name_and_version("SenZmaKi/Senpwai@2.1.0"),
+            ("SenZmaKi/Senpwai", Some("2.1.0"))
+        );
+    }
+
+    #[test]
+    fn generates_a_table_without_rows() {
+        let column_headers = vec!["Name".to_owned(), "Version".to_owned()];
+        let table = generate_table_string(&column_headers, &Vec::new());
+        assert_eq!(table, "Name    Version\n---------------\n");
+    }
+
+    #[test]
+    fn runs_the_executable_of_the_given_version() {
+        let active_folder = Path::new("C:\\senget\\packages\\Senpwai\\2.1.1");
+        let mut package = Package::test_fixture("SenZmaKi/Senpwai", "2.1.1", active_folder);
+        package.install_info.executable_path = Some(active_folder.join("Senpwai.exe"));
+        let other_folder = Path::new("C:\\senget\\packages\\Senpwai\\2.1.0");
+        let mut other_install_info = InstallInfo::test_fixture(other_folder);
+        other_install_info.executable_path = Some(other_folder.join("Senpwai.exe"));
+        package.other_versions.push(InstalledVersion {
+            version: "2.1.0".to_owned(),
+            install_info: other_install_info,
+        });
+
+        let executable_path = |version| run_executable_path(&package, version).ok();
+        assert_eq!(
+            executable_path(None),
+            Some(active_folder.join("Senpwai.exe").as_path())
+        );
+        assert_eq!(
+            executable_path(Some("2.1.1")),
+            Some(active_folder.join("Senpwai.exe").as_path())
+        );
+        assert_eq!(
+            executable_path(Some("2.1.0")),
+            Some(other_folder.join("Senpwai.exe").as_path())
+        );
+        assert!(matches!(
+            run_executable_path(&package, Some("1.0.0")),
+            Err(SengetErrors::NoInstalledVersionError(_))
+        ));
+        package.install_info.executable_path = None;
+        assert!(matches!(
+            run_executable_path(&package, None),
+            Err(SengetErrors::NoExecutableError(_))
+        ));
+    }
+
+    #[test]
+    fn clears_downloaded_distributables_but_not_kept_ones() {
+        let test_folder = create_test_folder("clear-cache");
+        let statics = Statics::test_fixture(&test_folder);
+        let dists_folder = &statics.dists_folder_path;
+        fs::write(dists_folder.join("Senpwai-2.1.1.zip"), "zip").unwrap();
+        let package = Package::test_fixture("SenZmaKi/Senpwai", "2.1.0", &test_folder);
+        fs::write(dists_folder.join("Senpwai-2.1.0.zip"), "zip").unwrap();
+        keep_dist(&package, &dists_folder.join("Senpwai-2.1.0.zip"), &statics);
+        clear_cached_distributables(dists_folder).ok().unwrap();
+        assert!(dists_folder.folder_items().unwrap().is_empty());
+        assert!(statics
+            .kept_dists_folder_path
+            .join("Senpwai")
+            .join("2.1.0")
+            .join("Senpwai-2.1.0.zip")
+            .is_file());
+    }
+
+    #[test]
+    fn refuses_to_adopt_folders_senget_already_manages() {
+        let test_folder = create_test_folder("adopt-folder");
+        let packages_folder = test_folder.join("packages");
+        let installed = packages_folder.join("Senpwai").join("2.1.1");
+        let location = test_folder.join("Apps").join("Senget");
+        let unpacked = test_folder.join("Downloads").join("ripgrep-14.1.0");
+        for folder in [&installed, &location, &unpacked] {
+            fs::create_dir_all(folder).unwrap();
+        }
+        let mut located_package =
+            Package::test_fixture("SenZmaKi/Senget", "1.0.0", &location.join("1.0.0"));
+        located_package.install_info.location = Some(location.clone());
+        let packages = [
+            Package::test_fixture("SenZmaKi/Senpwai", "2.1.1", &installed),
+            located_package,
+        ];
+        let used_folders = [
+            installed.clone(),
+            packages_folder.join("Senpwai"),
+            location.clone(),
+            test_folder.join("Apps"),
+            unpacked.join("..").join("..").join("Apps").join("Senget"),
+            packages_folder.join("ripgrep"),
+        ];
+        for folder in used_folders {
+            assert!(check_adoptable_folder(&folder, &packages, &packages_folder).is_err());
+        }
+        assert!(check_adoptable_folder(&unpacked, &packages, &packages_folder).is_ok());
+    }
+
+    #[test]
+    fn parses_the_adopted_version_like_release_versions() {
+        let version_regex = Repo::generate_version_regex();
+        let folder = Path::new("C:\\Downloads\\ripgrep-14.1.0");
+        let adopted = |version| adopted_version(version, folder, &version_regex).ok();
+        assert_eq!(adopted(None), Some("14.1.0".to_owned()));
+        assert_eq!(adopted(Some("v14.2.0")), Some("14.2.0".to_owned()));
+        assert_eq!(adopted(Some("latest")), None);
+        assert!(adopted_version(None, Path::new("ripgrep"), &version_regex).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//!Parses version constraints and picks the releases they allow
+
+use crate::includes::error::InvalidVersionConstraintError;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    /// A bare version, matches every version starting with it
+    Prefix,
+    Exact,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    /// Allows changes that don't touch the first non zero segment e.g., ^1.4 is >=1.4, <2
+    Caret,
+    /// Allows changes to the last segment up to the minor version e.g., ~2.0 is >=2.0, <2.1
+    Tilde,
+}
+
+struct Comparator {
+    operator: Operator,
+    segments: Vec<u64>,
+}
+
+/// Comma separated comparators e.g., ">=3, <4", "^1.4" or "~2.0". A bare version like "1.2"
+/// matches 1.2.5 but not 1.20
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VersionConstraint(String);
+
+fn parse_segments(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split('.')
+        .map(|s| s.parse().ok())
+        .collect()
+}
+
+/// Missing segments count as 0 so 1.2 and 1.2.0 are the same version
+fn compare_segments(a: &[u64], b: &[u64]) -> Ordering {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Versions that can't be parsed are older than every other version
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (parse_segments(a), parse_segments(b)) {
+        (Some(a), Some(b)) => compare_segments(&a, &b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// The version right after the given number of leading segments e.g., 1.4.2 up to 2 segments
+/// gives 1.5
+fn bump(segments: &[u64], length: usize) -> Vec<u64> {
+    let mut bumped = segments
+        .iter()
+        .take(length.max(1))
+        .copied()
+        .collect::<Vec<u64>>();
+    if let Some(last) = bumped.last_mut() {
+        *last += 1;
+    }
+    bumped
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Option<Comparator> {
+        let text = text.trim();
+        let (operator, version) = [
+            (">=", Operator::GreaterOrEqual),
+            ("<=", Operator::LessOrEqual),
+            (">", Operator::Greater),
+            ("<", Operator::Less),
+            ("=", Operator::Exact),
+            ("^", Operator::Caret),
+            ("~", Operator::Tilde),
+        ]
+        .into_iter()
+        .find_map(|(symbol, operator)| text.strip_prefix(symbol).map(|v| (operator, v)))
+        .unwrap_or((Operator::Prefix, text));
+        let segments = match (operator, version.trim()) {
+            (Operator::Prefix, "*") => Vec::new(),
+            (Operator::Prefix, version) => {
+                parse_segments(version.strip_suffix(".*").unwrap_or(version))?
+            }
+            (_, version) => parse_segments(version)?,
+        };
+        Some(Comparator { operator, segments })
+    }
+
+    fn allows(&self, version: &[u64]) -> bool {
+        let ordering = compare_segments(version, &self.segments);
+        match self.operator {
+            Operator::Prefix => self
+                .segments
+                .iter()
+                .enumerate()
+                .all(|(i, s)| version.get(i).unwrap_or(&0) == s),
+            Operator::Exact => ordering.is_eq(),
+            Operator::Greater => ordering.is_gt(),
+            Operator::GreaterOrEqual => ordering.is_ge(),
+            Operator::Less => ordering.is_lt(),
+            Operator::LessOrEqual => ordering.is_le(),
+            Operator::Caret => {
+                let length = match self.segments.iter().position(|s| *s != 0) {
+                    Some(first_non_zero) => first_non_zero + 1,
+                    None => self.segments.len(),
+                };
+                let upper_bound = bump(&self.segments, length);
+                ordering.is_ge() && compare_segments(version, &upper_bound).is_lt()
+            }
+            Operator::Tilde => {
+                let upper_bound = bump(&self.segments, 2);
+                ordering.is_ge() && compare_segments(version, &upper_bound).is_lt()
+            }
+        }
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl VersionConstraint {
+    pub fn parse(text: &str) -> Result<VersionConstraint, InvalidVersionConstraintError> {
+        match text.split(',').all(|c| Comparator::parse(c).is_some()) {
+            true => Ok(VersionConstraint(text.trim().to_owned())),
+            false => Err(InvalidVersionConstraintError),
+        }
+    }
+
+    /// The version argument of install and update is a constraint when it uses any constraint
+    /// syntax, a bare version still means that exact version there
+    pub fn from_version_arg(
+        version: &str,
+    ) -> Result<Option<VersionConstraint>, InvalidVersionConstraintError> {
+        match version.contains(['^', '~', '<', '>', '=', '*', ',']) {
+            true => VersionConstraint::parse(version).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    pub fn allows(&self, version: &str) -> bool {
+        let segments = match parse_segments(version) {
+            Some(segments) => segments,
+            None => return false,
+        };
+        self.0
+            .split(',')
+            .filter_map(Comparator::parse)
+            .all(|c| c.allows(&segments))
+    }
+
+    pub fn newest_allowed<'a>(&self, versions: &'a [String]) -> Option<&'a String> {
+        versions
+            .iter()
+            .filter(|v| self.allows(v))
+            .max_by(|a, b| compare_versions(a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(versions: &[&str]) -> Vec<String> {
+        versions.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_valid_and_rejects_invalid_constraints() {
+        let cases = [
+            ("^1.4", true),
+            ("~2.0", true),
+            ("1.2", true),
+            ("1.*", true),
+            ("*", true),
+            (">=3, <4", true),
+            ("=v1.2.3", true),
+            ("", false),
+            ("^", false),
+            ("1.x", false),
+            (">=3,", false),
+            ("latest", false),
+        ];
+        for (text, is_valid) in cases {
+            assert_eq!(VersionConstraint::parse(text).is_ok(), is_valid, "{}", text);
+        }
+    }
+
+    #[test]
+    fn detects_constraints_in_version_args() {
+        let cases = [
+            ("^1.4", Some("^1.4")),
+            (">=3, <4", Some(">=3, <4")),
+            ("1.*", Some("1.*")),
+            ("1.2.3", None),
+            ("v1.2.3", None),
+            ("latest", None),
+        ];
+        for (version, constraint) in cases {
+            let parsed = VersionConstraint::from_version_arg(version).ok().flatten();
+            assert_eq!(
+                parsed.as_ref().map(|c| c.0.as_str()),
+                constraint,
+                "{}",
+                version
+            );
+        }
+        assert!(VersionConstraint::from_version_arg("^x").is_err());
+    }
+
+    #[test]
+    fn allows_versions_inside_the_constraint() {
+        let cases = [
+            ("^1.4", "1.4.0", true),
+            ("^1.4", "1.9.9", true),
+            ("^1.4", "2.0", false),
+            ("^1.4", "1.3.9", false),
+            ("^0.2", "0.2.5", true),
+            ("^0.2", "0.3.0", false),
+            ("^0.0", "0.0.7", true),
+            ("^0.0", "0.1.0", false),
+            ("~2.0", "2.0.9", true),
+            ("~2.0", "2.1", false),
+            ("~1", "1.9", true),
+            ("~1", "2.0", false),
+            ("1.2", "1.2.5", true),
+            ("1.2", "1.20", false),
+            ("1.*", "1.20.1", true),
+            ("1.*", "2.0", false),
+            ("*", "0.0.1", true),
+            (">=3, <4", "3.5", true),
+            (">=3, <4", "4.0", false),
+            (">1.2", "1.2.0", false),
+            ("<=1.2", "1.2.0", true),
+            ("=1.2", "1.2.0", true),
+            ("=1.2", "1.20", false),
+            ("^1.4", "v1.5", true),
+            ("^1.4", "nightly", false),
+        ];
+        for (constraint, version, is_allowed) in cases {
+            let constraint = VersionConstraint::parse(constraint).ok().unwrap();
+            assert_eq!(
+                constraint.allows(version),
+                is_allowed,
+                "{} {}",
+                constraint,
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn compares_versions_with_zero_padding() {
+        assert!(compare_versions("1.2", "1.2.0").is_eq());
+        assert!(compare_versions("v1.10", "1.9").is_gt());
+        assert!(compare_versions("1.2", "1.20").is_lt());
+        assert!(compare_versions("nightly", "0.0.1").is_lt());
+    }
+
+    #[test]
+    fn picks_the_newest_allowed_version() {
+        let constraint = VersionConstraint::parse("^1.4").ok().unwrap();
+        let available = versions(&["2.0.0", "1.10.0", "1.9.3", "1.3.0"]);
+        assert_eq!(constraint.newest_allowed(&available).unwrap(), "1.10.0");
+        let constraint = VersionConstraint::parse("~0.1").ok().unwrap();
+        assert_eq!(constraint.newest_allowed(&available), None);
+    }
+}
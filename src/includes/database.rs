@@ -1,14 +1,80 @@
 //!Manages the database for installed packages
 
-use crate::includes::{error::SengetErrors, package::Package};
+use crate::includes::{
+    error::{
+        CorruptDatabaseError, NewerDatabaseSchemaError, NoDatabaseBackupError,
+        PackageAlreadyInstalledError, PackageChangedError, SengetErrors,
+    },
+    package::Package,
+    utils::pick_by_name,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
     fs::{self, File},
-    io::Write,
+    io::{self, Write},
     path::{Path, PathBuf},
 };
 
+/// Bumped whenever the stored packages change in a way serde defaults can't cover, along with a
+/// new migration
+const SCHEMA_VERSION: usize = 1;
+
+/// The migration at index n upgrades the packages of schema n to schema n + 1
+const MIGRATIONS: [fn(Vec<Value>) -> Vec<Value>; SCHEMA_VERSION] = [
+    // Schema 0 is the bare list of packages from before the envelope, every field added to it
+    // has a default so the packages load as they are
+    |packages| packages,
+];
+
+#[derive(Serialize)]
+struct DatabaseEnvelope<'a> {
+    schema: usize,
+    packages: &'a [Package],
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredDatabase {
+    Versioned { schema: usize, packages: Vec<Value> },
+    Legacy(Vec<Value>),
+}
+
+impl StoredDatabase {
+    fn parse(database_str: &str) -> Result<(usize, Vec<Value>), CorruptDatabaseError> {
+        match serde_json::from_str(database_str).map_err(CorruptDatabaseError::new)? {
+            StoredDatabase::Versioned { schema, packages } => Ok((schema, packages)),
+            StoredDatabase::Legacy(packages) => Ok((0, packages)),
+        }
+    }
+}
+
+/// Upgrades the packages of an older schema through every migration after it
+fn parse_packages(database_str: &str) -> Result<Vec<Package>, SengetErrors> {
+    let (schema, mut packages) = StoredDatabase::parse(database_str)?;
+    if schema > SCHEMA_VERSION {
+        return Err(NewerDatabaseSchemaError.into());
+    }
+    for migration in &MIGRATIONS[schema..] {
+        packages = migration(packages);
+    }
+    packages
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<Package>, serde_json::Error>>()
+        .map_err(|err| CorruptDatabaseError::new(err).into())
+}
+
 pub struct PackageDatabase {
     db_path: PathBuf,
+    backup_path: PathBuf,
+    lock_path: PathBuf,
+}
+
+/// Held while the database is read, changed and saved so concurrent senget processes can't
+/// overwrite each other's changes, the OS releases it even if senget crashes
+struct DatabaseLock {
+    _lock_file: File,
 }
 
 impl PackageDatabase {
@@ -17,44 +83,138 @@ impl PackageDatabase {
         if !db_folder.is_dir() {
             fs::create_dir(&db_folder)?;
         }
-        let db_path = db_folder.join("packages.json");
-        let pd = PackageDatabase { db_path };
-        if !pd.db_path.is_file() {
-            File::create(&pd.db_path)?;
-            pd.save_packages(Vec::new())?;
+        let pd = PackageDatabase {
+            db_path: db_folder.join("packages.json"),
+            backup_path: db_folder.join("packages.json.bak"),
+            lock_path: db_folder.join("packages.json.lock"),
+        };
+        let _lock = pd.lock()?;
+        match pd.stored_schema()? {
+            None if !pd.db_path.is_file() => pd.save_packages(&[])?,
+            // Saved right away so an older database is only migrated once, the backup keeps
+            // it as it was
+            Some(schema) if schema < SCHEMA_VERSION => {
+                let packages = pd.fetch_all_packages()?;
+                fs::copy(&pd.db_path, &pd.backup_path)?;
+                pd.save_packages(&packages)?;
+            }
+            _ => {}
         }
         Ok(pd)
     }
 
+    /// None if there's no database or it's corrupted
+    fn stored_schema(&self) -> Result<Option<usize>, io::Error> {
+        if !self.db_path.is_file() {
+            return Ok(None);
+        }
+        let database_str = fs::read_to_string(&self.db_path)?;
+        Ok(StoredDatabase::parse(&database_str)
+            .ok()
+            .map(|(schema, _)| schema))
+    }
+
+    /// Blocks until no other senget process holds the lock
+    fn lock(&self) -> Result<DatabaseLock, io::Error> {
+        let lock_file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)?;
+        lock_file.lock()?;
+        Ok(DatabaseLock {
+            _lock_file: lock_file,
+        })
+    }
+
+    fn read_packages(path: &Path) -> Result<Vec<Package>, SengetErrors> {
+        let database_str = fs::read_to_string(path)?;
+        parse_packages(&database_str)
+    }
+
     pub fn fetch_all_packages(&self) -> Result<Vec<Package>, SengetErrors> {
-        let packages_str = fs::read_to_string(&self.db_path)?;
-        let packages = serde_json::from_str(&packages_str)?;
-        Ok(packages)
+        PackageDatabase::read_packages(&self.db_path)
+    }
+
+    /// Refuses to overwrite a database a newer version of senget saved
+    fn ensure_schema_not_newer(&self) -> Result<(), SengetErrors> {
+        match self.stored_schema()? {
+            Some(schema) if schema > SCHEMA_VERSION => Err(NewerDatabaseSchemaError.into()),
+            _ => Ok(()),
+        }
     }
 
-    fn save_packages(&self, packages: Vec<Package>) -> Result<(), SengetErrors> {
-        let updated_packages_str = serde_json::to_string_pretty(&packages)?;
-        // Create instead of open with write permissions
-        // incase some weirdo decides to delete the file as the program runs
-        File::create(&self.db_path)?.write_all(updated_packages_str.as_bytes())?;
+    fn save_packages(&self, packages: &[Package]) -> Result<(), SengetErrors> {
+        self.ensure_schema_not_newer()?;
+        let envelope = DatabaseEnvelope {
+            schema: SCHEMA_VERSION,
+            packages,
+        };
+        let updated_packages_str = serde_json::to_string_pretty(&envelope)?;
+        // Write to a temporary file then rename it over the database so that a crash midway
+        // through writing never leaves behind a half written database
+        let tmp_db_path = self.db_path.with_extension("json.tmp");
+        let mut tmp_db_file = File::create(&tmp_db_path)?;
+        tmp_db_file.write_all(updated_packages_str.as_bytes())?;
+        tmp_db_file.sync_all()?;
+        fs::rename(tmp_db_path, &self.db_path)?;
         Ok(())
     }
+
+    /// Reads, changes and saves the packages under the lock, the database before the change is
+    /// kept as the backup
+    fn modify_packages<F>(&self, modify: F) -> Result<(), SengetErrors>
+    where
+        F: FnOnce(&mut Vec<Package>) -> Result<(), SengetErrors>,
+    {
+        let _lock = self.lock()?;
+        let mut packages = self.fetch_all_packages()?;
+        modify(&mut packages)?;
+        fs::copy(&self.db_path, &self.backup_path)?;
+        self.save_packages(&packages)
+    }
+
+    /// Swaps the database with its backup so restoring twice undoes the restore
+    pub fn restore_backup(&self) -> Result<(), SengetErrors> {
+        let _lock = self.lock()?;
+        if !self.backup_path.is_file() {
+            return Err(NoDatabaseBackupError.into());
+        }
+        let backup_packages = PackageDatabase::read_packages(&self.backup_path)?;
+        self.ensure_schema_not_newer()?;
+        if self.db_path.is_file() {
+            fs::copy(&self.db_path, &self.backup_path)?;
+        }
+        self.save_packages(&backup_packages)
+    }
     pub fn find_package(&self, name: &str) -> Result<Option<Package>, SengetErrors> {
-        let name_lower = name.to_lowercase();
         let packages = self.fetch_all_packages()?;
-        let result = packages.into_iter().find(|p| {
-            p.repo.name.to_lowercase() == name_lower
-                || p.repo.full_name.to_lowercase() == name_lower
-        });
-        Ok(result)
+        pick_by_name(name, packages, |p| (&p.repo.name, &p.repo.full_name))
     }
-    fn find_package_index(&self, package: &Package, packages: &[Package]) -> Option<usize> {
-        packages.iter().position(|p| p == package)
+    /// The package is missing if another senget process changed it after it was fetched
+    fn find_package_index(
+        &self,
+        package: &Package,
+        packages: &[Package],
+    ) -> Result<usize, PackageChangedError> {
+        packages
+            .iter()
+            .position(|p| p == package)
+            .ok_or(PackageChangedError)
     }
     pub fn add_package(&self, package: Package) -> Result<(), SengetErrors> {
-        let mut packages = self.fetch_all_packages()?;
-        packages.push(package);
-        self.save_packages(packages)
+        self.modify_packages(|packages| {
+            // Checked under the lock since another senget process may have installed it after
+            // it was looked up
+            if packages
+                .iter()
+                .any(|p| p.repo.full_name == package.repo.full_name)
+            {
+                return Err(PackageAlreadyInstalledError.into());
+            }
+            packages.push(package);
+            Ok(())
+        })
     }
 
     pub fn update_package(
@@ -62,16 +222,155 @@ impl PackageDatabase {
         old_package: &Package,
         updated_package: Package,
     ) -> Result<(), SengetErrors> {
-        let mut packages = self.fetch_all_packages()?;
-        let index = self.find_package_index(old_package, &packages).unwrap();
-        packages[index] = updated_package;
-        self.save_packages(packages)
+        self.modify_packages(|packages| {
+            let index = self.find_package_index(old_package, packages)?;
+            packages[index] = updated_package;
+            Ok(())
+        })
     }
 
     pub fn remove_package(&self, package: &Package) -> Result<(), SengetErrors> {
-        let mut packages = self.fetch_all_packages()?;
-        let index = self.find_package_index(package, &packages).unwrap();
-        packages.remove(index);
-        self.save_packages(packages)
+        self.modify_packages(|packages| {
+            let index = self.find_package_index(package, packages)?;
+            packages.remove(index);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+
+    fn package(full_name: &str, version: &str) -> Package {
+        Package::test_fixture(
+            full_name,
+            version,
+            Path::new("C:\\senget\\packages\\package"),
+        )
+    }
+
+    fn full_names(packages: &[Package]) -> Vec<&str> {
+        packages.iter().map(|p| p.repo.full_name.as_str()).collect()
+    }
+
+    #[test]
+    fn adds_updates_and_removes_packages() {
+        let db = PackageDatabase::new(&create_test_folder("database-modify")).unwrap();
+        assert!(db.fetch_all_packages().unwrap().is_empty());
+        let senpwai = package("SenZmaKi/Senpwai", "2.1.0");
+        db.add_package(senpwai.clone()).unwrap();
+        db.add_package(package("SenZmaKi/Senget", "1.0.0")).unwrap();
+        assert!(matches!(
+            db.add_package(package("SenZmaKi/Senpwai", "2.1.1")),
+            Err(SengetErrors::PackageAlreadyInstalledError(_))
+        ));
+
+        let updated_senpwai = package("SenZmaKi/Senpwai", "2.1.1");
+        db.update_package(&senpwai, updated_senpwai.clone())
+            .unwrap();
+        let packages = db.fetch_all_packages().unwrap();
+        assert_eq!(
+            full_names(&packages),
+            ["SenZmaKi/Senpwai", "SenZmaKi/Senget"]
+        );
+        assert_eq!(packages[0].version, "2.1.1");
+        // The package fetched before the update is stale now
+        assert!(matches!(
+            db.remove_package(&senpwai),
+            Err(SengetErrors::PackageChangedError(_))
+        ));
+
+        db.remove_package(&updated_senpwai).unwrap();
+        let packages = db.fetch_all_packages().unwrap();
+        assert_eq!(full_names(&packages), ["SenZmaKi/Senget"]);
+    }
+
+    #[test]
+    fn backs_up_the_database_before_each_change() {
+        let db = PackageDatabase::new(&create_test_folder("database-backup")).unwrap();
+        assert!(matches!(
+            db.restore_backup(),
+            Err(SengetErrors::NoDatabaseBackupError(_))
+        ));
+        db.add_package(package("SenZmaKi/Senpwai", "2.1.0"))
+            .unwrap();
+        assert!(PackageDatabase::read_packages(&db.backup_path)
+            .unwrap()
+            .is_empty());
+        db.add_package(package("SenZmaKi/Senget", "1.0.0")).unwrap();
+        let backup_packages = PackageDatabase::read_packages(&db.backup_path).unwrap();
+        assert_eq!(full_names(&backup_packages), ["SenZmaKi/Senpwai"]);
+        // A failed change leaves both alone
+        assert!(db.add_package(package("SenZmaKi/Senget", "1.0.0")).is_err());
+        let backup_packages = PackageDatabase::read_packages(&db.backup_path).unwrap();
+        assert_eq!(full_names(&backup_packages), ["SenZmaKi/Senpwai"]);
+    }
+
+    #[test]
+    fn restoring_the_backup_twice_undoes_the_restore() {
+        let db = PackageDatabase::new(&create_test_folder("database-restore")).unwrap();
+        db.add_package(package("SenZmaKi/Senpwai", "2.1.0"))
+            .unwrap();
+        db.add_package(package("SenZmaKi/Senget", "1.0.0")).unwrap();
+        db.restore_backup().unwrap();
+        let packages = db.fetch_all_packages().unwrap();
+        assert_eq!(full_names(&packages), ["SenZmaKi/Senpwai"]);
+        db.restore_backup().unwrap();
+        let packages = db.fetch_all_packages().unwrap();
+        assert_eq!(
+            full_names(&packages),
+            ["SenZmaKi/Senpwai", "SenZmaKi/Senget"]
+        );
+    }
+
+    #[test]
+    fn loads_and_migrates_a_legacy_database() {
+        let packages = [package("SenZmaKi/Senpwai", "2.1.0")];
+        let legacy_str = serde_json::to_string(&packages).unwrap();
+        let parsed = parse_packages(&legacy_str).ok().unwrap();
+        assert_eq!(parsed, packages);
+
+        let config_dir = create_test_folder("database-legacy");
+        fs::create_dir_all(config_dir.join("database")).unwrap();
+        let db_path = config_dir.join("database").join("packages.json");
+        fs::write(&db_path, &legacy_str).unwrap();
+        let db = PackageDatabase::new(&config_dir).unwrap();
+        assert_eq!(db.stored_schema().unwrap(), Some(SCHEMA_VERSION));
+        assert_eq!(db.fetch_all_packages().ok().unwrap(), packages);
+        // The backup keeps the database as it was before the migration
+        assert_eq!(fs::read_to_string(&db.backup_path).unwrap(), legacy_str);
+    }
+
+    #[test]
+    fn rejects_a_database_from_a_newer_schema() {
+        let newer_str = format!(r#"{{"schema": {}, "packages": []}}"#, SCHEMA_VERSION + 1);
+        assert!(matches!(
+            parse_packages(&newer_str),
+            Err(SengetErrors::NewerDatabaseSchemaError(_))
+        ));
+        assert!(matches!(
+            parse_packages(r#"{"schema": 1, "packages": [{"version": 1}]}"#),
+            Err(SengetErrors::CorruptDatabaseError(_))
+        ));
+    }
+
+    #[test]
+    fn round_trips_through_the_envelope() {
+        let packages = [
+            package("SenZmaKi/Senpwai", "2.1.0"),
+            package("SenZmaKi/Senget", "1.0.0"),
+        ];
+        let envelope = DatabaseEnvelope {
+            schema: SCHEMA_VERSION,
+            packages: &packages,
+        };
+        let database_str = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            StoredDatabase::parse(&database_str).ok().unwrap().0,
+            SCHEMA_VERSION
+        );
+        assert_eq!(parse_packages(&database_str).ok().unwrap(), packages);
     }
 }
@@ -2,7 +2,6 @@
 
 use clap::ValueEnum;
 use indicatif::{ProgressBar, ProgressStyle};
-use lnk;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
@@ -13,30 +12,88 @@ use std::io::{self, Read};
 use std::{
     collections::HashSet,
     env,
-    path::{Path, PathBuf},
+    os::windows::process::CommandExt,
+    path::{Component, Path, PathBuf},
     process::Command,
 };
 use winreg::{
     enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE},
     RegKey,
 };
-use zip::ZipArchive;
+use zip::{read::ZipFile, ZipArchive};
 
 use crate::includes::package::MSI_EXEC;
 use crate::includes::utils::Cmd;
 use crate::includes::{
-    error::{NoExeFoundInZipError, SengetErrors},
-    senget_manager::env::add_package_folder_to_senget_env_var,
-    utils::{FilenameLower, FolderItems, MoveDirAll, PathStr, Take, DEBUG},
+    constraint::VersionConstraint,
+    error::{
+        AmbiguousExecutableError, BinaryNotFoundError, ExecutableNotFoundError, MaliciousZipError,
+        SengetErrors,
+    },
+    hook::Hooks,
+    manifest::{sha256_file, Manifest},
+    pe::InstallerKind,
+    persist::unlink_persisted,
+    shortcut::{read_shortcut, ShortcutFile},
+    transaction::{generate_staging_folder_path, Transaction},
+    utils::{
+        folder_size, is_interactive, prompt_choice, FilenameLower, FolderItems, MoveDirAll,
+        PathStr, Take, DEBUG, IBYTES_TO_MBS_DIVISOR,
+    },
 };
 
-// Running an msi installer that needs admin access silently is problematic since
-// it'll just exit silently without an error if it fails cause of lack of admin access
-// and there's no way to know that it needs admin access ahead of time
-// const MSI_SILENT_ARG: &str = "/qn";
-const INNO_SILENT_ARG: &str = "/VERYSILENT";
-const NSIS_SILENT_ARG: &str = "/S";
 const STARTMENU_FOLDER_ENDPOINT: &str = "\\Microsoft\\Windows\\Start Menu\\Programs";
+// Limits used to reject zip bombs, they're way above what any sane package distributable needs
+const MAX_ZIP_ENTRIES: usize = 100_000;
+const MAX_ZIP_EXTRACTED_SIZE: u64 = 8 * 1024 * 1024 * 1024;
+// Mask and value of the file type bits in a unix mode that mark a symlink
+const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+const UNIX_SYMLINK_FILE_TYPE: u32 = 0o120000;
+
+/// Command line switches an installer technology understands, {} in install_dir is replaced
+/// with the installation folder
+pub struct InstallerSwitches {
+    pub silent: &'static [&'static str],
+    pub install_dir: Option<&'static str>,
+}
+
+const INNO_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["/VERYSILENT", "/SUPPRESSMSGBOXES", "/NORESTART"],
+    install_dir: Some("/DIR=\"{}\""),
+};
+// NSIS requires /D to be the last argument and the path to be unquoted even if it has spaces
+const NSIS_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["/S"],
+    install_dir: Some("/D={}"),
+};
+// Burn has no standard switch for the installation folder, each bundle names its own variable
+// and the rest silently ignore it
+const WIX_BURN_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["/quiet", "/norestart"],
+    install_dir: None,
+};
+const INSTALLSHIELD_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["/s"],
+    install_dir: Some("/v\"INSTALLDIR=\\\"{}\\\"\""),
+};
+// Squirrel always installs into LOCALAPPDATA
+const SQUIRREL_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["--silent"],
+    install_dir: None,
+};
+// Running an msi installer that needs admin access with /qn is problematic since
+// it'll just exit silently without an error if it fails cause of lack of admin access
+// and there's no way to know that it needs admin access ahead of time, /passive still shows
+// the elevation prompt
+const MSI_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["/passive", "/norestart"],
+    install_dir: Some("INSTALLDIR=\"{}\""),
+};
+// Used when we couldn't tell what the installer was built with, Inno and NSIS are the most common
+const FALLBACK_SWITCHES: InstallerSwitches = InstallerSwitches {
+    silent: &["/VERYSILENT", "/S"],
+    install_dir: None,
+};
 
 pub struct StartmenuFolders {
     pub appdata: PathBuf,
@@ -50,6 +107,26 @@ pub enum DistType {
     Exe,
 }
 
+/// Which distributable to pick when a release has several types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DistPreference {
+    /// The best one available
+    Any,
+    /// Only this type e.g., the one passed with --dist
+    Only(DistType),
+    /// This type if the release has it otherwise the best one available
+    Prefer(DistType),
+}
+
+impl From<Option<DistType>> for DistPreference {
+    fn from(value: Option<DistType>) -> Self {
+        match value {
+            Some(dist_type) => DistPreference::Only(dist_type),
+            None => DistPreference::Any,
+        }
+    }
+}
+
 impl From<clap::builder::Str> for DistType {
     fn from(value: clap::builder::Str) -> Self {
         if value == "installer" {
@@ -62,6 +139,71 @@ impl From<clap::builder::Str> for DistType {
     }
 }
 
+/// User choices that affect how a package is installed, they're saved in the InstallInfo so that
+/// updates are installed the same way
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    pub create_shortcut_file: bool,
+    /// Arguments passed to the installer instead of its silent switches
+    pub installer_args: Option<String>,
+    /// Run the installer with its UI instead of silently
+    pub interactive: bool,
+    /// Folder to install the package's versions into instead of the default one
+    pub location: Option<PathBuf>,
+    /// Executables to expose instead of the detected ones
+    pub binaries: Vec<Binary>,
+    /// Main executable of a zip package instead of the detected one, relative to the
+    /// installation folder
+    pub executable: Option<PathBuf>,
+    /// Files and folders relative to the installation folder that are kept in the persist store
+    pub persist: Vec<PathBuf>,
+    /// Versions updates stay inside of, it's saved on the package rather than the InstallInfo
+    pub constraint: Option<VersionConstraint>,
+    /// Commands to run after installing or updating and before uninstalling, also saved on the
+    /// package
+    pub hooks: Hooks,
+}
+
+fn is_file_inside_folder(relative_path: &Path, folder: &Path) -> bool {
+    relative_path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        && folder.join(relative_path).is_file()
+}
+
+/// An executable of a package that's exposed as a command through a shim
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binary {
+    /// Relative to the installation folder so it survives moving the package
+    pub path: PathBuf,
+    pub alias: Option<String>,
+}
+
+impl Binary {
+    /// Parses the relpath[=alias] format the --bin option takes
+    pub fn parse(value: &str) -> Binary {
+        let (path, alias) = match value.rsplit_once('=') {
+            Some((path, alias)) => (path, Some(alias.to_owned())),
+            None => (value, None),
+        };
+        Binary {
+            path: PathBuf::from(path),
+            alias,
+        }
+    }
+
+    /// Name of the command, defaults to the executable's name without the extension
+    pub fn command_name(&self) -> String {
+        self.alias.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Dist {
     /// Zipped package distributable
@@ -92,19 +234,40 @@ impl Dist {
         }
     }
 
-    fn create_shortcut_file(
-        package_name: &str,
-        executable_path: &Path,
-        appdata_startmenu_folder: &Path,
-    ) -> Result<(), mslnk::MSLinkError> {
-        // For whatever reason mslnk doesn't work with a normal Path struct, only a String
-        let lnk = mslnk::ShellLink::new(executable_path.path_str()?)?;
-        let lnk_path = appdata_startmenu_folder.join(format!("{}.lnk", package_name));
-        if lnk_path.is_file() {
-            fs::remove_file(&lnk_path)?
+    pub fn from_source(
+        name: &str,
+        version: &str,
+        dist_type: DistType,
+        source: &DistSource,
+    ) -> Dist {
+        PackageInfo::new(
+            name.to_owned(),
+            source.download_url.clone(),
+            version.to_owned(),
+            source.file_title.clone(),
+            source.file_size,
+        )
+        .fetch_dist(dist_type)
+    }
+
+    pub fn source(&self, downloaded_dist_path: &Path) -> Result<DistSource, io::Error> {
+        let package_info = self.package_info();
+        Ok(DistSource {
+            file_title: package_info.file_title.clone(),
+            file_size: package_info.file_size,
+            download_url: package_info.download_url.clone(),
+            sha256: Some(sha256_file(downloaded_dist_path)?),
+        })
+    }
+
+    pub fn dist_type(&self) -> DistType {
+        match self {
+            Dist::Exe(_) => DistType::Exe,
+            Dist::Zip(_) => DistType::Zip,
+            Dist::Installer(_) => DistType::Installer,
         }
-        lnk.create_lnk(lnk_path)
     }
+
     fn package_info(&self) -> &PackageInfo {
         match self {
             Dist::Exe(dist) => &dist.package_info,
@@ -113,47 +276,49 @@ impl Dist {
         }
     }
 
+    /// The downloaded distributable is left in place for the caller to keep or delete
     pub fn install(
         &self,
         downloaded_dist_path: &Path,
         packages_folder_path: &Path,
-        create_shortcut_file: bool,
+        install_options: &InstallOptions,
         startmenu_folders: &StartmenuFolders,
         user_uninstall_reg_key: &RegKey,
         machine_uninstall_reg_key: &RegKey,
-    ) -> Result<InstallInfo, SengetErrors> {
-        let install_info = match self {
+    ) -> Result<(InstallInfo, Transaction), SengetErrors> {
+        let mut transaction = Transaction::new();
+        let mut install_info = match self {
             Dist::Exe(dist) => dist.install(
                 downloaded_dist_path,
                 packages_folder_path,
-                create_shortcut_file,
+                install_options,
+                &mut transaction,
             )?,
             Dist::Zip(dist) => dist.install(
                 downloaded_dist_path,
                 packages_folder_path,
-                create_shortcut_file,
+                install_options,
+                &mut transaction,
             )?,
             Dist::Installer(dist) => dist.install(
                 downloaded_dist_path,
-                create_shortcut_file,
+                install_options,
                 startmenu_folders,
                 user_uninstall_reg_key,
                 machine_uninstall_reg_key,
             )?,
         };
-        if !matches!(self, Dist::Installer(_)) && create_shortcut_file {
-            Dist::create_shortcut_file(
-                &self.package_info().name,
-                install_info.executable_path.as_ref().unwrap(),
-                &startmenu_folders.appdata,
-            )?;
-        }
-        if let Some(installation_folder) = install_info.installation_folder.as_ref() {
-            add_package_folder_to_senget_env_var(
-                &installation_folder.path_str().unwrap_or_default(),
-            )?;
+        if let (false, true, Some(ep)) = (
+            matches!(self, Dist::Installer(_)),
+            install_options.create_shortcut_file,
+            install_info.executable_path.clone(),
+        ) {
+            let shortcut =
+                ShortcutFile::new(&self.package_info().name, &startmenu_folders.appdata, None);
+            shortcut.create_in_transaction(&ep, None, &mut transaction)?;
+            install_info.shortcuts.push(shortcut);
         }
-        Ok(install_info)
+        Ok((install_info, transaction))
     }
 
     fn generate_path_from_config(name: &str, config_dir: &Path) -> Result<PathBuf, io::Error> {
@@ -168,9 +333,45 @@ impl Dist {
         Self::generate_path_from_config("distributables", config_dir)
     }
 
+    /// Distributables kept for rolling back live outside the cache so clearing it doesn't drop them
+    pub fn generate_kept_dists_folder_path(config_dir: &Path) -> Result<PathBuf, io::Error> {
+        Self::generate_path_from_config("kept_distributables", config_dir)
+    }
+
     pub fn generate_packages_folder_path(config_dir: &Path) -> Result<PathBuf, io::Error> {
         Self::generate_path_from_config("packages", config_dir)
     }
+
+    /// Folder an exe or zip package is installed into, every version gets its own subfolder in
+    /// the package folder so that multiple versions can be installed side by side
+    fn generate_installation_folder_path(
+        package_info: &PackageInfo,
+        packages_folder_path: &Path,
+        install_options: &InstallOptions,
+    ) -> PathBuf {
+        install_options
+            .location
+            .clone()
+            .unwrap_or_else(|| packages_folder_path.join(&package_info.name))
+            .join(&package_info.version)
+    }
+
+    /// The binaries the user picked with --bin if any, otherwise the detected ones
+    fn resolve_binaries(
+        installation_folder: &Path,
+        install_options: &InstallOptions,
+        detected: Vec<Binary>,
+    ) -> Result<Vec<Binary>, BinaryNotFoundError> {
+        if install_options.binaries.is_empty() {
+            return Ok(detected);
+        }
+        for binary in install_options.binaries.iter() {
+            if !is_file_inside_folder(&binary.path, installation_folder) {
+                return Err(BinaryNotFoundError);
+            }
+        }
+        Ok(install_options.binaries.clone())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -187,7 +388,10 @@ impl PackageInfo {
         match dist_type {
             DistType::Exe => Dist::Exe(ExeDist { package_info: self }),
             DistType::Zip => Dist::Zip(ZipDist { package_info: self }),
-            DistType::Installer => Dist::Installer(InstallerDist { package_info: self }),
+            DistType::Installer => Dist::Installer(InstallerDist {
+                package_info: self,
+                installer_kind: None,
+            }),
         }
     }
     pub fn new(
@@ -255,23 +459,12 @@ impl ExeDist {
         self,
         downloaded_dist_path: &Path,
     ) -> Result<Dist, SengetErrors> {
-        let mut buffer = Vec::new();
-        File::open(downloaded_dist_path)?.read_to_end(&mut buffer)?;
-        let text: String = buffer
-            .into_iter()
-            .filter_map(|byte| {
-                if byte.is_ascii_alphabetic() {
-                    return Some(byte as char);
-                }
-                None
-            })
-            .collect();
-        if text.contains("Inno") || text.contains("Nullsoft") {
+        if let Some(installer_kind) = InstallerKind::detect(downloaded_dist_path)? {
             return Ok(Dist::Installer(InstallerDist {
                 package_info: self.package_info,
+                installer_kind: Some(installer_kind),
             }));
         }
-
         Ok(Dist::Exe(self))
     }
 
@@ -279,26 +472,57 @@ impl ExeDist {
         &self,
         downloaded_dist_path: &Path,
         packages_folder_path: &Path,
-        create_shortcut_file: bool,
-    ) -> Result<InstallInfo, io::Error> {
-        let p_folder_path = packages_folder_path.join(&self.package_info.name);
-        if !p_folder_path.is_dir() {
-            fs::create_dir(&p_folder_path)?;
+        install_options: &InstallOptions,
+        transaction: &mut Transaction,
+    ) -> Result<InstallInfo, SengetErrors> {
+        let p_folder_path = Dist::generate_installation_folder_path(
+            &self.package_info,
+            packages_folder_path,
+            install_options,
+        );
+        let staging_folder = generate_staging_folder_path(&p_folder_path)?;
+        let exe_file_name = format!("{}.exe", self.package_info.name);
+        // Nothing is registered with the transaction yet, so the staging folder is removed by hand
+        // if staging fails
+        let stage = || -> Result<(Vec<Binary>, Manifest), SengetErrors> {
+            fs::copy(downloaded_dist_path, staging_folder.join(&exe_file_name))?;
+            let detected_binaries = vec![Binary {
+                path: PathBuf::from(&exe_file_name),
+                alias: None,
+            }];
+            let binaries =
+                Dist::resolve_binaries(&staging_folder, install_options, detected_binaries)?;
+            let manifest = Manifest::record(&staging_folder)?;
+            Ok((binaries, manifest))
         };
-        let exe_path = p_folder_path.join(format!("{}.exe", self.package_info.name));
-        if DEBUG {
-            fs::copy(downloaded_dist_path, &exe_path)?;
-        } else {
-            fs::rename(downloaded_dist_path, &exe_path)?;
-        }
+        let (binaries, manifest) = match stage() {
+            Ok(staged) => staged,
+            Err(err) => {
+                let _ = fs::remove_dir_all(staging_folder);
+                return Err(err);
+            }
+        };
+        transaction.swap_in_folder(&staging_folder, &p_folder_path)?;
+        let executable_path = Some(p_folder_path.join(exe_file_name));
         let installation_folder = Some(p_folder_path);
-        let executable_path = Some(exe_path);
         let install_info = InstallInfo {
             executable_path,
             installation_folder,
             uninstall_command: None,
             dist_type: DistType::Exe,
-            create_shortcut_file,
+            installer_kind: None,
+            create_shortcut_file: install_options.create_shortcut_file,
+            installer_args: install_options.installer_args.clone(),
+            interactive: install_options.interactive,
+            location: install_options.location.clone(),
+            adopted: false,
+            binaries,
+            custom_binaries: !install_options.binaries.is_empty(),
+            executable: install_options.executable.clone(),
+            persist: install_options.persist.clone(),
+            shortcuts: Vec::new(),
+            source: None,
+            manifest: Some(manifest),
         };
         Ok(install_info)
     }
@@ -324,15 +548,17 @@ impl ZipDist {
         self.package_info.download(dists_folder_path, client).await
     }
 
-    fn find_executable_path(
+    /// Every executable in the folder, the ones named after the package come first followed by
+    /// the ones containing its name then the rest, shallower ones first within each group
+    pub fn rank_executables(
         self_name_lower: &str,
         folder: PathBuf,
-    ) -> Result<Option<PathBuf>, io::Error> {
+    ) -> Result<Vec<PathBuf>, io::Error> {
         let mut queue = VecDeque::new();
         let self_exe_name_lower = format!("{}.exe", self_name_lower);
         queue.push_back(folder);
-        let mut found_exe: Option<PathBuf> = None;
-        // Looks for the executable breadth first
+        let mut executables = Vec::new();
+        // Breadth first so the shallower executables come first
         while let Some(current_folder) = queue.pop_front() {
             let folder_items: Vec<PathBuf> = current_folder
                 .folder_items()?
@@ -340,24 +566,234 @@ impl ZipDist {
                 .map(|item| item.path())
                 .collect();
             for item in folder_items.iter() {
-                let lower_file_name = item.filename_lower();
-                if lower_file_name == self_exe_name_lower {
-                    return Ok(Some(item.clone()));
-                }
-                if found_exe.is_none()
-                    && lower_file_name.ends_with("exe")
-                    && lower_file_name.contains(self_name_lower)
-                {
-                    found_exe = Some(item.clone());
+                if item.is_file() && item.filename_lower().ends_with(".exe") {
+                    executables.push(item.clone());
                 }
             }
-
             folder_items
                 .into_iter()
                 .filter(|f| f.is_dir())
                 .for_each(|f| queue.push_back(f));
         }
-        Ok(found_exe)
+        // Stable so the breadth first order is kept within each rank
+        executables.sort_by_key(|exe| {
+            let lower_file_name = exe.filename_lower();
+            if lower_file_name == self_exe_name_lower {
+                0
+            } else if lower_file_name.contains(self_name_lower) {
+                1
+            } else {
+                2
+            }
+        });
+        Ok(executables)
+    }
+
+    /// The main executable in the folder, the passed or saved one if there's one otherwise the
+    /// user picks when there are several and none of them is named after the package. The picked
+    /// one is saved in executable so updates don't ask again, None means it's a data-only package
+    fn pick_executable(
+        name: &str,
+        folder: &Path,
+        executable: &mut Option<PathBuf>,
+    ) -> Result<Option<PathBuf>, SengetErrors> {
+        ZipDist::pick_executable_with_prompt(name, folder, executable, is_interactive())
+    }
+
+    /// Errors out listing the candidates instead of asking when it can't prompt
+    fn pick_executable_with_prompt(
+        name: &str,
+        folder: &Path,
+        executable: &mut Option<PathBuf>,
+        can_prompt: bool,
+    ) -> Result<Option<PathBuf>, SengetErrors> {
+        if let Some(executable) = executable.as_ref() {
+            if !is_file_inside_folder(executable, folder) {
+                return Err(ExecutableNotFoundError.into());
+            }
+            return Ok(Some(folder.join(executable)));
+        }
+        let name_lower = name.to_lowercase();
+        let candidates = ZipDist::rank_executables(&name_lower, folder.to_owned())?;
+        let is_named_after_package = candidates
+            .first()
+            .is_some_and(|c| c.filename_lower() == format!("{}.exe", name_lower));
+        if candidates.len() < 2 || is_named_after_package {
+            return Ok(candidates.take(0));
+        }
+        let relative_paths = candidates
+            .iter()
+            .map(|c| c.strip_prefix(folder).unwrap_or(c).to_owned())
+            .collect::<Vec<PathBuf>>();
+        let options = relative_paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<String>>();
+        if !can_prompt {
+            return Err(AmbiguousExecutableError {
+                candidates: options,
+            }
+            .into());
+        }
+        let question = format!(
+            "Found multiple executables for {}, which one is its main executable?",
+            name
+        );
+        let choice = prompt_choice(&question, &options)?;
+        *executable = relative_paths.take(choice);
+        Ok(candidates.take(choice))
+    }
+
+    /// Executables in the root or bin folder of the unpacked zip, CLI bundles put all their
+    /// commands in one of them
+    fn detect_binaries(folder: &Path) -> Result<Vec<Binary>, io::Error> {
+        let mut binaries = Vec::new();
+        for subfolder in [Path::new(""), Path::new("bin")] {
+            let current_folder = folder.join(subfolder);
+            if !current_folder.is_dir() {
+                continue;
+            }
+            for item in current_folder.folder_items()? {
+                let path = item.path();
+                if path.is_file() && path.filename_lower().ends_with(".exe") {
+                    binaries.push(Binary {
+                        path: subfolder.join(item.file_name()),
+                        alias: None,
+                    });
+                }
+            }
+        }
+        Ok(binaries)
+    }
+
+    /// Validates every entry in the zip file before extracting anything so a malicious entry
+    /// never touches the disk, then extracts while counting the actual bytes written since the
+    /// sizes in the headers can be forged
+    fn extract(
+        downloaded_dist_path: &Path,
+        installation_folder: &Path,
+    ) -> Result<(), SengetErrors> {
+        ZipDist::extract_with_limits(
+            downloaded_dist_path,
+            installation_folder,
+            MAX_ZIP_ENTRIES,
+            MAX_ZIP_EXTRACTED_SIZE,
+        )
+    }
+
+    fn extract_with_limits(
+        downloaded_dist_path: &Path,
+        installation_folder: &Path,
+        max_entries: usize,
+        max_extracted_size: u64,
+    ) -> Result<(), SengetErrors> {
+        let mut archive = ZipArchive::new(File::open(downloaded_dist_path)?)?;
+        if archive.len() > max_entries {
+            return Err(MaliciousZipError::new(format!(
+                "it has {} entries, the maximum is {}",
+                archive.len(),
+                max_entries
+            ))
+            .into());
+        }
+        let mut relative_paths = Vec::with_capacity(archive.len());
+        let mut declared_size: u64 = 0;
+        for idx in 0..archive.len() {
+            let entry = archive.by_index(idx)?;
+            relative_paths.push(ZipDist::validate_entry(&entry, installation_folder)?);
+            declared_size = declared_size.saturating_add(entry.size());
+        }
+        if declared_size > max_extracted_size {
+            return Err(MaliciousZipError::new(format!(
+                "it unpacks to {} MBs, the maximum is {} MBs",
+                declared_size / IBYTES_TO_MBS_DIVISOR,
+                max_extracted_size / IBYTES_TO_MBS_DIVISOR
+            ))
+            .into());
+        }
+        fs::create_dir_all(installation_folder)?;
+        let mut extracted_size: u64 = 0;
+        for (idx, relative_path) in relative_paths.into_iter().enumerate() {
+            let mut entry = archive.by_index(idx)?;
+            let out_path = installation_folder.join(relative_path);
+            if entry.is_dir() {
+                fs::create_dir_all(&out_path)?;
+                continue;
+            }
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let declared_entry_size = entry.size();
+            let mut out_file = File::create(&out_path)?;
+            // Read one byte past the declared size so we can tell if the entry lied about it
+            let entry_size = io::copy(
+                &mut (&mut entry).take(declared_entry_size + 1),
+                &mut out_file,
+            )?;
+            if entry_size > declared_entry_size {
+                return Err(MaliciousZipError::new(format!(
+                    "\"{}\" unpacks to more than its declared size",
+                    entry.name()
+                ))
+                .into());
+            }
+            extracted_size += entry_size;
+            if extracted_size > max_extracted_size {
+                return Err(MaliciousZipError::new(format!(
+                    "it unpacks to more than {} MBs",
+                    max_extracted_size / IBYTES_TO_MBS_DIVISOR
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_entry(
+        entry: &ZipFile,
+        installation_folder: &Path,
+    ) -> Result<PathBuf, SengetErrors> {
+        let malicious =
+            |reason: &str| MaliciousZipError::new(format!("\"{}\" {}", entry.name(), reason));
+        let relative_path = match entry.enclosed_name() {
+            Some(p) => p.to_owned(),
+            None => return Err(malicious("points outside the installation folder").into()),
+        };
+        if relative_path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(malicious("points outside the installation folder").into());
+        }
+        if !installation_folder
+            .join(&relative_path)
+            .starts_with(installation_folder)
+        {
+            return Err(malicious("points outside the installation folder").into());
+        }
+        if entry
+            .unix_mode()
+            .is_some_and(|mode| mode & UNIX_FILE_TYPE_MASK == UNIX_SYMLINK_FILE_TYPE)
+        {
+            return Err(malicious("is a symlink").into());
+        }
+        Ok(relative_path)
+    }
+
+    /// Unpacks the zip into the staging folder and returns the path to the executable in it
+    fn stage(
+        &self,
+        downloaded_dist_path: &Path,
+        staging_folder: &Path,
+        executable: &mut Option<PathBuf>,
+    ) -> Result<Option<PathBuf>, SengetErrors> {
+        ZipDist::extract(downloaded_dist_path, staging_folder)?;
+        let inner_unzip_dir = ZipDist::find_inner_unzip_folder(staging_folder.to_owned())?;
+        if inner_unzip_dir != staging_folder {
+            inner_unzip_dir.move_dir_all(staging_folder)?;
+        }
+        // Picked before the shortcut file and shims are created so they point at it
+        ZipDist::pick_executable(&self.package_info.name, staging_folder, executable)
     }
 
     fn find_inner_unzip_folder(outer_unzip_folder: PathBuf) -> Result<PathBuf, io::Error> {
@@ -383,30 +819,89 @@ impl ZipDist {
         &self,
         downloaded_dist_path: &Path,
         packages_folder_path: &Path,
-        create_shortcut_file: bool,
+        install_options: &InstallOptions,
+        transaction: &mut Transaction,
     ) -> Result<InstallInfo, SengetErrors> {
-        let installation_folder = packages_folder_path.join(&self.package_info.name);
-        ZipArchive::new(File::open(downloaded_dist_path)?)?.extract(&installation_folder)?;
-        let inner_unzip_dir = ZipDist::find_inner_unzip_folder(installation_folder.to_owned())?;
-        if inner_unzip_dir != installation_folder {
-            inner_unzip_dir.move_dir_all(&installation_folder)?;
-        }
-        if !DEBUG {
-            fs::remove_file(downloaded_dist_path)?;
-        }
-        let self_name_lower = self.package_info.name.to_lowercase();
-        let executable_path =
-            ZipDist::find_executable_path(&self_name_lower, installation_folder.to_owned())?;
-        if executable_path.is_none() {
-            fs::remove_dir_all(installation_folder)?;
-            return Err(SengetErrors::NoExeFound(NoExeFoundInZipError));
-        }
+        let installation_folder = Dist::generate_installation_folder_path(
+            &self.package_info,
+            packages_folder_path,
+            install_options,
+        );
+        let staging_folder = generate_staging_folder_path(&installation_folder)?;
+        let mut executable = install_options.executable.clone();
+        // The live installation folder is never touched until the package is fully unpacked
+        let staged = self
+            .stage(downloaded_dist_path, &staging_folder, &mut executable)
+            .and_then(|ep| {
+                let detected_binaries = ZipDist::detect_binaries(&staging_folder)?;
+                let binaries =
+                    Dist::resolve_binaries(&staging_folder, install_options, detected_binaries)?;
+                let manifest = Manifest::record(&staging_folder)?;
+                Ok((ep, binaries, manifest))
+            });
+        let (staged_executable_path, binaries, manifest) = match staged {
+            Ok(staged) => staged,
+            Err(err) => {
+                let _ = fs::remove_dir_all(staging_folder);
+                return Err(err);
+            }
+        };
+        transaction.swap_in_folder(&staging_folder, &installation_folder)?;
+        let executable_path = staged_executable_path.and_then(|ep| {
+            ep.strip_prefix(&staging_folder)
+                .map(|relative_path| installation_folder.join(relative_path))
+                .ok()
+        });
         Ok(InstallInfo {
             executable_path,
             installation_folder: Some(installation_folder),
             uninstall_command: None,
             dist_type: DistType::Zip,
-            create_shortcut_file,
+            installer_kind: None,
+            create_shortcut_file: install_options.create_shortcut_file,
+            installer_args: install_options.installer_args.clone(),
+            interactive: install_options.interactive,
+            location: install_options.location.clone(),
+            adopted: false,
+            binaries,
+            custom_binaries: !install_options.binaries.is_empty(),
+            executable,
+            persist: install_options.persist.clone(),
+            shortcuts: Vec::new(),
+            source: None,
+            manifest: Some(manifest),
+        })
+    }
+
+    /// InstallInfo for a folder the user unpacked by hand, nothing is moved and the folder is left
+    /// as it is when other versions are installed
+    pub fn adopt(
+        name: &str,
+        folder: &Path,
+        install_options: &InstallOptions,
+    ) -> Result<InstallInfo, SengetErrors> {
+        let mut executable = install_options.executable.clone();
+        let executable_path = ZipDist::pick_executable(name, folder, &mut executable)?;
+        let detected_binaries = ZipDist::detect_binaries(folder)?;
+        let binaries = Dist::resolve_binaries(folder, install_options, detected_binaries)?;
+        Ok(InstallInfo {
+            executable_path,
+            installation_folder: Some(folder.to_owned()),
+            uninstall_command: None,
+            dist_type: DistType::Zip,
+            installer_kind: None,
+            create_shortcut_file: install_options.create_shortcut_file,
+            installer_args: None,
+            interactive: false,
+            location: None,
+            adopted: true,
+            binaries,
+            custom_binaries: !install_options.binaries.is_empty(),
+            executable,
+            persist: Vec::new(),
+            shortcuts: Vec::new(),
+            source: None,
+            manifest: Some(Manifest::record(folder)?),
         })
     }
 }
@@ -414,6 +909,8 @@ impl ZipDist {
 #[derive(Debug, Clone, PartialEq)]
 pub struct InstallerDist {
     pub package_info: PackageInfo,
+    /// Set once the downloaded installer has been parsed so it isn't parsed again
+    pub installer_kind: Option<InstallerKind>,
 }
 impl InstallerDist {
     const UNINSTALL_KEY_STR: &'static str =
@@ -472,18 +969,64 @@ impl InstallerDist {
         parent_regkey.enum_keys().collect()
     }
 
-    fn run_installation(file_extension: &str, file_path: &Path) -> Result<(), std::io::Error> {
-        match file_extension == "msi" {
-            true => Command::new(MSI_EXEC).arg("/i").arg(file_path).output()?,
-            false => Command::cmd()
-                .arg(file_path)
-                .arg(INNO_SILENT_ARG)
-                .arg(NSIS_SILENT_ARG)
-                .output()?,
+    pub fn switches(installer_kind: Option<InstallerKind>) -> &'static InstallerSwitches {
+        match installer_kind {
+            Some(InstallerKind::Inno) => &INNO_SWITCHES,
+            Some(InstallerKind::Nsis) => &NSIS_SWITCHES,
+            Some(InstallerKind::WixBurn) => &WIX_BURN_SWITCHES,
+            Some(InstallerKind::InstallShield) => &INSTALLSHIELD_SWITCHES,
+            Some(InstallerKind::Squirrel) => &SQUIRREL_SWITCHES,
+            Some(InstallerKind::Msi) => &MSI_SWITCHES,
+            None => &FALLBACK_SWITCHES,
+        }
+    }
+
+    fn run_installation(
+        installer_kind: Option<InstallerKind>,
+        file_path: &Path,
+        install_options: &InstallOptions,
+    ) -> Result<(), std::io::Error> {
+        let mut command = match installer_kind == Some(InstallerKind::Msi) {
+            true => {
+                let mut command = Command::new(MSI_EXEC);
+                command.arg("/i");
+                command
+            }
+            false => Command::cmd(),
         };
+        command.arg(file_path);
+        // Raw cause the user and the install dir switches already quote things the way the
+        // installer expects
+        for arg in InstallerDist::installer_args(installer_kind, install_options) {
+            command.raw_arg(arg);
+        }
+        command.output()?;
         Ok(())
     }
 
+    /// Arguments passed to the installer after its path, the user's installer arguments replace
+    /// all the switches including the install dir one since they might set it themselves
+    fn installer_args(
+        installer_kind: Option<InstallerKind>,
+        install_options: &InstallOptions,
+    ) -> Vec<String> {
+        if let Some(installer_args) = &install_options.installer_args {
+            return vec![installer_args.clone()];
+        }
+        let switches = InstallerDist::switches(installer_kind);
+        let mut args = Vec::new();
+        if !install_options.interactive {
+            args.extend(switches.silent.iter().map(|s| s.to_string()));
+        }
+        if let (Some(location), Some(install_dir_switch)) =
+            (&install_options.location, switches.install_dir)
+        {
+            // Last cause NSIS ignores anything after /D
+            args.push(install_dir_switch.replace("{}", &location.display().to_string()));
+        }
+        args
+    }
+
     fn statically_generate_package_shortcut(&self, startmenu_folder: &Path) -> Option<PathBuf> {
         let shortcut_file_name = format!("{}.lnk", &self.package_info.name);
         let shortcut_path = startmenu_folder.join(&shortcut_file_name);
@@ -499,12 +1042,6 @@ impl InstallerDist {
         None
     }
 
-    fn find_shortcut_target(shortcut_path: &Path) -> Option<PathBuf> {
-        let lnk = lnk::ShellLink::open(shortcut_path).ok()?;
-        let target = lnk.link_info().as_ref()?.local_base_path().as_ref()?;
-        Some(PathBuf::from(target))
-    }
-
     fn dynamically_find_package_shortcut(
         target_name_lower: &str,
         shortcut_files_before: &HashSet<PathBuf>,
@@ -585,6 +1122,22 @@ impl InstallerDist {
         }
         Ok(uninstall_command)
     }
+    /// Looks through every uninstall registry key instead of only the ones an installation added
+    pub fn find_uninstall_command(
+        target_name_lower: &str,
+        installation_folder: &Option<PathBuf>,
+        user_uninstall_reg_key: &RegKey,
+        machine_uninstall_reg_key: &RegKey,
+    ) -> Result<Option<String>, io::Error> {
+        InstallerDist::fetch_uninstall_command(
+            target_name_lower,
+            installation_folder,
+            &HashSet::new(),
+            &HashSet::new(),
+            user_uninstall_reg_key,
+            machine_uninstall_reg_key,
+        )
+    }
     pub fn fetch_uninstall_command_from_executable(
         installation_folder: &Path,
     ) -> Result<Option<String>, io::Error> {
@@ -602,11 +1155,11 @@ impl InstallerDist {
     pub fn install(
         &self,
         installer_path: &Path,
-        create_shortcut_file: bool,
+        install_options: &InstallOptions,
         startmenu_folders: &StartmenuFolders,
         user_uninstall_reg_key: &RegKey,
         machine_uninstall_reg_key: &RegKey,
-    ) -> Result<InstallInfo, io::Error> {
+    ) -> Result<InstallInfo, SengetErrors> {
         let user_reg_keys_before = InstallerDist::fetch_reg_keys(user_uninstall_reg_key)?;
         let machine_reg_keys_before = InstallerDist::fetch_reg_keys(machine_uninstall_reg_key)?;
         let mut shortcut_files_before = HashSet::<PathBuf>::new();
@@ -618,15 +1171,17 @@ impl InstallerDist {
             &mut shortcut_files_before,
             &startmenu_folders.programdata,
         )?;
-        let file_extension = installer_path
-            .extension()
-            .unwrap()
-            .to_str()
-            .unwrap_or_default();
-        InstallerDist::run_installation(file_extension, installer_path)?;
-        if !DEBUG {
-            fs::remove_file(installer_path)?;
-        }
+        let installer_kind = match self.installer_kind {
+            Some(installer_kind) => Some(installer_kind),
+            None => InstallerKind::detect(installer_path)?,
+        };
+        InstallerDist::run_installation(installer_kind, installer_path, install_options)?;
+        // Installers that can't be told where to install pick their own folder
+        let location = install_options.location.clone().filter(|_| {
+            InstallerDist::switches(installer_kind)
+                .install_dir
+                .is_some()
+        });
 
         let mut shortcut_path = self
             .statically_generate_package_shortcut(&startmenu_folders.appdata)
@@ -648,10 +1203,16 @@ impl InstallerDist {
         };
         let executable_path = shortcut_path
             .as_ref()
-            .and_then(|path| InstallerDist::find_shortcut_target(path));
+            .and_then(|path| read_shortcut(path))
+            .map(|shortcut| shortcut.target);
         let installation_folder = executable_path
             .as_ref()
             .and_then(|ep| ep.parent().map(PathBuf::from));
+        // Installers only expose their main executable unless the user picks the binaries
+        let binaries = match installation_folder.as_ref() {
+            Some(folder) => Dist::resolve_binaries(folder, install_options, Vec::new())?,
+            None => Vec::new(),
+        };
 
         let uninstall_command = InstallerDist::fetch_uninstall_command(
             &self_name_lower,
@@ -667,7 +1228,19 @@ impl InstallerDist {
             installation_folder,
             uninstall_command,
             dist_type: DistType::Installer,
-            create_shortcut_file,
+            installer_kind,
+            create_shortcut_file: install_options.create_shortcut_file,
+            installer_args: install_options.installer_args.clone(),
+            interactive: install_options.interactive,
+            location,
+            adopted: false,
+            binaries,
+            custom_binaries: !install_options.binaries.is_empty(),
+            executable: install_options.executable.clone(),
+            persist: install_options.persist.clone(),
+            shortcuts: Vec::new(),
+            source: None,
+            manifest: None,
         })
     }
 }
@@ -678,5 +1251,665 @@ pub struct InstallInfo {
     pub installation_folder: Option<PathBuf>,
     pub uninstall_command: Option<String>,
     pub dist_type: DistType,
+    #[serde(default)]
+    pub installer_kind: Option<InstallerKind>,
     pub create_shortcut_file: bool,
+    #[serde(default)]
+    pub installer_args: Option<String>,
+    #[serde(default)]
+    pub interactive: bool,
+    #[serde(default)]
+    pub location: Option<PathBuf>,
+    /// Whether the installation folder was unpacked by hand and adopted, it's never moved into a
+    /// versioned folder and other versions are installed into the packages folder instead
+    #[serde(default)]
+    pub adopted: bool,
+    /// Empty in packages installed before multiple binaries were supported, the executable is
+    /// exposed instead
+    #[serde(default)]
+    pub binaries: Vec<Binary>,
+    /// Whether the binaries were picked with --bin so updates keep them
+    #[serde(default)]
+    pub custom_binaries: bool,
+    /// Main executable of a zip package the user picked, relative to the installation folder
+    #[serde(default)]
+    pub executable: Option<PathBuf>,
+    /// Files an exe or zip package installed, None in installers and packages installed before
+    /// manifests were recorded
+    #[serde(default)]
+    pub manifest: Option<Manifest>,
+    #[serde(default)]
+    pub persist: Vec<PathBuf>,
+    /// Shortcut files senget made, empty in packages installed before they were recorded
+    #[serde(default)]
+    pub shortcuts: Vec<ShortcutFile>,
+    /// Distributable the version was installed from, None in packages installed before it was
+    /// recorded
+    #[serde(default)]
+    pub source: Option<DistSource>,
+}
+
+/// Lets rolling back to a version reuse its cached distributable or download it without
+/// looking up the release again
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistSource {
+    pub file_title: String,
+    pub file_size: u64,
+    pub download_url: String,
+    /// None in versions installed before distributables were hashed
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl InstallInfo {
+    pub fn install_options(&self) -> InstallOptions {
+        InstallOptions {
+            create_shortcut_file: self.create_shortcut_file,
+            installer_args: self.installer_args.clone(),
+            interactive: self.interactive,
+            location: self.location.clone(),
+            binaries: match self.custom_binaries {
+                true => self.binaries.clone(),
+                false => Vec::new(),
+            },
+            executable: self.executable.clone(),
+            persist: self.persist.clone(),
+            constraint: None,
+            hooks: Hooks::default(),
+        }
+    }
+
+    /// Size of the files the package installed, only the recorded ones if there's a manifest
+    pub fn disk_size(&self) -> Option<u64> {
+        match (self.manifest.as_ref(), self.installation_folder.as_ref()) {
+            (Some(manifest), _) => Some(manifest.files.iter().map(|f| f.size).sum()),
+            (None, Some(folder)) if folder.is_dir() => folder_size(folder).ok(),
+            _ => None,
+        }
+    }
+
+    /// Removes the files the package installed, only the recorded ones if there's a manifest.
+    /// Without one the whole installation folder is deleted so it has to be one senget owns
+    pub fn remove_installed_files(&self, packages_folder_path: &Path) -> Result<(), io::Error> {
+        unlink_persisted(self)?;
+        match (self.manifest.as_ref(), self.installation_folder.as_ref()) {
+            (Some(manifest), Some(folder)) => manifest.remove_files(folder),
+            (None, Some(folder)) if self.dist_type != DistType::Installer && folder.is_dir() => {
+                if !self.owns_folder(folder, packages_folder_path) {
+                    return Err(io::Error::other(format!(
+                        "Refusing to delete {} since it's outside the packages folder",
+                        folder.display()
+                    )));
+                }
+                fs::remove_dir_all(folder)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the folder is inside the packages folder or the location the package was
+    /// installed into, so a bad record can't point the deletion at any other folder
+    fn owns_folder(&self, folder: &Path, packages_folder_path: &Path) -> bool {
+        let is_inside = |root: &Path| {
+            folder.strip_prefix(root).is_ok_and(|relative_path| {
+                relative_path
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+            })
+        };
+        (folder != packages_folder_path && is_inside(packages_folder_path))
+            || self.location.as_deref().is_some_and(is_inside)
+    }
+
+    /// Command names and absolute paths of the executables the package exposes
+    pub fn exposed_binaries(&self) -> Vec<(String, PathBuf)> {
+        match (self.binaries.is_empty(), self.installation_folder.as_ref()) {
+            (false, Some(folder)) => self
+                .binaries
+                .iter()
+                .map(|b| (b.command_name(), folder.join(&b.path)))
+                .collect(),
+            _ => self
+                .executable_path
+                .iter()
+                .map(|ep| {
+                    let name = ep.file_stem().unwrap_or_default().to_string_lossy();
+                    (name.into_owned(), ep.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl InstallInfo {
+    /// A zip package in the folder with nothing recorded about it
+    pub fn test_fixture(installation_folder: &Path) -> InstallInfo {
+        InstallInfo {
+            executable_path: None,
+            installation_folder: Some(installation_folder.to_owned()),
+            uninstall_command: None,
+            dist_type: DistType::Zip,
+            installer_kind: None,
+            create_shortcut_file: false,
+            installer_args: None,
+            interactive: false,
+            location: None,
+            adopted: false,
+            binaries: Vec::new(),
+            custom_binaries: false,
+            executable: None,
+            manifest: None,
+            persist: Vec::new(),
+            shortcuts: Vec::new(),
+            source: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::{transaction::find_leftover_folders, utils::create_test_folder};
+    use zip::{write::FileOptions, ZipWriter};
+
+    enum FixtureEntry<'a> {
+        File(&'a str, &'a [u8]),
+        Symlink(&'a str, &'a str),
+    }
+
+    /// Creates a fresh folder containing a crafted zip fixture and an empty installation folder
+    fn create_fixture(test_name: &str, entries: &[FixtureEntry]) -> (PathBuf, PathBuf) {
+        let test_folder = create_test_folder(test_name);
+        let installation_folder = test_folder.join("packages").join("package");
+        fs::create_dir_all(&installation_folder).unwrap();
+        let zip_path = test_folder.join("fixture.zip");
+        let mut writer = ZipWriter::new(File::create(&zip_path).unwrap());
+        for entry in entries {
+            match entry {
+                FixtureEntry::File(name, content) => {
+                    writer.start_file(*name, FileOptions::default()).unwrap();
+                    writer.write_all(content).unwrap();
+                }
+                FixtureEntry::Symlink(name, target) => {
+                    writer
+                        .add_symlink(*name, *target, FileOptions::default())
+                        .unwrap();
+                }
+            }
+        }
+        writer.finish().unwrap();
+        (zip_path, installation_folder)
+    }
+
+    /// Rewrites the uncompressed size in the local and central directory headers of every entry
+    fn forge_uncompressed_size(zip_path: &Path, size: u32) {
+        let mut bytes = fs::read(zip_path).unwrap();
+        // Offsets of the uncompressed size in the local and central directory file headers
+        let headers = [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)];
+        for (signature, size_offset) in headers {
+            let header_offsets = bytes
+                .windows(4)
+                .enumerate()
+                .filter(|(_, w)| w == signature)
+                .map(|(offset, _)| offset + size_offset)
+                .collect::<Vec<usize>>();
+            for offset in header_offsets {
+                bytes[offset..offset + 4].copy_from_slice(&size.to_le_bytes());
+            }
+        }
+        fs::write(zip_path, bytes).unwrap();
+    }
+
+    fn assert_malicious(result: Result<(), SengetErrors>) {
+        assert!(matches!(result, Err(SengetErrors::MaliciousZipError(_))));
+    }
+
+    #[test]
+    fn extracts_benign_zip() {
+        let (zip_path, installation_folder) = create_fixture(
+            "benign-zip",
+            &[
+                FixtureEntry::File("package.exe", b"exe"),
+                FixtureEntry::File("bin/helper.dll", b"dll"),
+            ],
+        );
+        ZipDist::extract(&zip_path, &installation_folder).unwrap();
+        assert!(installation_folder.join("package.exe").is_file());
+        assert!(installation_folder.join("bin").join("helper.dll").is_file());
+    }
+
+    #[test]
+    fn rejects_parent_dir_entry() {
+        let (zip_path, installation_folder) = create_fixture(
+            "parent-dir-zip",
+            &[
+                FixtureEntry::File("package.exe", b"exe"),
+                FixtureEntry::File("../../evil.exe", b"evil"),
+            ],
+        );
+        assert_malicious(ZipDist::extract(&zip_path, &installation_folder));
+        // Validation happens before extraction so not even the benign entry is written
+        assert!(!installation_folder.join("package.exe").exists());
+        assert!(!installation_folder
+            .join("..")
+            .join("..")
+            .join("evil.exe")
+            .exists());
+    }
+
+    #[test]
+    fn rejects_absolute_entry() {
+        let (zip_path, installation_folder) =
+            create_fixture("absolute-zip", &[FixtureEntry::File("/evil.exe", b"evil")]);
+        assert_malicious(ZipDist::extract(&zip_path, &installation_folder));
+    }
+
+    #[test]
+    fn rejects_symlink_entry() {
+        let (zip_path, installation_folder) = create_fixture(
+            "symlink-zip",
+            &[FixtureEntry::Symlink("link", "C:\\Windows\\System32")],
+        );
+        assert_malicious(ZipDist::extract(&zip_path, &installation_folder));
+        assert!(!installation_folder.join("link").exists());
+    }
+
+    #[test]
+    fn rejects_too_many_entries() {
+        let (zip_path, installation_folder) = create_fixture(
+            "too-many-entries-zip",
+            &[
+                FixtureEntry::File("a.txt", b"a"),
+                FixtureEntry::File("b.txt", b"b"),
+                FixtureEntry::File("c.txt", b"c"),
+            ],
+        );
+        assert_malicious(ZipDist::extract_with_limits(
+            &zip_path,
+            &installation_folder,
+            2,
+            MAX_ZIP_EXTRACTED_SIZE,
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_zip() {
+        let content = vec![0; 4096];
+        let (zip_path, installation_folder) =
+            create_fixture("oversized-zip", &[FixtureEntry::File("bomb.bin", &content)]);
+        assert_malicious(ZipDist::extract_with_limits(
+            &zip_path,
+            &installation_folder,
+            MAX_ZIP_ENTRIES,
+            1024,
+        ));
+    }
+
+    #[test]
+    fn rejects_entry_larger_than_its_forged_size() {
+        let content = vec![b'a'; 4096];
+        let (zip_path, installation_folder) = create_fixture(
+            "forged-size-zip",
+            &[FixtureEntry::File("bomb.bin", &content)],
+        );
+        forge_uncompressed_size(&zip_path, 16);
+        assert_malicious(ZipDist::extract(&zip_path, &installation_folder));
+        let extracted_size = fs::metadata(installation_folder.join("bomb.bin"))
+            .unwrap()
+            .len();
+        assert!(extracted_size <= 17);
+    }
+
+    #[test]
+    fn keeps_the_installer_kind_detected_in_an_exe() {
+        let test_folder = create_test_folder("exe-installer-kind");
+        let msi_path = test_folder.join("package.exe");
+        let msi_signature = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+        fs::write(&msi_path, [&msi_signature[..], &[0; 512]].concat()).unwrap();
+        let exe_dist = ExeDist {
+            package_info: PackageInfo::new(
+                "package".to_owned(),
+                "https://github.com/package/package.exe".to_owned(),
+                "1.0.0".to_owned(),
+                "package.exe".to_owned(),
+                520,
+            ),
+        };
+        let dist = exe_dist.check_if_is_actually_installer(&msi_path).unwrap();
+        assert!(matches!(
+            dist,
+            Dist::Installer(InstallerDist {
+                installer_kind: Some(InstallerKind::Msi),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn removes_the_staging_folder_when_staging_an_exe_fails() {
+        let test_folder = create_test_folder("exe-staging-failed");
+        let exe_dist = ExeDist {
+            package_info: PackageInfo::new(
+                "package".to_owned(),
+                "https://github.com/package/package.exe".to_owned(),
+                "1.0.0".to_owned(),
+                "package.exe".to_owned(),
+                520,
+            ),
+        };
+        let packages_folder = test_folder.join("packages");
+        let installed = exe_dist.install(
+            &test_folder.join("missing.exe"),
+            &packages_folder,
+            &InstallOptions::default(),
+            &mut Transaction::new(),
+        );
+        assert!(installed.is_err());
+        assert!(find_leftover_folders(&packages_folder.join("package"))
+            .unwrap()
+            .is_empty());
+    }
+
+    fn installer_args(
+        installer_kind: Option<InstallerKind>,
+        installer_args: Option<&str>,
+        interactive: bool,
+        location: Option<&str>,
+    ) -> Vec<String> {
+        let install_options = InstallOptions {
+            installer_args: installer_args.map(String::from),
+            interactive,
+            location: location.map(PathBuf::from),
+            ..InstallOptions::default()
+        };
+        InstallerDist::installer_args(installer_kind, &install_options)
+    }
+
+    #[test]
+    fn runs_each_installer_kind_with_its_own_switches() {
+        let cases = [
+            (
+                Some(InstallerKind::Inno),
+                vec![
+                    "/VERYSILENT",
+                    "/SUPPRESSMSGBOXES",
+                    "/NORESTART",
+                    "/DIR=\"C:\\Apps\"",
+                ],
+            ),
+            (Some(InstallerKind::Nsis), vec!["/S", "/D=C:\\Apps"]),
+            (Some(InstallerKind::WixBurn), vec!["/quiet", "/norestart"]),
+            (
+                Some(InstallerKind::Msi),
+                vec!["/passive", "/norestart", "INSTALLDIR=\"C:\\Apps\""],
+            ),
+            (
+                Some(InstallerKind::InstallShield),
+                vec!["/s", "/v\"INSTALLDIR=\\\"C:\\Apps\\\"\""],
+            ),
+            (Some(InstallerKind::Squirrel), vec!["--silent"]),
+            (None, vec!["/VERYSILENT", "/S"]),
+        ];
+        for (installer_kind, expected) in cases {
+            assert_eq!(
+                installer_args(installer_kind, None, false, Some("C:\\Apps")),
+                expected,
+                "{:?}",
+                installer_kind
+            );
+        }
+    }
+
+    #[test]
+    fn interactive_installs_only_pass_the_install_dir() {
+        assert_eq!(
+            installer_args(Some(InstallerKind::Nsis), None, true, Some("C:\\Apps")),
+            vec!["/D=C:\\Apps"]
+        );
+        assert!(installer_args(Some(InstallerKind::Msi), None, true, None).is_empty());
+    }
+
+    #[test]
+    fn user_installer_args_replace_every_switch() {
+        let user_args = "/SILENT /DIR=\"D:\\Games\"";
+        for interactive in [false, true] {
+            assert_eq!(
+                installer_args(
+                    Some(InstallerKind::Inno),
+                    Some(user_args),
+                    interactive,
+                    Some("C:\\Apps")
+                ),
+                vec![user_args]
+            );
+        }
+    }
+
+    /// Creates empty files at the relative paths inside the folder
+    fn create_files(folder: &Path, relative_paths: &[&str]) {
+        for relative_path in relative_paths {
+            let path = folder.join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+    }
+
+    #[test]
+    fn parses_binary_paths_and_aliases() {
+        let cases = [
+            ("rg.exe", "rg.exe", None, "rg"),
+            ("bin/rg.exe", "bin/rg.exe", None, "rg"),
+            ("bin/ripgrep.exe=rg", "bin/ripgrep.exe", Some("rg"), "rg"),
+            ("tools/a=b.exe=ab", "tools/a=b.exe", Some("ab"), "ab"),
+        ];
+        for (value, path, alias, command_name) in cases {
+            let binary = Binary::parse(value);
+            assert_eq!(binary.path, PathBuf::from(path), "{}", value);
+            assert_eq!(binary.alias.as_deref(), alias, "{}", value);
+            assert_eq!(binary.command_name(), command_name, "{}", value);
+        }
+    }
+
+    #[test]
+    fn detects_binaries_in_the_root_and_bin_folders() {
+        let folder = create_test_folder("detect-binaries");
+        create_files(
+            &folder,
+            &[
+                "app.exe",
+                "readme.txt",
+                "bin/tool.EXE",
+                "bin/tool.dll",
+                "lib/nested.exe",
+                "bin/deeper/deep.exe",
+            ],
+        );
+        let mut binaries = ZipDist::detect_binaries(&folder)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.path)
+            .collect::<Vec<PathBuf>>();
+        binaries.sort();
+        assert_eq!(
+            binaries,
+            vec![PathBuf::from("app.exe"), Path::new("bin").join("tool.EXE")]
+        );
+        let no_binaries = create_test_folder("detect-no-binaries");
+        assert!(ZipDist::detect_binaries(&no_binaries).unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_resolves_binaries_inside_the_installation_folder() {
+        let folder = create_test_folder("resolve-binaries");
+        create_files(&folder, &["bin/rg.exe"]);
+        let detected = vec![Binary::parse("app.exe")];
+        let options = |binaries: &[&str]| InstallOptions {
+            binaries: binaries.iter().map(|b| Binary::parse(b)).collect(),
+            ..InstallOptions::default()
+        };
+        let resolved = Dist::resolve_binaries(&folder, &options(&[]), detected.clone());
+        assert_eq!(resolved.ok(), Some(detected.clone()));
+        let resolved = Dist::resolve_binaries(&folder, &options(&["bin/rg.exe=rg"]), detected);
+        assert_eq!(resolved.ok(), Some(vec![Binary::parse("bin/rg.exe=rg")]));
+        for missing in ["bin/missing.exe", "../rg.exe", "bin/../../rg.exe"] {
+            let resolved = Dist::resolve_binaries(&folder, &options(&[missing]), Vec::new());
+            assert!(resolved.is_err(), "{}", missing);
+        }
+    }
+
+    #[test]
+    fn ranks_executables_named_after_the_package_first() {
+        let folder = create_test_folder("rank-executables");
+        create_files(
+            &folder,
+            &[
+                "uninstall.exe",
+                "tools/senpwai-cli.exe",
+                "app/bin/Senpwai.exe",
+                "tools/helper.exe",
+                "senpwai.dll",
+            ],
+        );
+        let executables = ZipDist::rank_executables("senpwai", folder.clone()).unwrap();
+        let relative_paths = executables
+            .iter()
+            .map(|e| e.strip_prefix(&folder).unwrap().to_owned())
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(relative_paths.len(), 4);
+        assert_eq!(relative_paths[0], Path::new("app/bin/Senpwai.exe"));
+        assert_eq!(relative_paths[1], Path::new("tools/senpwai-cli.exe"));
+        // Shallower ones come first within the same rank
+        assert_eq!(relative_paths[2], Path::new("uninstall.exe"));
+        assert_eq!(relative_paths[3], Path::new("tools/helper.exe"));
+    }
+
+    #[test]
+    fn lists_the_candidates_of_an_ambiguous_executable_without_a_prompt() {
+        let folder = create_test_folder("pick-executable");
+        create_files(&folder, &["app.exe", "tools/helper.exe"]);
+        let mut executable = None;
+        match ZipDist::pick_executable_with_prompt("package", &folder, &mut executable, false) {
+            Err(SengetErrors::AmbiguousExecutableError(err)) => {
+                assert_eq!(err.candidates, vec!["app.exe", "tools\\helper.exe"]);
+            }
+            _ => panic!("expected an AmbiguousExecutableError"),
+        }
+        create_files(&folder, &["package.exe"]);
+        let picked =
+            ZipDist::pick_executable_with_prompt("package", &folder, &mut executable, false);
+        assert_eq!(picked.ok(), Some(Some(folder.join("package.exe"))));
+        let mut executable = Some(PathBuf::from("tools/helper.exe"));
+        let picked =
+            ZipDist::pick_executable_with_prompt("package", &folder, &mut executable, false);
+        assert_eq!(picked.ok(), Some(Some(folder.join("tools/helper.exe"))));
+    }
+
+    #[test]
+    fn stages_zip_without_executables_as_data_only() {
+        let (zip_path, installation_folder) = create_fixture(
+            "data-only-zip",
+            &[
+                FixtureEntry::File("fonts/Fira.ttf", b"font"),
+                FixtureEntry::File("readme.txt", b"readme"),
+            ],
+        );
+        let zip_dist = ZipDist {
+            package_info: PackageInfo::new(
+                "fonts".to_owned(),
+                "https://github.com/fonts/fonts.zip".to_owned(),
+                "1.0.0".to_owned(),
+                "fonts.zip".to_owned(),
+                1024,
+            ),
+        };
+        let executable_path = zip_dist
+            .stage(&zip_path, &installation_folder, &mut None)
+            .ok()
+            .unwrap();
+        assert_eq!(executable_path, None);
+        assert!(installation_folder.join("readme.txt").is_file());
+        assert!(ZipDist::rank_executables("fonts", installation_folder)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn only_deletes_unrecorded_installation_folders_senget_owns() {
+        let test_folder = create_test_folder("remove-installed-files");
+        let packages_folder = test_folder.join("packages");
+        let installed = packages_folder.join("package").join("1.0.0");
+        let outside = test_folder.join("Documents");
+        let location = test_folder.join("Apps").join("package");
+        for folder in [&installed, &outside, &location] {
+            create_files(folder, &["package.exe"]);
+        }
+        let escaping = packages_folder.join("..").join("Documents");
+        for folder in [&outside, &escaping, &packages_folder] {
+            let install_info = InstallInfo::test_fixture(folder);
+            assert!(install_info
+                .remove_installed_files(&packages_folder)
+                .is_err());
+        }
+        assert!(outside.join("package.exe").is_file());
+        assert!(installed.join("package.exe").is_file());
+        InstallInfo::test_fixture(&installed)
+            .remove_installed_files(&packages_folder)
+            .unwrap();
+        assert!(!installed.exists());
+        let mut install_info = InstallInfo::test_fixture(&location);
+        install_info.location = Some(location.clone());
+        install_info
+            .remove_installed_files(&packages_folder)
+            .unwrap();
+        assert!(!location.exists());
+    }
+
+    #[test]
+    fn installs_zip_into_the_location_folder() {
+        let (zip_path, installation_folder) =
+            create_fixture("zip-location", &[FixtureEntry::File("package.exe", b"exe")]);
+        let packages_folder = installation_folder.parent().unwrap();
+        let location = packages_folder
+            .parent()
+            .unwrap()
+            .join("Apps")
+            .join("package");
+        let zip_dist = ZipDist {
+            package_info: PackageInfo::new(
+                "package".to_owned(),
+                "https://github.com/package/package.zip".to_owned(),
+                "1.0.0".to_owned(),
+                "package.zip".to_owned(),
+                1024,
+            ),
+        };
+        let install_options = InstallOptions {
+            location: Some(location.clone()),
+            ..InstallOptions::default()
+        };
+        let mut transaction = Transaction::new();
+        let install_info = zip_dist
+            .install(
+                &zip_path,
+                packages_folder,
+                &install_options,
+                &mut transaction,
+            )
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        let version_folder = location.join("1.0.0");
+        assert_eq!(
+            install_info.installation_folder,
+            Some(version_folder.clone())
+        );
+        assert_eq!(
+            install_info.executable_path,
+            Some(version_folder.join("package.exe"))
+        );
+        assert_eq!(install_info.location, Some(location));
+        assert!(version_folder.join("package.exe").is_file());
+        assert!(!packages_folder.join("package").join("1.0.0").exists());
+    }
 }
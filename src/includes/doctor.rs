@@ -0,0 +1,514 @@
+//!Finds and repairs drift between the package database and what senget left on the system
+
+use crate::includes::{
+    commands::Statics,
+    database::PackageDatabase,
+    dist::{DistSource, DistType, InstallInfo},
+    error::SengetErrors,
+    package::Package,
+    senget_manager::env::{inspect_path_env_var, repair_path_env_var},
+    shim::{find_stray_shims, rebuild_shims, Shim},
+    shortcut::{find_shortcut_files, read_shortcut},
+    transaction::{clean_up_leftover_folder, find_leftover_folders},
+    utils::{FolderItems, PathStr, NAME},
+};
+use std::{
+    collections::BTreeSet,
+    fmt, fs, iter,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemStatus {
+    Fixed,
+    /// Running with --fix repairs it
+    Fixable,
+    /// Has to be repaired by hand e.g., by reinstalling or purging the package
+    Manual,
+}
+
+impl fmt::Display for ProblemStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            ProblemStatus::Fixed => "Fixed",
+            ProblemStatus::Fixable => "Fixable",
+            ProblemStatus::Manual => "Fix manually",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+pub struct Problem {
+    /// Package the problem is in, senget for the ones outside of any package
+    pub name: String,
+    pub description: String,
+    pub status: ProblemStatus,
+}
+
+/// Runs the checks in order so that later ones see what earlier ones repaired, e.g., the shims
+/// are checked after moved executables are found
+pub struct Doctor<'a> {
+    fix: bool,
+    db: &'a PackageDatabase,
+    statics: &'a Statics,
+    problems: Vec<Problem>,
+}
+
+/// Every version of the package senget still knows about
+fn install_infos(package: &Package) -> impl Iterator<Item = &InstallInfo> {
+    iter::once(&package.install_info).chain(
+        package
+            .other_versions
+            .iter()
+            .chain(package.previous_versions.iter())
+            .map(|v| &v.install_info),
+    )
+}
+
+impl<'a> Doctor<'a> {
+    pub fn new(fix: bool, db: &'a PackageDatabase, statics: &'a Statics) -> Doctor<'a> {
+        Doctor {
+            fix,
+            db,
+            statics,
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn examine(mut self) -> Result<Vec<Problem>, SengetErrors> {
+        self.check_executables()?;
+        self.check_uninstall_commands()?;
+        self.check_shortcuts()?;
+        self.check_shims()?;
+        self.check_path_env_var()?;
+        self.check_leftovers()?;
+        Ok(self.problems)
+    }
+
+    fn report(&mut self, name: &str, description: String) {
+        self.problems.push(Problem {
+            name: name.to_owned(),
+            description,
+            status: ProblemStatus::Manual,
+        });
+    }
+
+    fn repair<F>(&mut self, name: &str, description: String, fix: F) -> Result<(), SengetErrors>
+    where
+        F: FnOnce() -> Result<(), SengetErrors>,
+    {
+        self.repair_together(vec![(name.to_owned(), description)], fix)
+    }
+
+    /// Problems that the same fix repairs, it only runs once
+    fn repair_together<F>(
+        &mut self,
+        problems: Vec<(String, String)>,
+        fix: F,
+    ) -> Result<(), SengetErrors>
+    where
+        F: FnOnce() -> Result<(), SengetErrors>,
+    {
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let status = match self.fix {
+            true => {
+                fix()?;
+                ProblemStatus::Fixed
+            }
+            false => ProblemStatus::Fixable,
+        };
+        for (name, description) in problems {
+            self.problems.push(Problem {
+                name,
+                description,
+                status,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_executables(&mut self) -> Result<(), SengetErrors> {
+        let db = self.db;
+        for package in db.fetch_all_packages()? {
+            let name = &package.repo.name;
+            let executable_path = package.install_info.executable_path.as_ref();
+            for (_, path) in package.install_info.exposed_binaries() {
+                if !path.is_file() && Some(&path) != executable_path {
+                    self.report(name, format!("Binary {} is missing", path.display()));
+                }
+            }
+            for other_version in package.other_versions.iter() {
+                let installation_folder = other_version.install_info.installation_folder.as_ref();
+                if installation_folder.is_some_and(|f| !f.is_dir()) {
+                    let description = format!(
+                        "Installation folder of version {} is missing",
+                        other_version.version
+                    );
+                    self.report(name, description);
+                }
+            }
+            let executable_path = match executable_path {
+                Some(ep) if !ep.is_file() => ep,
+                _ => continue,
+            };
+            let description = format!("Executable {} is missing", executable_path.display());
+            match package.redetect_executable(&self.statics.startmenu_folders)? {
+                Some(found) => {
+                    let description = format!("{}, found {}", description, found.display());
+                    self.repair(name, description, || {
+                        let mut updated_package = package.clone();
+                        // An installer's folder is wherever its executable is
+                        if updated_package.install_info.dist_type == DistType::Installer {
+                            updated_package.install_info.installation_folder =
+                                found.parent().map(PathBuf::from);
+                        }
+                        updated_package.install_info.executable_path = Some(found);
+                        db.update_package(&package, updated_package)
+                    })?;
+                }
+                None => self.report(name, description),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_uninstall_commands(&mut self) -> Result<(), SengetErrors> {
+        let db = self.db;
+        for package in db.fetch_all_packages()? {
+            if package.install_info.dist_type != DistType::Installer || package.has_uninstaller() {
+                continue;
+            }
+            let name = &package.repo.name;
+            let uninstall_command = package.install_info.uninstall_command.as_ref();
+            let description = match uninstall_command {
+                Some(us) => format!("Uninstaller of {} is missing", us),
+                None => "No uninstall command was recorded".to_owned(),
+            };
+            let found = package
+                .redetect_uninstall_command(
+                    &self.statics.user_uninstall_reg_key,
+                    &self.statics.machine_uninstall_reg_key,
+                )?
+                .filter(|us| Some(us) != uninstall_command);
+            match found {
+                Some(found) => {
+                    let description = format!("{}, found {}", description, found);
+                    self.repair(name, description, || {
+                        let mut updated_package = package.clone();
+                        updated_package.install_info.uninstall_command = Some(found);
+                        db.update_package(&package, updated_package)
+                    })?;
+                }
+                None => self.report(name, description),
+            }
+        }
+        Ok(())
+    }
+
+    /// Shortcut files senget made that are missing or don't launch anything anymore, and ones
+    /// left behind that launch something in a package folder that's gone
+    fn check_shortcuts(&mut self) -> Result<(), SengetErrors> {
+        let packages = self.db.fetch_all_packages()?;
+        let startmenu_folder = &self.statics.startmenu_folders.appdata;
+        let mut recorded_shortcut_files = Vec::new();
+        for package in packages.iter() {
+            let executable_path = package
+                .install_info
+                .executable_path
+                .as_ref()
+                .filter(|ep| ep.is_file());
+            for shortcut in package.shortcut_files(startmenu_folder) {
+                recorded_shortcut_files.push(shortcut.path.clone());
+                let description = match shortcut.path.is_file() {
+                    true if read_shortcut(&shortcut.path).is_none_or(|s| s.target.is_file()) => {
+                        continue;
+                    }
+                    true => format!("Shortcut file {} is dangling", shortcut.path.display()),
+                    false => format!("Shortcut file {} is missing", shortcut.path.display()),
+                };
+                match executable_path {
+                    Some(executable_path) => {
+                        self.repair(&package.repo.name, description, || {
+                            Ok(shortcut.create(executable_path)?)
+                        })?
+                    }
+                    None => self.report(&package.repo.name, description),
+                }
+            }
+        }
+        // Installers put their folders anywhere and clean up their own shortcut files
+        let package_folders = iter::once(self.statics.packages_folder_path.clone())
+            .chain(
+                packages
+                    .iter()
+                    .filter(|p| p.install_info.dist_type != DistType::Installer)
+                    .filter_map(|p| p.package_folder()),
+            )
+            .collect::<Vec<PathBuf>>();
+        let mut shortcut_files = find_shortcut_files(startmenu_folder)?;
+        shortcut_files.extend(find_shortcut_files(&self.statics.desktop_folder_path)?);
+        for shortcut_file in shortcut_files {
+            if recorded_shortcut_files.contains(&shortcut_file) {
+                continue;
+            }
+            let is_dangling = read_shortcut(&shortcut_file).is_some_and(|s| {
+                !s.target.exists() && package_folders.iter().any(|f| s.target.starts_with(f))
+            });
+            if is_dangling {
+                let description = format!(
+                    "Shortcut file {} launches a package that's gone",
+                    shortcut_file.display()
+                );
+                self.repair(NAME, description, || Ok(fs::remove_file(&shortcut_file)?))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_shims(&mut self) -> Result<(), SengetErrors> {
+        let packages = self.db.fetch_all_packages()?;
+        let shims_folder = &self.statics.shims_folder_path;
+        // Later packages replace the shims of earlier ones with the same name like rebuilding does
+        let mut shims: Vec<(&str, String, Shim)> = Vec::new();
+        for package in packages.iter() {
+            for (command_name, executable_path) in package.install_info.exposed_binaries() {
+                if !executable_path.is_file() {
+                    continue;
+                }
+                let shim = Shim::new(&command_name, &executable_path, shims_folder);
+                shims.retain(|(_, _, s)| !s.has_same_name(&shim));
+                shims.push((&package.repo.name, command_name, shim));
+            }
+        }
+        let mut problems = shims
+            .iter()
+            .filter(|(_, _, shim)| !shim.is_up_to_date())
+            .map(|(name, command_name, _)| {
+                let description = format!("Shim for {} is missing or outdated", command_name);
+                (name.to_string(), description)
+            })
+            .collect::<Vec<(String, String)>>();
+        for stray_shim in find_stray_shims(&packages, shims_folder)? {
+            let description = format!("Shim {} belongs to no package", stray_shim.display());
+            problems.push((NAME.to_owned(), description));
+        }
+        self.repair_together(problems, || {
+            rebuild_shims(&packages, shims_folder)?;
+            Ok(())
+        })
+    }
+
+    fn check_path_env_var(&mut self) -> Result<(), SengetErrors> {
+        let shims_folder = self.statics.shims_folder_path.path_str()?;
+        let problems = inspect_path_env_var(&shims_folder)?
+            .into_iter()
+            .map(|description| (NAME.to_owned(), description))
+            .collect();
+        self.repair_together(problems, || Ok(repair_path_env_var(&shims_folder)?))
+    }
+
+    /// Incomplete downloads in the cache folder and folders an interrupted install or update left
+    fn check_leftovers(&mut self) -> Result<(), SengetErrors> {
+        let packages = self.db.fetch_all_packages()?;
+        let sources = packages
+            .iter()
+            .flat_map(install_infos)
+            .filter_map(|i| i.source.as_ref())
+            .collect::<Vec<&DistSource>>();
+        for item in self.statics.dists_folder_path.folder_items()? {
+            let path = item.path();
+            if !path.is_file() {
+                continue;
+            }
+            let size = item.metadata()?.len();
+            let file_title = item.file_name().to_string_lossy().into_owned();
+            let is_incomplete = size == 0
+                || sources
+                    .iter()
+                    .any(|s| s.file_title == file_title && s.file_size != size);
+            if is_incomplete {
+                let description = format!("Cached {} is an incomplete download", file_title);
+                self.repair(NAME, description, || Ok(fs::remove_file(&path)?))?;
+            }
+        }
+        // Staging and backup folders are siblings of the installation folder
+        let mut folders = BTreeSet::from([self.statics.packages_folder_path.clone()]);
+        for package_folder in packages.iter().filter_map(|p| p.package_folder()) {
+            folders.extend(package_folder.parent().map(Path::to_owned));
+            folders.insert(package_folder);
+        }
+        for folder in folders {
+            for leftover_folder in find_leftover_folders(&folder)? {
+                let description = format!(
+                    "{} was left behind by an interrupted install or update",
+                    leftover_folder.display()
+                );
+                self.repair(NAME, description, || {
+                    Ok(clean_up_leftover_folder(&leftover_folder)?)
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::{
+        package::InstalledVersion, shortcut::create_test_shortcut, utils::create_test_folder,
+    };
+
+    fn doctor_fixture(test_name: &str) -> (PackageDatabase, Statics) {
+        let config_dir = create_test_folder(test_name);
+        let db = PackageDatabase::new(&config_dir).unwrap();
+        (db, Statics::test_fixture(&config_dir))
+    }
+
+    /// A zip package with its executable named after it in its versioned folder
+    fn create_package(statics: &Statics, name: &str, version: &str) -> Package {
+        let installation_folder = statics.packages_folder_path.join(name).join(version);
+        fs::create_dir_all(&installation_folder).unwrap();
+        let executable_path = installation_folder.join(format!("{}.exe", name));
+        fs::write(&executable_path, "exe").unwrap();
+        let mut package =
+            Package::test_fixture(&format!("owner/{}", name), version, &installation_folder);
+        package.install_info.executable_path = Some(executable_path);
+        package
+    }
+
+    fn examine<F>(fix: bool, db: &PackageDatabase, statics: &Statics, check: F) -> Vec<Problem>
+    where
+        F: FnOnce(&mut Doctor) -> Result<(), SengetErrors>,
+    {
+        let mut doctor = Doctor::new(fix, db, statics);
+        check(&mut doctor).ok().unwrap();
+        doctor.problems
+    }
+
+    fn statuses(problems: &[Problem]) -> Vec<ProblemStatus> {
+        problems.iter().map(|p| p.status).collect()
+    }
+
+    #[test]
+    fn finds_moved_executables_and_missing_versions() {
+        let (db, statics) = doctor_fixture("doctor-executables");
+        let mut package = create_package(&statics, "tool", "1.0.0");
+        let installation_folder = package.install_info.installation_folder.clone().unwrap();
+        let moved_executable_path = installation_folder.join("bin").join("tool.exe");
+        fs::create_dir_all(moved_executable_path.parent().unwrap()).unwrap();
+        fs::rename(installation_folder.join("tool.exe"), &moved_executable_path).unwrap();
+        package.other_versions.push(InstalledVersion {
+            version: "0.9.0".to_owned(),
+            install_info: InstallInfo::test_fixture(&installation_folder.with_file_name("0.9.0")),
+        });
+        db.add_package(package).unwrap();
+
+        let problems = examine(false, &db, &statics, |d| d.check_executables());
+        assert_eq!(
+            statuses(&problems),
+            [ProblemStatus::Manual, ProblemStatus::Fixable]
+        );
+        assert!(problems[0].description.contains("version 0.9.0"));
+        assert!(problems[1]
+            .description
+            .ends_with(&format!("found {}", moved_executable_path.display())));
+
+        let problems = examine(true, &db, &statics, |d| d.check_executables());
+        assert_eq!(problems[1].status, ProblemStatus::Fixed);
+        let package = db.find_package("tool").ok().unwrap().unwrap();
+        assert_eq!(
+            package.install_info.executable_path,
+            Some(moved_executable_path)
+        );
+    }
+
+    #[test]
+    fn rebuilds_missing_and_stray_shims() {
+        let (db, statics) = doctor_fixture("doctor-shims");
+        db.add_package(create_package(&statics, "tool", "1.0.0"))
+            .unwrap();
+        let shims_folder = &statics.shims_folder_path;
+        Shim::new("removed", Path::new("removed.exe"), shims_folder)
+            .create()
+            .unwrap();
+
+        let problems = examine(false, &db, &statics, |d| d.check_shims());
+        let descriptions = problems
+            .iter()
+            .map(|p| p.description.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(descriptions[0], "Shim for tool is missing or outdated");
+        assert!(descriptions[1].contains("removed.cmd"));
+        assert_eq!(statuses(&problems), [ProblemStatus::Fixable; 2]);
+
+        let problems = examine(true, &db, &statics, |d| d.check_shims());
+        assert_eq!(statuses(&problems), [ProblemStatus::Fixed; 2]);
+        assert!(examine(false, &db, &statics, |d| d.check_shims()).is_empty());
+    }
+
+    #[test]
+    fn removes_shortcut_files_of_packages_that_are_gone() {
+        let (db, statics) = doctor_fixture("doctor-shortcuts");
+        let startmenu_folder = &statics.startmenu_folders.appdata;
+        let gone_shortcut_path = startmenu_folder.join("gone.lnk");
+        let gone_executable_path = statics.packages_folder_path.join("gone").join("gone.exe");
+        create_test_shortcut(&gone_shortcut_path, &gone_executable_path);
+        // Shortcut files of programs senget doesn't manage are left alone even if they dangle
+        let other_shortcut_path = statics.desktop_folder_path.join("other.lnk");
+        let other_executable_path = statics.desktop_folder_path.join("other.exe");
+        create_test_shortcut(&other_shortcut_path, &other_executable_path);
+
+        let problems = examine(false, &db, &statics, |d| d.check_shortcuts());
+        assert_eq!(statuses(&problems), [ProblemStatus::Fixable]);
+        assert!(problems[0].description.contains("gone.lnk"));
+        assert!(gone_shortcut_path.is_file());
+
+        let problems = examine(true, &db, &statics, |d| d.check_shortcuts());
+        assert_eq!(statuses(&problems), [ProblemStatus::Fixed]);
+        assert!(!gone_shortcut_path.exists());
+        assert!(other_shortcut_path.is_file());
+    }
+
+    #[test]
+    fn cleans_up_incomplete_downloads_and_leftover_folders() {
+        let (db, statics) = doctor_fixture("doctor-leftovers");
+        let mut package = create_package(&statics, "tool", "1.0.0");
+        package.install_info.source = Some(DistSource {
+            file_title: "tool-1.0.0.zip".to_owned(),
+            file_size: 3,
+            download_url: "https://github.com/owner/tool/tool-1.0.0.zip".to_owned(),
+            sha256: None,
+        });
+        db.add_package(package).unwrap();
+        let dists_folder = &statics.dists_folder_path;
+        fs::write(dists_folder.join("tool-1.0.0.zip"), "zi").unwrap();
+        fs::write(dists_folder.join("empty.zip"), "").unwrap();
+        fs::write(dists_folder.join("other.zip"), "zip").unwrap();
+        let package_folder = statics.packages_folder_path.join("tool");
+        let staging_folder = package_folder.join(".1.0.0.senget-staging");
+        let backup_folder = package_folder.join(".0.9.0.senget-backup");
+        fs::create_dir_all(&staging_folder).unwrap();
+        fs::create_dir_all(&backup_folder).unwrap();
+
+        let problems = examine(false, &db, &statics, |d| d.check_leftovers());
+        assert_eq!(statuses(&problems), [ProblemStatus::Fixable; 4]);
+
+        let problems = examine(true, &db, &statics, |d| d.check_leftovers());
+        assert_eq!(statuses(&problems), [ProblemStatus::Fixed; 4]);
+        let mut cached_files = dists_folder
+            .folder_items()
+            .unwrap()
+            .iter()
+            .map(|item| item.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        cached_files.sort();
+        assert_eq!(cached_files, ["other.zip"]);
+        assert!(!staging_folder.exists());
+        // The backup of a version that's missing is moved back instead of deleted
+        assert!(!backup_folder.exists());
+        assert!(package_folder.join("0.9.0").is_dir());
+    }
+}
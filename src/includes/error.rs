@@ -10,7 +10,7 @@ use std::io;
 use zip::result::ZipError;
 
 use crate::eprintln_pretty;
-
+use crate::includes::hook::HookKind;
 
 pub struct ExportFileNotFoundError;
 
@@ -20,12 +20,29 @@ impl fmt::Debug for ExportFileNotFoundError {
     }
 }
 
-pub struct NoExeFoundInZipError;
-impl fmt::Debug for NoExeFoundInZipError {
+pub struct ExecutableNotFoundError;
+impl fmt::Debug for ExecutableNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The executable passed with --exe doesn't exist in the unpacked zip file"
+        )
+    }
+}
+pub struct MaliciousZipError {
+    reason: String,
+}
+impl MaliciousZipError {
+    pub fn new(reason: String) -> Self {
+        Self { reason }
+    }
+}
+impl fmt::Debug for MaliciousZipError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "No executable found in the unpacked zip file")
+        write!(f, "Refusing to unpack the zip file cause {}.", self.reason)
     }
 }
+
 pub struct PrivilegeError;
 impl fmt::Debug for PrivilegeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -96,6 +113,248 @@ impl fmt::Debug for VersionAlreadyInstalledError {
     }
 }
 
+pub struct CannotMoveInstallerError;
+impl fmt::Debug for CannotMoveInstallerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Installer packages can't be moved, uninstall then reinstall the package with --location instead.")
+    }
+}
+
+pub struct LocationNotEmptyError;
+impl fmt::Debug for LocationNotEmptyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The location already exists and isn't empty.")
+    }
+}
+
+pub struct LocationInsidePackageError;
+impl fmt::Debug for LocationInsidePackageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The location is inside the folder the package is already in."
+        )
+    }
+}
+
+pub struct PersistedMoveAcrossDrivesError;
+impl fmt::Debug for PersistedMoveAcrossDrivesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Packages with persisted files can't be moved to another drive, pick a location on the same drive instead."
+        )
+    }
+}
+
+pub struct SideBySideInstallerError;
+impl fmt::Debug for SideBySideInstallerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Only exe and zip packages can have multiple versions installed side by side."
+        )
+    }
+}
+
+pub struct NoInstalledVersionError;
+impl fmt::Debug for NoInstalledVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The version of the package isn't installed.")
+    }
+}
+
+pub struct BinaryNotFoundError;
+impl fmt::Debug for BinaryNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "A binary passed with --bin doesn't exist in the package, paths are relative to the installation folder.")
+    }
+}
+
+pub struct NoPackageProvidesCommandError;
+impl fmt::Debug for NoPackageProvidesCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No installed package provides the command.")
+    }
+}
+
+pub struct InvalidPersistRuleError;
+impl fmt::Debug for InvalidPersistRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Persist rules have to be paths inside the installation folder."
+        )
+    }
+}
+
+pub struct ShortcutNotFoundError;
+impl fmt::Debug for ShortcutNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The package has no shortcut file made by senget with that name there."
+        )
+    }
+}
+
+pub struct InvalidVersionConstraintError;
+impl fmt::Debug for InvalidVersionConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid version constraint, use comma separated comparators like \">=1.2, <2\" or a version prefix like \"1.2\".")
+    }
+}
+
+pub struct PackageNotPinnedError;
+impl fmt::Debug for PackageNotPinnedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The package isn't pinned.")
+    }
+}
+
+pub struct VersionOutsideConstraintError;
+impl fmt::Debug for VersionOutsideConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The version is outside the package's version constraint, pass a new constraint to change it.")
+    }
+}
+
+pub struct HookFailedError {
+    pub kind: HookKind,
+    pub reason: String,
+}
+impl fmt::Debug for HookFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The {} hook failed, {}.", self.kind, self.reason)
+    }
+}
+
+pub struct CorruptDatabaseError {
+    source: serde_json::Error,
+}
+impl CorruptDatabaseError {
+    pub fn new(source: serde_json::Error) -> Self {
+        Self { source }
+    }
+}
+impl fmt::Debug for CorruptDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The package database is corrupted ({}), run \"senget db restore\" to restore it from its backup.", self.source)
+    }
+}
+
+pub struct NoDatabaseBackupError;
+impl fmt::Debug for NoDatabaseBackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The package database has no backup yet.")
+    }
+}
+
+pub struct PackageChangedError;
+impl fmt::Debug for PackageChangedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The package was changed by another senget process, try again."
+        )
+    }
+}
+
+pub struct NewerDatabaseSchemaError;
+impl fmt::Debug for NewerDatabaseSchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The package database was saved by a newer version of senget, update senget to use it."
+        )
+    }
+}
+
+pub struct InvalidHistoryDateError;
+impl fmt::Debug for InvalidHistoryDateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid date, use a date like 2024-01-31 or a duration like 7d or 12h."
+        )
+    }
+}
+
+pub struct NoPreviousVersionError;
+impl fmt::Debug for NoPreviousVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The package has no previous version to roll back to.")
+    }
+}
+
+pub struct AmbiguousNameError {
+    pub name: String,
+    pub candidates: Vec<String>,
+}
+impl fmt::Debug for AmbiguousNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Multiple packages are named {}, use one of {} instead.",
+            self.name,
+            self.candidates.join(", ")
+        )
+    }
+}
+
+/// A package wasn't found but one with a similar name was
+pub struct DidYouMeanError {
+    pub installed: bool,
+    pub suggestion: String,
+}
+impl fmt::Debug for DidYouMeanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.installed {
+            true => write!(f, "No installed package with the given name found."),
+            false => write!(f, "No package with the given name found."),
+        }?;
+        write!(f, " Did you mean {}?", self.suggestion)
+    }
+}
+
+pub struct AdoptFolderNotFoundError;
+impl fmt::Debug for AdoptFolderNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The folder to adopt doesn't exist.")
+    }
+}
+
+pub struct AdoptFolderInUseError;
+impl fmt::Debug for AdoptFolderInUseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The folder is already used by an installed package or is inside senget's packages folder.")
+    }
+}
+
+pub struct UnknownAdoptedVersionError;
+impl fmt::Debug for UnknownAdoptedVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Couldn't find a version like 1.2.3 in the folder name or the one passed with --version."
+        )
+    }
+}
+
+/// Several executables could be the main one and there's no terminal to ask which
+pub struct AmbiguousExecutableError {
+    pub candidates: Vec<String>,
+}
+impl fmt::Debug for AmbiguousExecutableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Found multiple executables: {}. Pass the main one with --exe <relative path>.",
+            self.candidates.join(", ")
+        )
+    }
+}
+
 pub struct NoExecutableError;
 impl fmt::Debug for NoExecutableError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -103,6 +362,16 @@ impl fmt::Debug for NoExecutableError {
     }
 }
 
+pub struct NoInstallationFolderError;
+impl fmt::Debug for NoInstallationFolderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The package has no installation folder recorded, reinstall it to fix this."
+        )
+    }
+}
+
 pub enum SengetErrors {
     RequestError(reqwest::Error),
     IoError(io::Error),
@@ -113,6 +382,7 @@ pub enum SengetErrors {
     NetworkError(NetworkError),
     PrivilegeError(PrivilegeError),
     NoExecutableError(NoExecutableError),
+    NoInstallationFolderError(NoInstallationFolderError),
     NoInstalledPackageError(NoInstalledPackageError),
     FailedToUninstallError(FailedToUninstallError),
     AlreadyUptoDateError(AlreadyUptoDateError),
@@ -120,7 +390,34 @@ pub enum SengetErrors {
     NoPackageError(NoPackageError),
     NoValidDistError(NoValidDistError),
     PackageAlreadyInstalledError(PackageAlreadyInstalledError),
-    NoExeFound(NoExeFoundInZipError),
+    ExecutableNotFound(ExecutableNotFoundError),
+    MaliciousZipError(MaliciousZipError),
+    CannotMoveInstallerError(CannotMoveInstallerError),
+    LocationNotEmptyError(LocationNotEmptyError),
+    SideBySideInstallerError(SideBySideInstallerError),
+    NoInstalledVersionError(NoInstalledVersionError),
+    BinaryNotFoundError(BinaryNotFoundError),
+    NoPackageProvidesCommandError(NoPackageProvidesCommandError),
+    InvalidPersistRuleError(InvalidPersistRuleError),
+    ShortcutNotFoundError(ShortcutNotFoundError),
+    InvalidVersionConstraintError(InvalidVersionConstraintError),
+    PackageNotPinnedError(PackageNotPinnedError),
+    VersionOutsideConstraintError(VersionOutsideConstraintError),
+    HookFailedError(HookFailedError),
+    CorruptDatabaseError(CorruptDatabaseError),
+    NoDatabaseBackupError(NoDatabaseBackupError),
+    PackageChangedError(PackageChangedError),
+    NewerDatabaseSchemaError(NewerDatabaseSchemaError),
+    InvalidHistoryDateError(InvalidHistoryDateError),
+    NoPreviousVersionError(NoPreviousVersionError),
+    AmbiguousNameError(AmbiguousNameError),
+    DidYouMeanError(DidYouMeanError),
+    AdoptFolderNotFoundError(AdoptFolderNotFoundError),
+    UnknownAdoptedVersionError(UnknownAdoptedVersionError),
+    AmbiguousExecutableError(AmbiguousExecutableError),
+    LocationInsidePackageError(LocationInsidePackageError),
+    PersistedMoveAcrossDrivesError(PersistedMoveAcrossDrivesError),
+    AdoptFolderInUseError(AdoptFolderInUseError),
     ExportFileNotFoundError(ExportFileNotFoundError),
 }
 
@@ -128,6 +425,7 @@ impl fmt::Debug for SengetErrors {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SengetErrors::NoExecutableError(err) => write!(f, "{:?}", err),
+            SengetErrors::NoInstallationFolderError(err) => write!(f, "{:?}", err),
             SengetErrors::RequestError(err) => write!(f, "{:?}", err),
             SengetErrors::IoError(err) => write!(f, "{:?}", err),
             SengetErrors::PrivilegeError(err) => write!(f, "{:?}", err),
@@ -139,7 +437,34 @@ impl fmt::Debug for SengetErrors {
             SengetErrors::NoValidDistError(err) => write!(f, "{:?}", err),
             SengetErrors::PackageAlreadyInstalledError(err) => write!(f, "{:?}", err),
             SengetErrors::NetworkError(err) => write!(f, "{:?}", err),
-            SengetErrors::NoExeFound(err) => write!(f, "{:?}", err),
+            SengetErrors::ExecutableNotFound(err) => write!(f, "{:?}", err),
+            SengetErrors::MaliciousZipError(err) => write!(f, "{:?}", err),
+            SengetErrors::CannotMoveInstallerError(err) => write!(f, "{:?}", err),
+            SengetErrors::LocationNotEmptyError(err) => write!(f, "{:?}", err),
+            SengetErrors::SideBySideInstallerError(err) => write!(f, "{:?}", err),
+            SengetErrors::NoInstalledVersionError(err) => write!(f, "{:?}", err),
+            SengetErrors::BinaryNotFoundError(err) => write!(f, "{:?}", err),
+            SengetErrors::NoPackageProvidesCommandError(err) => write!(f, "{:?}", err),
+            SengetErrors::InvalidPersistRuleError(err) => write!(f, "{:?}", err),
+            SengetErrors::ShortcutNotFoundError(err) => write!(f, "{:?}", err),
+            SengetErrors::InvalidVersionConstraintError(err) => write!(f, "{:?}", err),
+            SengetErrors::PackageNotPinnedError(err) => write!(f, "{:?}", err),
+            SengetErrors::VersionOutsideConstraintError(err) => write!(f, "{:?}", err),
+            SengetErrors::HookFailedError(err) => write!(f, "{:?}", err),
+            SengetErrors::CorruptDatabaseError(err) => write!(f, "{:?}", err),
+            SengetErrors::NoDatabaseBackupError(err) => write!(f, "{:?}", err),
+            SengetErrors::PackageChangedError(err) => write!(f, "{:?}", err),
+            SengetErrors::NewerDatabaseSchemaError(err) => write!(f, "{:?}", err),
+            SengetErrors::InvalidHistoryDateError(err) => write!(f, "{:?}", err),
+            SengetErrors::NoPreviousVersionError(err) => write!(f, "{:?}", err),
+            SengetErrors::AmbiguousNameError(err) => write!(f, "{:?}", err),
+            SengetErrors::DidYouMeanError(err) => write!(f, "{:?}", err),
+            SengetErrors::AdoptFolderNotFoundError(err) => write!(f, "{:?}", err),
+            SengetErrors::UnknownAdoptedVersionError(err) => write!(f, "{:?}", err),
+            SengetErrors::AmbiguousExecutableError(err) => write!(f, "{:?}", err),
+            SengetErrors::LocationInsidePackageError(err) => write!(f, "{:?}", err),
+            SengetErrors::PersistedMoveAcrossDrivesError(err) => write!(f, "{:?}", err),
+            SengetErrors::AdoptFolderInUseError(err) => write!(f, "{:?}", err),
             SengetErrors::SerdeError(err) => write!(f, "{:?}", err),
             SengetErrors::ExportFileNotFoundError(err) => write!(f, "{:?}", err),
             SengetErrors::MSLinkError(err) => write!(f, "{:?}", err),
@@ -185,6 +510,12 @@ impl From<NoExecutableError> for SengetErrors {
         SengetErrors::NoExecutableError(err)
     }
 }
+
+impl From<NoInstallationFolderError> for SengetErrors {
+    fn from(err: NoInstallationFolderError) -> Self {
+        SengetErrors::NoInstallationFolderError(err)
+    }
+}
 impl From<FailedToUninstallError> for SengetErrors {
     fn from(err: FailedToUninstallError) -> Self {
         SengetErrors::FailedToUninstallError(err)
@@ -243,9 +574,145 @@ impl From<ZipError> for SengetErrors {
         SengetErrors::ZipError(err)
     }
 }
-impl From<NoExeFoundInZipError> for SengetErrors {
-    fn from(err: NoExeFoundInZipError) -> Self {
-        SengetErrors::NoExeFound(err)
+impl From<ExecutableNotFoundError> for SengetErrors {
+    fn from(err: ExecutableNotFoundError) -> Self {
+        SengetErrors::ExecutableNotFound(err)
+    }
+}
+impl From<MaliciousZipError> for SengetErrors {
+    fn from(err: MaliciousZipError) -> Self {
+        SengetErrors::MaliciousZipError(err)
+    }
+}
+impl From<CannotMoveInstallerError> for SengetErrors {
+    fn from(err: CannotMoveInstallerError) -> Self {
+        SengetErrors::CannotMoveInstallerError(err)
+    }
+}
+impl From<LocationNotEmptyError> for SengetErrors {
+    fn from(err: LocationNotEmptyError) -> Self {
+        SengetErrors::LocationNotEmptyError(err)
+    }
+}
+impl From<SideBySideInstallerError> for SengetErrors {
+    fn from(err: SideBySideInstallerError) -> Self {
+        SengetErrors::SideBySideInstallerError(err)
+    }
+}
+impl From<NoInstalledVersionError> for SengetErrors {
+    fn from(err: NoInstalledVersionError) -> Self {
+        SengetErrors::NoInstalledVersionError(err)
+    }
+}
+impl From<BinaryNotFoundError> for SengetErrors {
+    fn from(err: BinaryNotFoundError) -> Self {
+        SengetErrors::BinaryNotFoundError(err)
+    }
+}
+impl From<NoPackageProvidesCommandError> for SengetErrors {
+    fn from(err: NoPackageProvidesCommandError) -> Self {
+        SengetErrors::NoPackageProvidesCommandError(err)
+    }
+}
+impl From<InvalidPersistRuleError> for SengetErrors {
+    fn from(err: InvalidPersistRuleError) -> Self {
+        SengetErrors::InvalidPersistRuleError(err)
+    }
+}
+impl From<ShortcutNotFoundError> for SengetErrors {
+    fn from(err: ShortcutNotFoundError) -> Self {
+        SengetErrors::ShortcutNotFoundError(err)
+    }
+}
+impl From<InvalidVersionConstraintError> for SengetErrors {
+    fn from(err: InvalidVersionConstraintError) -> Self {
+        SengetErrors::InvalidVersionConstraintError(err)
+    }
+}
+impl From<PackageNotPinnedError> for SengetErrors {
+    fn from(err: PackageNotPinnedError) -> Self {
+        SengetErrors::PackageNotPinnedError(err)
+    }
+}
+impl From<VersionOutsideConstraintError> for SengetErrors {
+    fn from(err: VersionOutsideConstraintError) -> Self {
+        SengetErrors::VersionOutsideConstraintError(err)
+    }
+}
+impl From<HookFailedError> for SengetErrors {
+    fn from(err: HookFailedError) -> Self {
+        SengetErrors::HookFailedError(err)
+    }
+}
+impl From<CorruptDatabaseError> for SengetErrors {
+    fn from(err: CorruptDatabaseError) -> Self {
+        SengetErrors::CorruptDatabaseError(err)
+    }
+}
+impl From<NoDatabaseBackupError> for SengetErrors {
+    fn from(err: NoDatabaseBackupError) -> Self {
+        SengetErrors::NoDatabaseBackupError(err)
+    }
+}
+impl From<PackageChangedError> for SengetErrors {
+    fn from(err: PackageChangedError) -> Self {
+        SengetErrors::PackageChangedError(err)
+    }
+}
+impl From<NewerDatabaseSchemaError> for SengetErrors {
+    fn from(err: NewerDatabaseSchemaError) -> Self {
+        SengetErrors::NewerDatabaseSchemaError(err)
+    }
+}
+impl From<InvalidHistoryDateError> for SengetErrors {
+    fn from(err: InvalidHistoryDateError) -> Self {
+        SengetErrors::InvalidHistoryDateError(err)
+    }
+}
+impl From<NoPreviousVersionError> for SengetErrors {
+    fn from(err: NoPreviousVersionError) -> Self {
+        SengetErrors::NoPreviousVersionError(err)
+    }
+}
+impl From<AmbiguousNameError> for SengetErrors {
+    fn from(err: AmbiguousNameError) -> Self {
+        SengetErrors::AmbiguousNameError(err)
+    }
+}
+impl From<DidYouMeanError> for SengetErrors {
+    fn from(err: DidYouMeanError) -> Self {
+        SengetErrors::DidYouMeanError(err)
+    }
+}
+impl From<AdoptFolderNotFoundError> for SengetErrors {
+    fn from(err: AdoptFolderNotFoundError) -> Self {
+        SengetErrors::AdoptFolderNotFoundError(err)
+    }
+}
+impl From<UnknownAdoptedVersionError> for SengetErrors {
+    fn from(err: UnknownAdoptedVersionError) -> Self {
+        SengetErrors::UnknownAdoptedVersionError(err)
+    }
+}
+impl From<AmbiguousExecutableError> for SengetErrors {
+    fn from(err: AmbiguousExecutableError) -> Self {
+        SengetErrors::AmbiguousExecutableError(err)
+    }
+}
+impl From<LocationInsidePackageError> for SengetErrors {
+    fn from(err: LocationInsidePackageError) -> Self {
+        SengetErrors::LocationInsidePackageError(err)
+    }
+}
+
+impl From<PersistedMoveAcrossDrivesError> for SengetErrors {
+    fn from(err: PersistedMoveAcrossDrivesError) -> Self {
+        SengetErrors::PersistedMoveAcrossDrivesError(err)
+    }
+}
+impl From<AdoptFolderInUseError> for SengetErrors {
+    fn from(err: AdoptFolderInUseError) -> Self {
+        SengetErrors::AdoptFolderInUseError(err)
     }
 }
 
@@ -6,7 +6,8 @@ use crate::{
         SearchResponseJson,
     },
     includes::{
-        dist::{Dist, DistType, PackageInfo},
+        constraint::VersionConstraint,
+        dist::{Dist, DistPreference, DistType, PackageInfo},
         utils::Take,
     },
 };
@@ -15,6 +16,8 @@ use regex::{self, Regex};
 use serde::{Deserialize, Serialize};
 
 const GITHUB_API_ENTRY_POINT: &str = "https://api.github.com";
+/// The most releases GitHub returns in a page
+const RELEASES_PER_PAGE: usize = 100;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Repo {
@@ -74,22 +77,79 @@ impl Repo {
         Regex::new(r"(\d+(\.\d+)*)").unwrap()
     }
 
+    async fn get_releases_page(
+        &self,
+        client: &reqwest::Client,
+        page: usize,
+    ) -> Result<ReleasesResponseJson, reqwest::Error> {
+        let url = self.generate_endpoint(&format!(
+            "releases?per_page={}&page={}",
+            RELEASES_PER_PAGE, page
+        ));
+        client.get(url).send().await?.json().await
+    }
+
+    /// Releases from the newest one, GitHub only returns a page of them at a time so pages are
+    /// fetched until one has a version that's allowed or they run out
+    async fn get_releases<F>(
+        &self,
+        client: &reqwest::Client,
+        version_regex: &Regex,
+        allows: F,
+    ) -> Result<ReleasesResponseJson, reqwest::Error>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut releases = Vec::new();
+        let mut page = 1;
+        loop {
+            let page_releases = self.get_releases_page(client, page).await?;
+            let is_last_page = page_releases.len() < RELEASES_PER_PAGE;
+            let has_allowed_version = page_releases
+                .iter()
+                .filter_map(|r| Repo::parse_version(&r.tag_name, version_regex))
+                .any(&allows);
+            releases.extend(page_releases);
+            if has_allowed_version || is_last_page {
+                return Ok(releases);
+            }
+            page += 1;
+        }
+    }
+
+    /// Versions of the releases up to the newest page with a version that's allowed
+    pub async fn get_versions<F>(
+        &self,
+        client: &reqwest::Client,
+        version_regex: &Regex,
+        allows: F,
+    ) -> Result<Vec<String>, reqwest::Error>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let versions = self
+            .get_releases(client, version_regex, allows)
+            .await?
+            .iter()
+            .filter_map(|r| Repo::parse_version(&r.tag_name, version_regex))
+            .map(String::from)
+            .collect();
+        Ok(versions)
+    }
+
     async fn get_assets_by_version(
         &self,
         version: &str,
         client: &reqwest::Client,
         version_regex: &Regex,
     ) -> Result<Option<(AssetsResponseJson, String)>, reqwest::Error> {
-        let url = self.generate_endpoint("releases");
-        let releases_response_json: ReleasesResponseJson =
-            client.get(url).send().await?.json().await?;
-        if releases_response_json.is_empty() {
-            return Ok(None);
-        }
         let parsed_version = match Repo::parse_version(version, version_regex) {
             None => return Ok(None),
             Some(v) => v,
         };
+        let releases_response_json = self
+            .get_releases(client, version_regex, |v| v == parsed_version)
+            .await?;
         for r in releases_response_json {
             let curr_ver = match Repo::parse_version(&r.tag_name, version_regex) {
                 None => continue,
@@ -173,53 +233,50 @@ impl Repo {
         None
     }
 
-    fn find_preferred_dist(
-        preferred_dist_type: &Option<DistType>,
+    /// The first asset of the preferred type, the best one otherwise ranking windows assets,
+    /// then portable types, then assets named exactly like the repo first
+    fn pick_asset_info(
+        dist_preference: &DistPreference,
         mut asset_infos: Vec<AssetInfo>,
+    ) -> Option<AssetInfo> {
+        if let DistPreference::Only(dist_type) | DistPreference::Prefer(dist_type) = dist_preference
+        {
+            let index = asset_infos.iter().position(|ai| ai.dist_type == *dist_type);
+            match (index, dist_preference) {
+                (Some(index), _) => return asset_infos.take(index),
+                (None, DistPreference::Only(_)) => return None,
+                (None, _) => {}
+            }
+        }
+        asset_infos.sort_by_key(|a| !a.file_title.contains("win"));
+        asset_infos.sort_by(|a, b| b.dist_type.partial_cmp(&a.dist_type).unwrap());
+        asset_infos.sort_by_key(|ai| !ai.is_exact_match);
+        asset_infos.take(0)
+    }
+
+    fn find_preferred_dist(
+        dist_preference: &DistPreference,
+        asset_infos: Vec<AssetInfo>,
         repo_name: String,
         version: String,
     ) -> Option<Dist> {
-        match preferred_dist_type {
-            None => {
-                asset_infos.sort_by_key(|a| !a.file_title.contains("win"));
-                asset_infos.sort_by(|a, b| b.dist_type.partial_cmp(&a.dist_type).unwrap());
-                asset_infos.sort_by_key(|ai| !ai.is_exact_match);
-                let asset_info = asset_infos.take(0).unwrap();
-                let dist = PackageInfo::new(
-                    repo_name,
-                    asset_info.download_url,
-                    version,
-                    asset_info.file_title,
-                    asset_info.file_size as u64,
-                )
-                .fetch_dist(asset_info.dist_type);
-                Some(dist)
-            }
-
-            Some(pref_inst) => {
-                let dist = asset_infos
-                    .iter()
-                    .find(|ai| ai.dist_type == *pref_inst)
-                    .map(|ai| {
-                        let pi = PackageInfo::new(
-                            repo_name,
-                            ai.download_url.clone(),
-                            version,
-                            ai.file_title.clone(),
-                            ai.file_size as u64,
-                        );
-                        pi.fetch_dist(ai.dist_type.clone())
-                    });
-                dist
-            }
-        }
+        let asset_info = Repo::pick_asset_info(dist_preference, asset_infos)?;
+        let dist = PackageInfo::new(
+            repo_name,
+            asset_info.download_url,
+            version,
+            asset_info.file_title,
+            asset_info.file_size as u64,
+        )
+        .fetch_dist(asset_info.dist_type);
+        Some(dist)
     }
 
     fn parse_assets_for_distributable(
         &self,
         assets: AssetsResponseJson,
         version: String,
-        preferred_dist_type: &Option<DistType>,
+        dist_preference: &DistPreference,
     ) -> Option<Dist> {
         let repo_name_lower = self.name.to_lowercase();
         let asset_infos: Vec<AssetInfo> = assets
@@ -229,7 +286,7 @@ impl Repo {
         if asset_infos.is_empty() {
             return None;
         };
-        Repo::find_preferred_dist(preferred_dist_type, asset_infos, self.name.clone(), version)
+        Repo::find_preferred_dist(dist_preference, asset_infos, self.name.clone(), version)
     }
 
     pub async fn get_dist(
@@ -237,7 +294,7 @@ impl Repo {
         client: &reqwest::Client,
         version: &str,
         version_regex: &Regex,
-        preferred_dist_type: &Option<DistType>,
+        dist_preference: &DistPreference,
     ) -> Result<Option<Dist>, reqwest::Error> {
         let (assets, parsed_version) = match self
             .get_assets_by_version(version, client, version_regex)
@@ -246,13 +303,40 @@ impl Repo {
             None => return Ok(None),
             Some(asset_url_and_version) => asset_url_and_version,
         };
-        Ok(self.parse_assets_for_distributable(assets, parsed_version, preferred_dist_type))
+        Ok(self.parse_assets_for_distributable(assets, parsed_version, dist_preference))
+    }
+    /// Distributable of the newest release the constraint allows
+    pub async fn get_constrained_dist(
+        &self,
+        client: &reqwest::Client,
+        constraint: &VersionConstraint,
+        version_regex: &Regex,
+        dist_preference: &DistPreference,
+    ) -> Result<Option<Dist>, reqwest::Error> {
+        let releases_response_json = self
+            .get_releases(client, version_regex, |v| constraint.allows(v))
+            .await?;
+        let versions = releases_response_json
+            .iter()
+            .filter_map(|r| Repo::parse_version(&r.tag_name, version_regex))
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let version = match constraint.newest_allowed(&versions) {
+            None => return Ok(None),
+            Some(v) => v.to_owned(),
+        };
+        let assets = releases_response_json
+            .into_iter()
+            .find(|r| Repo::parse_version(&r.tag_name, version_regex) == Some(version.as_str()))
+            .map(|r| r.assets)
+            .unwrap_or_default();
+        Ok(self.parse_assets_for_distributable(assets, version, dist_preference))
     }
     pub async fn get_latest_dist(
         &self,
         client: &reqwest::Client,
         version_regex: &Regex,
-        preferred_dist_type: &Option<DistType>,
+        dist_preference: &DistPreference,
     ) -> Result<Option<Dist>, reqwest::Error> {
         let url = self.generate_endpoint("releases/latest");
         let response = client.get(url).send().await?;
@@ -264,7 +348,7 @@ impl Repo {
             return Ok(self.parse_assets_for_distributable(
                 release_response_json.assets,
                 version.to_owned(),
-                preferred_dist_type,
+                dist_preference,
             ));
         }
         Ok(None)
@@ -291,6 +375,20 @@ fn extract_repo(repo_response_json: RepoResponseJson) -> Repo {
         repo_response_json.license.and_then(|l| l.name),
     )
 }
+/// None if there's no repo with the full name
+pub async fn get_repo(
+    full_name: &str,
+    client: &reqwest::Client,
+) -> Result<Option<Repo>, reqwest::Error> {
+    let url = format!("{GITHUB_API_ENTRY_POINT}/repos/{full_name}");
+    let response = client.get(url).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let repo_response_json: RepoResponseJson = response.error_for_status()?.json().await?;
+    Ok(Some(extract_repo(repo_response_json)))
+}
+
 pub async fn search(query: &str, client: &reqwest::Client) -> Result<Vec<Repo>, reqwest::Error> {
     let url = format!("{GITHUB_API_ENTRY_POINT}/search/repositories?q={query}&per_page=10");
     let search_response_json: SearchResponseJson = client.get(url).send().await?.json().await?;
@@ -302,3 +400,49 @@ pub async fn search(query: &str, client: &reqwest::Client) -> Result<Vec<Repo>,
     Ok(results)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset_info(file_title: &str, dist_type: DistType) -> AssetInfo {
+        AssetInfo {
+            file_title: file_title.to_owned(),
+            download_url: format!("https://github.com/Senpwai/releases/{}", file_title),
+            file_size: 1024,
+            dist_type,
+            is_exact_match: true,
+        }
+    }
+
+    fn picked_file_title(dist_preference: DistPreference) -> Option<String> {
+        let asset_infos = vec![
+            asset_info("Senpwai-win.zip", DistType::Zip),
+            asset_info("Senpwai-win.exe", DistType::Exe),
+        ];
+        Repo::pick_asset_info(&dist_preference, asset_infos).map(|ai| ai.file_title)
+    }
+
+    #[test]
+    fn picks_the_preferred_dist_type() {
+        let zip = Some("Senpwai-win.zip".to_owned());
+        assert_eq!(picked_file_title(DistPreference::Only(DistType::Zip)), zip);
+        assert_eq!(
+            picked_file_title(DistPreference::Prefer(DistType::Zip)),
+            zip
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_best_dist_only_for_a_soft_preference() {
+        let exe = Some("Senpwai-win.exe".to_owned());
+        assert_eq!(picked_file_title(DistPreference::Any), exe);
+        assert_eq!(
+            picked_file_title(DistPreference::Prefer(DistType::Installer)),
+            exe
+        );
+        assert_eq!(
+            picked_file_title(DistPreference::Only(DistType::Installer)),
+            None
+        );
+    }
+}
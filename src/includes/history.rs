@@ -0,0 +1,255 @@
+//!Keeps an append only journal of the installs, updates and uninstalls senget carried out
+
+use crate::includes::{
+    dist::{Dist, DistSource, DistType},
+    error::{InvalidHistoryDateError, SengetErrors},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryAction {
+    Install,
+    Update,
+    Rollback,
+    Uninstall,
+    Switch,
+    Move,
+    Purge,
+    Adopt,
+}
+
+impl fmt::Display for HistoryAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryAction::Install => write!(f, "install"),
+            HistoryAction::Update => write!(f, "update"),
+            HistoryAction::Rollback => write!(f, "rollback"),
+            HistoryAction::Uninstall => write!(f, "uninstall"),
+            HistoryAction::Switch => write!(f, "switch"),
+            HistoryAction::Move => write!(f, "move"),
+            HistoryAction::Purge => write!(f, "purge"),
+            HistoryAction::Adopt => write!(f, "adopt"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryOutcome {
+    Succeeded,
+    Failed(String),
+}
+
+impl fmt::Display for HistoryOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistoryOutcome::Succeeded => write!(f, "Succeeded"),
+            HistoryOutcome::Failed(reason) => write!(f, "Failed: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    pub action: HistoryAction,
+    pub package: String,
+    pub from_version: Option<String>,
+    pub to_version: Option<String>,
+    pub dist_type: Option<DistType>,
+    pub asset_url: Option<String>,
+    /// Sha256 of the downloaded distributable
+    pub sha256: Option<String>,
+    pub outcome: HistoryOutcome,
+}
+
+pub fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Days since the unix epoch of a date in the proleptic gregorian calendar
+fn days_from_date(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146097 + day_of_era).saturating_sub(719468)
+}
+
+fn date_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// In UTC e.g., 2024-01-31 18:05
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (year, month, day) = date_from_days(timestamp / SECONDS_PER_DAY);
+    let seconds_of_day = timestamp % SECONDS_PER_DAY;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60
+    )
+}
+
+/// A UTC date like 2024-01-31, or a duration before now like 7d, 12h or 2w
+pub fn parse_since(since: &str, now: u64) -> Result<u64, InvalidHistoryDateError> {
+    let since = since.trim();
+    let duration_unit = match since.chars().last() {
+        Some('h') => Some(3600),
+        Some('d') => Some(SECONDS_PER_DAY),
+        Some('w') => Some(7 * SECONDS_PER_DAY),
+        _ => None,
+    };
+    if let Some(unit) = duration_unit {
+        let amount: u64 = since[..since.len() - 1]
+            .parse()
+            .map_err(|_| InvalidHistoryDateError)?;
+        return Ok(now.saturating_sub(amount * unit));
+    }
+    let date = since
+        .split('-')
+        .map(|s| s.parse().ok())
+        .collect::<Option<Vec<u64>>>();
+    match date.as_deref() {
+        Some(&[year, month, day])
+            if year >= 1970 && (1..=12).contains(&month) && (1..=31).contains(&day) =>
+        {
+            Ok(days_from_date(year, month, day) * SECONDS_PER_DAY)
+        }
+        _ => Err(InvalidHistoryDateError),
+    }
+}
+
+impl HistoryEntry {
+    pub fn new(action: HistoryAction, package: &str, from_version: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: current_timestamp(),
+            action,
+            package: package.to_owned(),
+            from_version: from_version.map(|v| v.to_owned()),
+            to_version: None,
+            dist_type: None,
+            asset_url: None,
+            sha256: None,
+            outcome: HistoryOutcome::Succeeded,
+        }
+    }
+
+    /// Records the version, type, url and hash of the distributable being installed
+    pub fn with_dist(mut self, dist: &Dist, source: &DistSource) -> HistoryEntry {
+        self.to_version = Some(dist.version().to_owned());
+        self.dist_type = Some(dist.dist_type());
+        self.asset_url = Some(source.download_url.clone());
+        self.sha256 = source.sha256.clone();
+        self
+    }
+
+    pub fn with_outcome<T>(mut self, result: &Result<T, SengetErrors>) -> HistoryEntry {
+        if let Err(err) = result {
+            self.outcome = HistoryOutcome::Failed(format!("{:?}", err));
+        }
+        self
+    }
+}
+
+pub struct History {
+    journal_path: PathBuf,
+}
+
+impl History {
+    pub fn new(config_dir: &Path) -> History {
+        History {
+            journal_path: config_dir.join("history.jsonl"),
+        }
+    }
+
+    /// Each entry is appended as a single line so earlier entries are never rewritten
+    pub fn record(&self, entry: &HistoryEntry) -> Result<(), SengetErrors> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        File::options()
+            .create(true)
+            .append(true)
+            .open(&self.journal_path)?
+            .write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Oldest first, lines that don't parse like one cut short by a crash are skipped
+    pub fn entries(
+        &self,
+        package: Option<&str>,
+        since: Option<u64>,
+    ) -> Result<Vec<HistoryEntry>, io::Error> {
+        if !self.journal_path.is_file() {
+            return Ok(Vec::new());
+        }
+        let package_lower = package.map(|p| p.to_lowercase());
+        let entries = fs::read_to_string(&self.journal_path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok())
+            .filter(|e| {
+                package_lower
+                    .as_ref()
+                    .is_none_or(|p| e.package.to_lowercase() == *p)
+            })
+            .filter(|e| since.is_none_or(|s| e.timestamp >= s))
+            .collect();
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_timestamps_as_utc_dates() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        // 2024-02-29 13:37:59
+        assert_eq!(format_timestamp(1709213879), "2024-02-29 13:37");
+    }
+
+    #[test]
+    fn parses_dates_and_durations() {
+        let now = 1709213879;
+        assert_eq!(parse_since("2024-02-29", now).ok(), Some(1709164800));
+        assert_eq!(parse_since("1970-01-01", now).ok(), Some(0));
+        assert_eq!(parse_since("2d", now).ok(), Some(now - 2 * SECONDS_PER_DAY));
+        assert_eq!(parse_since("12h", now).ok(), Some(now - 12 * 3600));
+        assert!(parse_since("2024-13-01", now).is_err());
+        assert!(parse_since("yesterday", now).is_err());
+    }
+}
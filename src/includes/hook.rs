@@ -0,0 +1,139 @@
+//!Runs the commands a package is set to run after installing or updating and before uninstalling
+
+use crate::includes::{error::HookFailedError, package::Package, utils::Cmd};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{fmt, os::windows::process::CommandExt, process::Command};
+
+#[derive(ValueEnum, Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookKind {
+    PostInstall,
+    PostUpdate,
+    PreUninstall,
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookKind::PostInstall => write!(f, "post-install"),
+            HookKind::PostUpdate => write!(f, "post-update"),
+            HookKind::PreUninstall => write!(f, "pre-uninstall"),
+        }
+    }
+}
+
+/// Commands run through cmd in the installation folder of the package
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hooks {
+    #[serde(default)]
+    pub post_install: Option<String>,
+    #[serde(default)]
+    pub post_update: Option<String>,
+    #[serde(default)]
+    pub pre_uninstall: Option<String>,
+}
+
+impl Hooks {
+    pub fn get(&self, kind: HookKind) -> Option<&String> {
+        match kind {
+            HookKind::PostInstall => self.post_install.as_ref(),
+            HookKind::PostUpdate => self.post_update.as_ref(),
+            HookKind::PreUninstall => self.pre_uninstall.as_ref(),
+        }
+    }
+
+    pub fn set(&mut self, kind: HookKind, command: Option<String>) {
+        match kind {
+            HookKind::PostInstall => self.post_install = command,
+            HookKind::PostUpdate => self.post_update = command,
+            HookKind::PreUninstall => self.pre_uninstall = command,
+        }
+    }
+
+    /// The package's name, installation folder and version are passed through SENGET_PKG_NAME,
+    /// SENGET_PKG_DIR and SENGET_PKG_VERSION
+    pub fn run(&self, kind: HookKind, package: &Package) -> Result<(), HookFailedError> {
+        let command = match self.get(kind) {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+        let installation_folder = package
+            .install_info
+            .installation_folder
+            .clone()
+            .unwrap_or_default();
+        let mut hook = Command::cmd();
+        // Passed as is so quotes in the command reach cmd unescaped
+        hook.raw_arg(command)
+            .env("SENGET_PKG_NAME", &package.repo.name)
+            .env("SENGET_PKG_DIR", &installation_folder)
+            .env("SENGET_PKG_VERSION", &package.version);
+        if installation_folder.is_dir() {
+            hook.current_dir(&installation_folder);
+        }
+        let reason = match hook.status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => format!("it exited with {}", status),
+            Err(err) => err.to_string(),
+        };
+        Err(HookFailedError { kind, reason })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+    use std::fs;
+
+    #[test]
+    fn displays_each_kind_as_its_cli_name() {
+        for kind in HookKind::value_variants() {
+            let cli_name = kind.to_possible_value().unwrap();
+            assert_eq!(kind.to_string(), cli_name.get_name());
+        }
+        assert_eq!(
+            HookKind::from_str("pre-uninstall", false),
+            Ok(HookKind::PreUninstall)
+        );
+    }
+
+    #[test]
+    fn sets_and_gets_each_kind_separately() {
+        let mut hooks = Hooks::default();
+        hooks.set(HookKind::PostUpdate, Some("echo updated".to_owned()));
+        assert_eq!(hooks.get(HookKind::PostInstall), None);
+        assert_eq!(
+            hooks.get(HookKind::PostUpdate),
+            Some(&"echo updated".to_owned())
+        );
+        hooks.set(HookKind::PostUpdate, None);
+        assert_eq!(hooks, Hooks::default());
+    }
+
+    #[test]
+    fn runs_the_hook_in_the_installation_folder_with_the_package_env() {
+        let installation_folder = create_test_folder("hook-run");
+        let package = Package::test_fixture("SenZmaKi/package", "1.0.0", &installation_folder);
+        let mut hooks = Hooks::default();
+        assert!(hooks.run(HookKind::PostInstall, &package).is_ok());
+        hooks.set(
+            HookKind::PostInstall,
+            Some("echo %SENGET_PKG_NAME% %SENGET_PKG_VERSION%> hook.txt".to_owned()),
+        );
+        assert!(hooks.run(HookKind::PostInstall, &package).is_ok());
+        let hook_output = fs::read_to_string(installation_folder.join("hook.txt")).unwrap();
+        assert_eq!(hook_output.trim(), "package 1.0.0");
+    }
+
+    #[test]
+    fn fails_with_the_exit_code_of_the_hook() {
+        let installation_folder = create_test_folder("hook-fail");
+        let package = Package::test_fixture("SenZmaKi/package", "1.0.0", &installation_folder);
+        let mut hooks = Hooks::default();
+        hooks.set(HookKind::PreUninstall, Some("exit 3".to_owned()));
+        let err = hooks.run(HookKind::PreUninstall, &package).unwrap_err();
+        assert_eq!(err.kind, HookKind::PreUninstall);
+        assert!(err.reason.contains('3'));
+    }
+}
@@ -0,0 +1,233 @@
+//!Records the files an exe or zip package installed so uninstalling only removes those and the
+//!installation can be verified later
+
+use crate::includes::utils::FolderItems;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the installation folder so it survives moving the package
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub missing: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub extra: Vec<PathBuf>,
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, io::Error> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Every file in the folder and its subfolders, relative to the folder
+fn relative_file_paths(folder: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_owned()];
+    while let Some(current_folder) = folders.pop() {
+        for item in current_folder.folder_items()? {
+            let path = item.path();
+            if path.is_dir() {
+                folders.push(path);
+            } else if let Ok(relative_path) = path.strip_prefix(folder) {
+                files.push(relative_path.to_owned());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+impl Manifest {
+    pub fn record(folder: &Path) -> Result<Manifest, io::Error> {
+        let files = relative_file_paths(folder)?
+            .into_iter()
+            .map(|path| {
+                let full_path = folder.join(&path);
+                Ok(ManifestEntry {
+                    size: full_path.metadata()?.len(),
+                    sha256: sha256_file(&full_path)?,
+                    path,
+                })
+            })
+            .collect::<Result<Vec<ManifestEntry>, io::Error>>()?;
+        Ok(Manifest { files })
+    }
+
+    /// Files under the ignored paths aren't reported as extra e.g., persisted user data
+    pub fn verify(&self, folder: &Path, ignored: &[PathBuf]) -> Result<VerifyReport, io::Error> {
+        let mut report = VerifyReport::default();
+        for entry in self.files.iter() {
+            let path = folder.join(&entry.path);
+            if !path.is_file() {
+                report.missing.push(entry.path.clone());
+            // Checking the size first skips hashing files that obviously changed
+            } else if path.metadata()?.len() != entry.size || sha256_file(&path)? != entry.sha256 {
+                report.modified.push(entry.path.clone());
+            }
+        }
+        if folder.is_dir() {
+            let recorded = self
+                .files
+                .iter()
+                .map(|e| &e.path)
+                .collect::<HashSet<&PathBuf>>();
+            report.extra = relative_file_paths(folder)?
+                .into_iter()
+                .filter(|path| {
+                    !recorded.contains(path) && !ignored.iter().any(|i| path.starts_with(i))
+                })
+                .collect();
+        }
+        Ok(report)
+    }
+
+    /// Removes only the recorded files then the folders they leave empty, anything else the
+    /// user put in the folder is left alone
+    pub fn remove_files(&self, folder: &Path) -> Result<(), io::Error> {
+        let mut parent_folders = HashSet::new();
+        for entry in self.files.iter() {
+            // A tampered record must never point outside the folder
+            if !entry
+                .path
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+            {
+                continue;
+            }
+            let path = folder.join(&entry.path);
+            if path.is_file() {
+                fs::remove_file(&path)?;
+            }
+            let mut parent = path.parent();
+            while let Some(p) = parent.filter(|p| p.starts_with(folder)) {
+                parent_folders.insert(p.to_owned());
+                parent = p.parent();
+            }
+        }
+        parent_folders.insert(folder.to_owned());
+        // Deepest first so a folder is only checked once its subfolders are gone
+        let mut parent_folders = parent_folders.into_iter().collect::<Vec<PathBuf>>();
+        parent_folders.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+        for parent_folder in parent_folders {
+            if parent_folder.is_dir() && parent_folder.folder_items()?.is_empty() {
+                fs::remove_dir(parent_folder)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+
+    /// Creates a folder with a couple of files and records it
+    fn create_fixture(test_name: &str) -> (PathBuf, Manifest) {
+        let folder = create_test_folder(test_name);
+        fs::create_dir_all(folder.join("bin")).unwrap();
+        fs::write(folder.join("package.exe"), "exe").unwrap();
+        fs::write(folder.join("bin").join("tool.exe"), "tool").unwrap();
+        let manifest = Manifest::record(&folder).unwrap();
+        (folder, manifest)
+    }
+
+    #[test]
+    fn records_every_file_with_its_size_and_hash() {
+        let (folder, manifest) = create_fixture("manifest-record");
+        let paths = manifest
+            .files
+            .iter()
+            .map(|e| e.path.clone())
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("bin").join("tool.exe"),
+                PathBuf::from("package.exe")
+            ]
+        );
+        let package_exe = &manifest.files[1];
+        assert_eq!(package_exe.size, 3);
+        assert_eq!(
+            package_exe.sha256,
+            sha256_file(&folder.join("package.exe")).unwrap()
+        );
+    }
+
+    #[test]
+    fn verifies_missing_modified_and_extra_files() {
+        let (folder, manifest) = create_fixture("manifest-verify");
+        let report = manifest.verify(&folder, &[]).unwrap();
+        assert!(report.missing.is_empty() && report.modified.is_empty());
+        assert!(report.extra.is_empty());
+
+        fs::remove_file(folder.join("bin").join("tool.exe")).unwrap();
+        // Same size so only the hash can tell
+        fs::write(folder.join("package.exe"), "EXE").unwrap();
+        fs::write(folder.join("notes.txt"), "notes").unwrap();
+        fs::create_dir_all(folder.join("data")).unwrap();
+        fs::write(folder.join("data").join("settings.json"), "{}").unwrap();
+        let report = manifest.verify(&folder, &[PathBuf::from("data")]).unwrap();
+        assert_eq!(report.missing, vec![Path::new("bin").join("tool.exe")]);
+        assert_eq!(report.modified, vec![PathBuf::from("package.exe")]);
+        assert_eq!(report.extra, vec![PathBuf::from("notes.txt")]);
+    }
+
+    #[test]
+    fn removes_only_the_recorded_files() {
+        let (folder, manifest) = create_fixture("manifest-remove");
+        fs::write(folder.join("notes.txt"), "notes").unwrap();
+        manifest.remove_files(&folder).unwrap();
+        assert!(!folder.join("package.exe").exists());
+        // Emptied folders go but the folder with the user's file stays
+        assert!(!folder.join("bin").exists());
+        assert!(folder.join("notes.txt").is_file());
+
+        let (folder, manifest) = create_fixture("manifest-remove-all");
+        manifest.remove_files(&folder).unwrap();
+        assert!(!folder.exists());
+    }
+
+    #[test]
+    fn skips_entries_pointing_outside_the_folder() {
+        let test_folder = create_test_folder("manifest-tampered");
+        let folder = test_folder.join("package");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(test_folder.join("victim.txt"), "victim").unwrap();
+        let outside_paths = [
+            PathBuf::from("..").join("victim.txt"),
+            test_folder.join("victim.txt"),
+        ];
+        let manifest = Manifest {
+            files: outside_paths
+                .into_iter()
+                .map(|path| ManifestEntry {
+                    path,
+                    size: 6,
+                    sha256: String::new(),
+                })
+                .collect(),
+        };
+        manifest.remove_files(&folder).unwrap();
+        assert!(test_folder.join("victim.txt").is_file());
+    }
+}
@@ -1,10 +1,22 @@
 //!Manages installed package uninstallation and update
 
 use crate::includes::{
-    dist::{Dist, DistType, StartmenuFolders},
-    error::SengetErrors,
-    senget_manager::env::remove_package_folder_from_senget_env_var,
-    utils::PathStr,
+    constraint::VersionConstraint,
+    dist::{
+        Dist, DistPreference, DistType, InstallOptions, InstallerDist, StartmenuFolders, ZipDist,
+    },
+    error::{
+        CannotMoveInstallerError, LocationInsidePackageError, LocationNotEmptyError,
+        NoInstallationFolderError, NoInstalledVersionError, NoPreviousVersionError,
+        PersistedMoveAcrossDrivesError, SengetErrors, SideBySideInstallerError,
+    },
+    history::format_timestamp,
+    hook::{HookKind, Hooks},
+    pin::Pin,
+    shim::Shim,
+    shortcut::{find_shortcut_files, read_shortcut, ShortcutFile},
+    transaction::{generate_staging_folder_path, Transaction},
+    utils::{format_size, CopyDirAll, FilenameLower, FolderItems, PathStr},
 };
 use crate::{dist::InstallInfo, github::api::Repo};
 use core::fmt;
@@ -12,11 +24,13 @@ use regex::Regex;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
-use std::{io, process::Command};
+use std::path::{Path, PathBuf};
+use std::{io, iter, process::Command};
 use winreg::RegKey;
 
 pub const MSI_EXEC: &str = "MsiExec.exe";
+/// How many versions a package can be rolled back through
+const MAX_PREVIOUS_VERSIONS: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportedPackage {
@@ -24,6 +38,15 @@ pub struct ExportedPackage {
     pub version: String,
     pub preferred_dist_type: DistType,
     pub create_shortcut_file: bool,
+    #[serde(default)]
+    pub constraint: Option<VersionConstraint>,
+}
+
+/// A version of a package that's installed side by side with the active one
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledVersion {
+    pub version: String,
+    pub install_info: InstallInfo,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -31,6 +54,28 @@ pub struct Package {
     pub version: String,
     pub repo: Repo,
     pub install_info: InstallInfo,
+    #[serde(default)]
+    pub other_versions: Vec<InstalledVersion>,
+    /// Keeps `update all` from moving the package off the versions it allows
+    #[serde(default)]
+    pub pin: Option<Pin>,
+    /// Versions updates stay inside of, set by installing or updating with a constraint
+    #[serde(default)]
+    pub constraint: Option<VersionConstraint>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Versions the package was updated from, the most recent one last
+    #[serde(default)]
+    pub previous_versions: Vec<InstalledVersion>,
+    /// Seconds since the unix epoch, None in packages installed before it was recorded
+    #[serde(default)]
+    pub installed_at: Option<u64>,
+    /// When the package was last updated or rolled back
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    /// Bytes the installed versions take up on disk
+    #[serde(default)]
+    pub size: Option<u64>,
 }
 
 impl fmt::Display for Package {
@@ -42,15 +87,82 @@ impl fmt::Display for Package {
             &self.version,
             &self.install_info.dist_type,
             self.installation_folder_str()
-        )
+        )?;
+        if !self.other_versions.is_empty() {
+            let other_versions = self
+                .other_versions
+                .iter()
+                .map(|v| v.version.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ");
+            write!(f, "\nOther Installed Versions: {}", other_versions)?;
+        }
+        if let Some(previous_version) = self.previous_versions.last() {
+            write!(f, "\nPrevious Version: {}", previous_version.version)?;
+        }
+        if let Some(size) = self.size {
+            write!(f, "\nSize: {}", format_size(size))?;
+        }
+        if let Some(installed_at) = self.installed_at {
+            write!(f, "\nInstalled At: {} UTC", format_timestamp(installed_at))?;
+        }
+        if let Some(updated_at) = self.updated_at {
+            write!(f, "\nUpdated At: {} UTC", format_timestamp(updated_at))?;
+        }
+        if let Some(source) = &self.install_info.source {
+            write!(
+                f,
+                "\nSource Asset: {}\nAsset URL: {}",
+                source.file_title, source.download_url
+            )?;
+            if let Some(sha256) = &source.sha256 {
+                write!(f, "\nSha256: {}", sha256)?;
+            }
+        }
+        if let Some(constraint) = &self.constraint {
+            write!(f, "\nVersion Constraint: {}", constraint)?;
+        }
+        if let Some(pin) = &self.pin {
+            write!(f, "\nPinned To: {}", pin)?;
+        }
+        for kind in [
+            HookKind::PostInstall,
+            HookKind::PostUpdate,
+            HookKind::PreUninstall,
+        ] {
+            if let Some(command) = self.hooks.get(kind) {
+                write!(f, "\nHook {}: {}", kind, command)?;
+            }
+        }
+        Ok(())
     }
 }
+
+/// Points paths in the old folder to the same place in the new folder
+fn rebase_install_info(install_info: &mut InstallInfo, old_folder: &Path, new_folder: &Path) {
+    let rebase = |path: &PathBuf| {
+        path.strip_prefix(old_folder)
+            .map(|relative_path| new_folder.join(relative_path))
+            .unwrap_or_else(|_| path.to_owned())
+    };
+    install_info.executable_path = install_info.executable_path.as_ref().map(rebase);
+    install_info.installation_folder = install_info.installation_folder.as_ref().map(rebase);
+}
+
 impl Package {
     pub fn new(version: String, repo: Repo, install_info: InstallInfo) -> Package {
         Package {
             version,
             repo,
             install_info,
+            other_versions: Vec::new(),
+            pin: None,
+            constraint: None,
+            hooks: Hooks::default(),
+            previous_versions: Vec::new(),
+            installed_at: None,
+            updated_at: None,
+            size: None,
         }
     }
     pub fn export(&self) -> ExportedPackage {
@@ -59,6 +171,7 @@ impl Package {
             version: self.version.clone(),
             preferred_dist_type: self.install_info.dist_type.clone(),
             create_shortcut_file: self.install_info.create_shortcut_file,
+            constraint: self.constraint.clone(),
         }
     }
     pub fn installation_folder_str(&self) -> String {
@@ -91,25 +204,400 @@ impl Package {
             }
         }
     }
-    pub fn uninstall(&self, startmenu_appdata_folder: &Path) -> Result<bool, io::Error> {
-        if let Some(installation_folder) = self.install_info.installation_folder.as_ref() {
-            remove_package_folder_from_senget_env_var(
-                &installation_folder.path_str().unwrap_or_default(),
-            )?
-        };
+    pub fn uninstall(
+        &self,
+        packages_folder_path: &Path,
+        startmenu_appdata_folder: &Path,
+        shims_folder: &Path,
+    ) -> Result<bool, io::Error> {
+        for shim in Shim::from_install_info(&self.install_info, shims_folder) {
+            shim.remove()?;
+        }
+        for shortcut in self.shortcut_files(startmenu_appdata_folder) {
+            shortcut.remove()?;
+        }
         if self.install_info.dist_type == DistType::Installer {
             return self.uninstall_installer_distributable();
         };
-        let installation_folder = self.install_info.installation_folder.as_ref().unwrap();
-        if installation_folder.is_dir() {
-            fs::remove_dir_all(installation_folder)?;
+        self.install_info
+            .remove_installed_files(packages_folder_path)?;
+        for other_version in self.other_versions.iter() {
+            other_version
+                .install_info
+                .remove_installed_files(packages_folder_path)?;
         }
-        let shortcut_file_path = startmenu_appdata_folder.join(format!("{}.lnk", self.repo.name));
-        if shortcut_file_path.is_file() {
-            fs::remove_file(shortcut_file_path)?;
+        if let Some(package_folder) = self.package_folder() {
+            if package_folder.is_dir() && package_folder.folder_items()?.is_empty() {
+                fs::remove_dir(package_folder)?;
+            }
         }
         Ok(true)
     }
+
+    /// Shortcut files senget made for the package, packages installed before they were recorded
+    /// only have the startmenu one
+    pub fn shortcut_files(&self, startmenu_appdata_folder: &Path) -> Vec<ShortcutFile> {
+        let install_info = &self.install_info;
+        if install_info.shortcuts.is_empty()
+            && install_info.create_shortcut_file
+            && install_info.dist_type != DistType::Installer
+        {
+            return vec![ShortcutFile::new(
+                &self.repo.name,
+                startmenu_appdata_folder,
+                None,
+            )];
+        }
+        install_info.shortcuts.clone()
+    }
+    /// Folder containing every installed version of an exe or zip package, the adopted folder
+    /// itself if the package was adopted
+    pub fn package_folder(&self) -> Option<PathBuf> {
+        let installation_folder = self.install_info.installation_folder.as_ref()?;
+        match !self.install_info.adopted && self.is_in_versioned_folder() {
+            true => installation_folder.parent().map(PathBuf::from),
+            false => Some(installation_folder.to_owned()),
+        }
+    }
+
+    fn is_in_versioned_folder(&self) -> bool {
+        self.install_info
+            .installation_folder
+            .as_ref()
+            .is_some_and(|f| f.filename_lower() == self.version.to_lowercase())
+    }
+
+    /// Packages installed before every version got its own subfolder live directly in their
+    /// package folder, adopted folders are left where the user put them
+    pub fn needs_versioned_folder_migration(&self) -> bool {
+        self.install_info.dist_type != DistType::Installer
+            && !self.install_info.adopted
+            && !self.is_in_versioned_folder()
+    }
+
+    /// Moves the package from its package folder into a subfolder for its version, every step is
+    /// undone if the transaction is rolled back
+    pub fn migrate_to_versioned_folder(
+        &self,
+        startmenu_appdata_folder: &Path,
+        shims_folder: &Path,
+    ) -> Result<(Package, Transaction), SengetErrors> {
+        let package_folder = self
+            .install_info
+            .installation_folder
+            .as_ref()
+            .ok_or(NoInstallationFolderError)?;
+        let version_folder = package_folder.join(&self.version);
+        // Move it out of the way first since the version folder is inside the package folder
+        let staging_folder = generate_staging_folder_path(package_folder)?;
+        fs::remove_dir(&staging_folder)?;
+        let mut transaction = Transaction::new();
+        // Rolling back runs these in reverse so each one undoes a step while the ones before
+        // it are still in place
+        fs::rename(package_folder, &staging_folder)?;
+        let (from, to) = (staging_folder.clone(), package_folder.clone());
+        transaction.on_rollback(move || fs::rename(from, to));
+        fs::create_dir(package_folder)?;
+        let created_folder = package_folder.clone();
+        transaction.on_rollback(move || fs::remove_dir(created_folder));
+        fs::rename(&staging_folder, &version_folder)?;
+        let (from, to) = (version_folder.clone(), staging_folder);
+        transaction.on_rollback(move || fs::rename(from, to));
+
+        let mut package = self.clone();
+        rebase_install_info(&mut package.install_info, package_folder, &version_folder);
+        package.activate(
+            self,
+            startmenu_appdata_folder,
+            shims_folder,
+            &mut transaction,
+        )?;
+        Ok((package, transaction))
+    }
+
+    /// Points the shims and shortcut files at the package's executables instead of the previous
+    /// ones, they point back at the previous ones if the transaction is rolled back
+    fn activate(
+        &self,
+        previous_package: &Package,
+        startmenu_appdata_folder: &Path,
+        shims_folder: &Path,
+        transaction: &mut Transaction,
+    ) -> Result<(), SengetErrors> {
+        self.replace_shims(Some(previous_package), shims_folder, transaction)?;
+        if let Some(ep) = &self.install_info.executable_path {
+            for shortcut in self.shortcut_files(startmenu_appdata_folder) {
+                shortcut.create_in_transaction(
+                    ep,
+                    previous_package.install_info.executable_path.clone(),
+                    transaction,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Points the shims at the package's executables, the previous package's shims are only
+    /// removed once the transaction is committed
+    pub fn replace_shims(
+        &self,
+        previous_package: Option<&Package>,
+        shims_folder: &Path,
+        transaction: &mut Transaction,
+    ) -> Result<(), io::Error> {
+        let shims = Shim::from_install_info(&self.install_info, shims_folder);
+        if let Some(previous_package) = previous_package {
+            for previous_shim in
+                Shim::from_install_info(&previous_package.install_info, shims_folder)
+            {
+                // If the names match the new shim just overwrites it
+                if !shims.iter().any(|s| s.has_same_name(&previous_shim)) {
+                    transaction.on_commit(move || previous_shim.remove());
+                }
+            }
+        }
+        for shim in shims {
+            shim.create_in_transaction(transaction)?;
+        }
+        Ok(())
+    }
+
+    /// Install info of the installed version, None if the version isn't installed
+    pub fn version_install_info(&self, version: &str) -> Option<&InstallInfo> {
+        if self.version == version {
+            return Some(&self.install_info);
+        }
+        self.other_versions
+            .iter()
+            .find(|v| v.version == version)
+            .map(|v| &v.install_info)
+    }
+
+    /// Makes one of the side by side installed versions the active one
+    pub fn switch_version(
+        &self,
+        version: &str,
+        startmenu_appdata_folder: &Path,
+        shims_folder: &Path,
+    ) -> Result<(Package, Transaction), SengetErrors> {
+        let idx = match self
+            .other_versions
+            .iter()
+            .position(|v| v.version == version)
+        {
+            Some(idx) => idx,
+            None => return Err(NoInstalledVersionError.into()),
+        };
+        let mut package = self.clone();
+        let target = package.other_versions.remove(idx);
+        package.other_versions.push(InstalledVersion {
+            version: self.version.clone(),
+            install_info: self.install_info.clone(),
+        });
+        package.version = target.version;
+        package.install_info = target.install_info;
+        // The shortcut files belong to the package rather than a version
+        package.install_info.shortcuts = self.shortcut_files(startmenu_appdata_folder);
+        let mut transaction = Transaction::new();
+        package.activate(
+            self,
+            startmenu_appdata_folder,
+            shims_folder,
+            &mut transaction,
+        )?;
+        Ok((package, transaction))
+    }
+
+    /// The options the package was installed with, with the ones the user passed for this version
+    /// on top
+    fn side_by_side_install_options(&self, install_options: &InstallOptions) -> InstallOptions {
+        let mut merged_options = self.install_info.install_options();
+        if install_options.location.is_some() {
+            merged_options.location = install_options.location.clone();
+        }
+        if !install_options.binaries.is_empty() {
+            merged_options.binaries = install_options.binaries.clone();
+        }
+        if install_options.executable.is_some() {
+            merged_options.executable = install_options.executable.clone();
+        }
+        if !install_options.persist.is_empty() {
+            merged_options.persist = install_options.persist.clone();
+        }
+        merged_options
+    }
+
+    pub fn install_side_by_side(
+        &self,
+        dist: Dist,
+        downloaded_dist_path: &Path,
+        packages_folder_path: &Path,
+        install_options: &InstallOptions,
+    ) -> Result<(Package, Transaction), SengetErrors> {
+        let install_options = self.side_by_side_install_options(install_options);
+        let mut transaction = Transaction::new();
+        let install_info = match &dist {
+            Dist::Exe(dist) => dist.install(
+                downloaded_dist_path,
+                packages_folder_path,
+                &install_options,
+                &mut transaction,
+            )?,
+            Dist::Zip(dist) => dist.install(
+                downloaded_dist_path,
+                packages_folder_path,
+                &install_options,
+                &mut transaction,
+            )?,
+            Dist::Installer(_) => return Err(SideBySideInstallerError.into()),
+        };
+        let mut package = self.clone();
+        package.other_versions.push(InstalledVersion {
+            version: dist.version().to_owned(),
+            install_info,
+        });
+        Ok((package, transaction))
+    }
+
+    fn has_persisted_items(&self) -> bool {
+        let mut versions = self
+            .other_versions
+            .iter()
+            .chain(self.previous_versions.iter());
+        !self.install_info.persist.is_empty()
+            || versions.any(|v| !v.install_info.persist.is_empty())
+    }
+
+    /// Moves the package folder of an exe or zip package into the new folder and returns the
+    /// updated package, the package is moved back if the transaction is rolled back
+    pub fn relocate(
+        &self,
+        new_folder: &Path,
+        startmenu_appdata_folder: &Path,
+        shims_folder: &Path,
+    ) -> Result<(Package, Transaction), SengetErrors> {
+        if self.install_info.dist_type == DistType::Installer {
+            return Err(CannotMoveInstallerError.into());
+        }
+        let old_folder = self.package_folder().ok_or(NoInstallationFolderError)?;
+        // The folder would end up moved or copied into itself
+        if new_folder.starts_with(&old_folder) {
+            return Err(LocationInsidePackageError.into());
+        }
+        if new_folder.is_dir() {
+            if !new_folder.folder_items()?.is_empty() {
+                return Err(LocationNotEmptyError.into());
+            }
+            fs::remove_dir(new_folder)?;
+        }
+        if let Some(parent) = new_folder.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut transaction = Transaction::new();
+        match fs::rename(&old_folder, new_folder) {
+            Ok(()) => {
+                let (old_folder, new_folder) = (old_folder.clone(), new_folder.to_owned());
+                transaction.on_rollback(move || fs::rename(new_folder, old_folder));
+            }
+            // Renaming can't move a folder to a different drive, the old folder is only deleted
+            // once the moved package is saved. Copying would turn the persisted junctions into
+            // plain folders and deleting the old folder would take the persisted data with it
+            Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+                if self.has_persisted_items() {
+                    return Err(PersistedMoveAcrossDrivesError.into());
+                }
+                let copied_folder = new_folder.to_owned();
+                transaction.on_rollback(move || fs::remove_dir_all(copied_folder));
+                old_folder.copy_dir_all(new_folder)?;
+                let old_folder = old_folder.clone();
+                transaction.on_commit(move || fs::remove_dir_all(old_folder));
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let mut package = self.clone();
+        rebase_install_info(&mut package.install_info, &old_folder, new_folder);
+        let versions = package
+            .other_versions
+            .iter_mut()
+            .chain(package.previous_versions.iter_mut());
+        for version in versions {
+            rebase_install_info(&mut version.install_info, &old_folder, new_folder);
+        }
+        // Other versions of an adopted package still go into the packages folder
+        if !package.install_info.adopted {
+            package.install_info.location = Some(new_folder.to_owned());
+        }
+        package.activate(
+            self,
+            startmenu_appdata_folder,
+            shims_folder,
+            &mut transaction,
+        )?;
+        Ok((package, transaction))
+    }
+
+    /// Whether the uninstaller of an installer package is still there, MsiExec is always there
+    pub fn has_uninstaller(&self) -> bool {
+        match &self.install_info.uninstall_command {
+            Some(us) => {
+                let (program, _) = Package::extract_program_and_args(us);
+                program == MSI_EXEC || Path::new(&program).is_file()
+            }
+            None => false,
+        }
+    }
+
+    /// Looks for the uninstall command of an installer package again, e.g., after the program
+    /// updated itself and registered under a new key
+    pub fn redetect_uninstall_command(
+        &self,
+        user_uninstall_reg_key: &RegKey,
+        machine_uninstall_reg_key: &RegKey,
+    ) -> Result<Option<String>, io::Error> {
+        InstallerDist::find_uninstall_command(
+            &self.repo.name.to_lowercase(),
+            &self.install_info.installation_folder,
+            user_uninstall_reg_key,
+            machine_uninstall_reg_key,
+        )
+    }
+
+    /// Looks for the executable again after something like the program updating itself moved it,
+    /// installer packages are found through their Start Menu shortcut files
+    pub fn redetect_executable(
+        &self,
+        startmenu_folders: &StartmenuFolders,
+    ) -> Result<Option<PathBuf>, io::Error> {
+        let name_lower = self.repo.name.to_lowercase();
+        if self.install_info.dist_type == DistType::Installer {
+            let mut shortcut_files = find_shortcut_files(&startmenu_folders.appdata)?;
+            shortcut_files.extend(find_shortcut_files(&startmenu_folders.programdata)?);
+            let executable_path = shortcut_files
+                .iter()
+                .filter(|s| s.filename_lower().contains(&name_lower))
+                .filter_map(|s| read_shortcut(s))
+                .map(|shortcut| shortcut.target)
+                .find(|target| target.is_file() && target.filename_lower().ends_with(".exe"));
+            return Ok(executable_path);
+        }
+        let installation_folder = match self.install_info.installation_folder.as_ref() {
+            Some(folder) if folder.is_dir() => folder.to_owned(),
+            _ => return Ok(None),
+        };
+        let executables = ZipDist::rank_executables(&name_lower, installation_folder)?;
+        let previous_file_name = self
+            .install_info
+            .executable_path
+            .as_ref()
+            .map(|ep| ep.filename_lower());
+        let executable_path = executables
+            .iter()
+            .find(|exe| Some(exe.filename_lower()) == previous_file_name)
+            .or(executables.first())
+            .cloned();
+        Ok(executable_path)
+    }
+
     fn uninstall_installer_distributable(&self) -> Result<bool, io::Error> {
         match &self.install_info.uninstall_command {
             Some(us) => {
@@ -133,33 +621,137 @@ impl Package {
             None => Ok(false),
         }
     }
+    /// Prefers the package's current distributable type but falls back to the best one the release
+    /// has, only a given type is required, the latest version is the newest one its constraint
+    /// allows
     pub async fn get_dist(
         &self,
         version: &str,
+        preferred_dist_type: &Option<DistType>,
         client: &Client,
         version_regex: &Regex,
-    ) -> Result<Option<Dist>, reqwest::Error> {
-        match version {
-            "latest" => {
+    ) -> Result<Option<Dist>, SengetErrors> {
+        let dist_preference = match preferred_dist_type {
+            Some(dist_type) => DistPreference::Only(dist_type.clone()),
+            None => DistPreference::Prefer(self.install_info.dist_type.clone()),
+        };
+        let constraint = match (version, VersionConstraint::from_version_arg(version)?) {
+            (_, Some(constraint)) => Some(constraint),
+            ("latest", None) => self.constraint.clone(),
+            (_, None) => None,
+        };
+        let dist = match (version, constraint) {
+            (_, Some(constraint)) => {
+                self.repo
+                    .get_constrained_dist(client, &constraint, version_regex, &dist_preference)
+                    .await?
+            }
+            ("latest", None) => {
                 self.repo
-                    .get_latest_dist(
-                        client,
-                        version_regex,
-                        &Some(self.install_info.dist_type.clone()),
-                    )
-                    .await
-            }
-            version => {
+                    .get_latest_dist(client, version_regex, &dist_preference)
+                    .await?
+            }
+            (version, None) => {
                 self.repo
-                    .get_dist(
-                        client,
-                        version,
-                        version_regex,
-                        &Some(self.install_info.dist_type.clone()),
-                    )
-                    .await
+                    .get_dist(client, version, version_regex, &dist_preference)
+                    .await?
+            }
+        };
+        Ok(dist)
+    }
+
+    /// Removes the previous form of a package whose distributable type changed once the update
+    /// is committed, the new form is installed first so a failed update leaves the package usable
+    fn replace_previous_form(
+        &self,
+        updated_package: &mut Package,
+        packages_folder_path: &Path,
+        startmenu_folders: &StartmenuFolders,
+        transaction: &mut Transaction,
+    ) -> Result<(), SengetErrors> {
+        let default_shortcut = ShortcutFile::new(&self.repo.name, &startmenu_folders.appdata, None);
+        let updated_install_info = &mut updated_package.install_info;
+        match self.install_info.dist_type {
+            DistType::Installer => {
+                let previous_package = self.clone();
+                transaction.on_commit(move || {
+                    previous_package.uninstall_installer_distributable()?;
+                    Ok(())
+                });
+            }
+            // If the folders match the swap already replaced the previous files
+            _ if self.install_info.installation_folder
+                != updated_install_info.installation_folder =>
+            {
+                let previous_install_info = self.install_info.clone();
+                let packages_folder_path = packages_folder_path.to_owned();
+                transaction.on_commit(move || {
+                    previous_install_info.remove_installed_files(&packages_folder_path)
+                });
+            }
+            _ => {}
+        }
+        if updated_install_info.dist_type == DistType::Installer {
+            // The installer makes its own shortcut so only remove the one senget made
+            updated_install_info
+                .shortcuts
+                .retain(|s| s.path != default_shortcut.path);
+            if let Some(previous_ep) = self.install_info.executable_path.clone() {
+                transaction.on_commit(move || match read_shortcut(&default_shortcut.path) {
+                    Some(shortcut) if shortcut.target == previous_ep => default_shortcut.remove(),
+                    _ => Ok(()),
+                });
+            }
+        } else if updated_install_info.create_shortcut_file
+            && !updated_install_info.shortcuts.contains(&default_shortcut)
+        {
+            updated_install_info.shortcuts.push(default_shortcut);
+        }
+        if let Some(ep) = updated_install_info.executable_path.as_ref() {
+            for shortcut in updated_install_info.shortcuts.iter() {
+                shortcut.create_in_transaction(
+                    ep,
+                    self.install_info.executable_path.clone(),
+                    transaction,
+                )?;
+                // The previous installer's uninstaller may delete a shortcut with the same name
+                shortcut.restore_on_commit(ep, transaction);
+            }
+        }
+        if updated_package.install_info.dist_type == DistType::Installer {
+            // Installers can't have versions installed side by side
+            for other_version in std::mem::take(&mut updated_package.other_versions) {
+                let install_info = other_version.install_info;
+                let packages_folder_path = packages_folder_path.to_owned();
+                transaction
+                    .on_commit(move || install_info.remove_installed_files(&packages_folder_path));
             }
         }
+        Ok(())
+    }
+
+    /// Points the shortcut files at the new version and deletes the previous version once the
+    /// update is committed
+    fn replace_previous_version(
+        &self,
+        updated_package: &Package,
+        packages_folder_path: &Path,
+        transaction: &mut Transaction,
+    ) -> Result<(), SengetErrors> {
+        if let Some(ep) = &updated_package.install_info.executable_path {
+            for shortcut in updated_package.install_info.shortcuts.iter() {
+                shortcut.create_in_transaction(
+                    ep,
+                    self.install_info.executable_path.clone(),
+                    transaction,
+                )?;
+            }
+        }
+        let old_install_info = self.install_info.clone();
+        let packages_folder_path = packages_folder_path.to_owned();
+        transaction
+            .on_commit(move || old_install_info.remove_installed_files(&packages_folder_path));
+        Ok(())
     }
 
     pub fn install_updated_version(
@@ -170,16 +762,18 @@ impl Package {
         startmenu_folders: &StartmenuFolders,
         user_uninstall_reg_key: &RegKey,
         machine_uninstall_reg_key: &RegKey,
-    ) -> Result<Package, SengetErrors> {
+    ) -> Result<(Package, Transaction), SengetErrors> {
         /* Generation of InstallInfo is pretty wonky, for the execuable_path it checks for
         new shortcut files after installation and for uninstall_command it checks for new registry entries.
         For these reasons there won't probably be any new shortcut files/registry entries if it's an update cause
         the update will just overwride the previously existing shortcut file/registry entry*/
+        let install_options = self.install_info.install_options();
+        let mut transaction = Transaction::new();
         let (install_info, version) = match dist {
             Dist::Installer(dist) => (
                 dist.install(
                     downloaded_dist_path,
-                    self.install_info.create_shortcut_file,
+                    &install_options,
                     startmenu_folders,
                     user_uninstall_reg_key,
                     machine_uninstall_reg_key,
@@ -190,7 +784,8 @@ impl Package {
                 dist.install(
                     downloaded_dist_path,
                     packages_folder_path,
-                    self.install_info.create_shortcut_file,
+                    &install_options,
+                    &mut transaction,
                 )?,
                 dist.package_info.version,
             ),
@@ -198,11 +793,27 @@ impl Package {
                 dist.install(
                     downloaded_dist_path,
                     packages_folder_path,
-                    self.install_info.create_shortcut_file,
+                    &install_options,
+                    &mut transaction,
                 )?,
                 dist.package_info.version,
             ),
         };
+        if install_info.dist_type != self.install_info.dist_type {
+            let mut package = self.clone();
+            package.other_versions.retain(|v| v.version != version);
+            package.version = version;
+            package.install_info = install_info;
+            package.install_info.shortcuts = self.shortcut_files(&startmenu_folders.appdata);
+            package.record_previous_version(self);
+            self.replace_previous_form(
+                &mut package,
+                packages_folder_path,
+                startmenu_folders,
+                &mut transaction,
+            )?;
+            return Ok((package, transaction));
+        }
         let executable_path = install_info
             .executable_path
             .or(self.install_info.executable_path.clone());
@@ -213,16 +824,587 @@ impl Package {
             .uninstall_command
             .or(self.install_info.uninstall_command.clone());
         let preferred_dist_type = install_info.dist_type;
-        Ok(Package::new(
-            version,
-            self.repo.clone(),
-            InstallInfo {
-                executable_path,
-                installation_folder,
-                uninstall_command,
-                dist_type: preferred_dist_type,
-                create_shortcut_file: self.install_info.create_shortcut_file,
-            },
-        ))
+        let installer_kind = install_info
+            .installer_kind
+            .or(self.install_info.installer_kind);
+        let mut package = self.clone();
+        // Incase the version was previously installed side by side
+        package.other_versions.retain(|v| v.version != version);
+        package.version = version;
+        package.install_info = InstallInfo {
+            executable_path,
+            installation_folder,
+            uninstall_command,
+            dist_type: preferred_dist_type,
+            installer_kind,
+            create_shortcut_file: install_options.create_shortcut_file,
+            installer_args: install_options.installer_args,
+            interactive: install_options.interactive,
+            location: install_options.location,
+            adopted: false,
+            binaries: install_info.binaries,
+            custom_binaries: install_info.custom_binaries,
+            executable: install_info.executable,
+            manifest: install_info.manifest,
+            persist: install_info.persist,
+            shortcuts: self.shortcut_files(&startmenu_folders.appdata),
+            source: None,
+        };
+        package.record_previous_version(self);
+        if package.install_info.installation_folder != self.install_info.installation_folder {
+            self.replace_previous_version(&package, packages_folder_path, &mut transaction)?;
+        }
+        Ok((package, transaction))
+    }
+
+    /// Records the size the installed versions take up on disk, None if any of them is unknown
+    pub fn refresh_size(&mut self) {
+        let sizes = self
+            .other_versions
+            .iter()
+            .map(|v| v.install_info.disk_size())
+            .chain([self.install_info.disk_size()]);
+        self.size = sizes.sum();
+    }
+
+    /// Keeps the version the package is updated from so it can be rolled back to
+    fn record_previous_version(&mut self, previous_package: &Package) {
+        self.previous_versions.push(InstalledVersion {
+            version: previous_package.version.clone(),
+            install_info: previous_package.install_info.clone(),
+        });
+        if self.previous_versions.len() > MAX_PREVIOUS_VERSIONS {
+            self.previous_versions.remove(0);
+        }
+    }
+
+    /// Distributables of the package's versions are kept in their own folders under it so rolling
+    /// back doesn't download them again
+    fn kept_dists_folder(&self, kept_dists_folder_path: &Path) -> PathBuf {
+        kept_dists_folder_path.join(&self.repo.name)
+    }
+
+    /// Where the distributable of the version the package was last updated from is kept, None if
+    /// it isn't there
+    pub fn kept_previous_dist_path(&self, kept_dists_folder_path: &Path) -> Option<PathBuf> {
+        let previous_version = self.previous_versions.last()?;
+        let source = previous_version.install_info.source.as_ref()?;
+        let kept_dist_path = self
+            .kept_dists_folder(kept_dists_folder_path)
+            .join(&previous_version.version)
+            .join(&source.file_title);
+        kept_dist_path
+            .metadata()
+            .is_ok_and(|m| m.is_file() && m.len() == source.file_size)
+            .then_some(kept_dist_path)
+    }
+
+    /// Keeps the distributable the package was installed from so it can be rolled back to later,
+    /// the ones of versions that were dropped from the previous versions are deleted
+    pub fn keep_dist(
+        &self,
+        downloaded_dist_path: &Path,
+        kept_dists_folder_path: &Path,
+    ) -> Result<(), io::Error> {
+        let kept_dists_folder = self.kept_dists_folder(kept_dists_folder_path);
+        let version_folder = kept_dists_folder.join(&self.version);
+        let kept_dist_path =
+            version_folder.join(downloaded_dist_path.file_name().unwrap_or_default());
+        // Rolling back installs straight from the kept distributable
+        if downloaded_dist_path != kept_dist_path {
+            if version_folder.is_dir() {
+                fs::remove_dir_all(&version_folder)?;
+            }
+            fs::create_dir_all(&version_folder)?;
+            fs::rename(downloaded_dist_path, kept_dist_path)?;
+        }
+        let kept_versions = iter::once(&self.version)
+            .chain(self.previous_versions.iter().map(|v| &v.version))
+            .collect::<Vec<&String>>();
+        for item in kept_dists_folder.folder_items()? {
+            let is_kept = kept_versions.iter().any(|v| item.file_name() == v.as_str());
+            if !is_kept && item.path().is_dir() {
+                fs::remove_dir_all(item.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the distributables kept for rolling back once the package is gone
+    pub fn remove_kept_dists(&self, kept_dists_folder_path: &Path) -> Result<(), io::Error> {
+        let kept_dists_folder = self.kept_dists_folder(kept_dists_folder_path);
+        if kept_dists_folder.is_dir() {
+            fs::remove_dir_all(kept_dists_folder)?;
+        }
+        Ok(())
+    }
+
+    /// Reinstalls the version the package was last updated from with the options it was
+    /// installed with, the location and persisted files stay since they belong to the package
+    pub fn install_previous_version(
+        &self,
+        dist: Dist,
+        downloaded_dist_path: &Path,
+        packages_folder_path: &Path,
+        startmenu_folders: &StartmenuFolders,
+        user_uninstall_reg_key: &RegKey,
+        machine_uninstall_reg_key: &RegKey,
+    ) -> Result<(Package, Transaction), SengetErrors> {
+        let mut previous_versions = self.previous_versions.clone();
+        let previous_install_info = match previous_versions.pop() {
+            Some(previous_version) => previous_version.install_info,
+            None => return Err(NoPreviousVersionError.into()),
+        };
+        let mut source_package = self.clone();
+        let install_info = &mut source_package.install_info;
+        install_info.create_shortcut_file = previous_install_info.create_shortcut_file;
+        install_info.installer_args = previous_install_info.installer_args;
+        install_info.interactive = previous_install_info.interactive;
+        install_info.binaries = previous_install_info.binaries;
+        install_info.custom_binaries = previous_install_info.custom_binaries;
+        install_info.executable = previous_install_info.executable;
+        let (mut package, transaction) = source_package.install_updated_version(
+            dist,
+            downloaded_dist_path,
+            packages_folder_path,
+            startmenu_folders,
+            user_uninstall_reg_key,
+            machine_uninstall_reg_key,
+        )?;
+        package.previous_versions = previous_versions;
+        Ok((package, transaction))
+    }
+}
+
+#[cfg(test)]
+impl Package {
+    /// A zip package of the repo installed in the folder
+    pub fn test_fixture(full_name: &str, version: &str, installation_folder: &Path) -> Package {
+        let name = full_name.rsplit('/').next().unwrap_or(full_name).to_owned();
+        let repo = Repo::new(
+            name,
+            full_name.to_owned(),
+            format!("https://github.com/{}", full_name),
+            None,
+            None,
+            None,
+        );
+        Package::new(
+            version.to_owned(),
+            repo,
+            InstallInfo::test_fixture(installation_folder),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::{
+        dist::{DistSource, PackageInfo},
+        utils::create_test_folder,
+    };
+    use std::io::Write;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use zip::{write::FileOptions, ZipWriter};
+
+    /// A zip package of the version with its executable in the installation folder
+    fn create_package(installation_folder: &Path, version: &str) -> Package {
+        fs::create_dir_all(installation_folder).unwrap();
+        fs::write(installation_folder.join("package.exe"), version).unwrap();
+        let mut package = Package::test_fixture("SenZmaKi/package", version, installation_folder);
+        package.install_info.executable_path = Some(installation_folder.join("package.exe"));
+        package
+    }
+
+    fn create_shims(package: &Package, test_folder: &Path) -> PathBuf {
+        let shims_folder = test_folder.join("shims");
+        fs::create_dir_all(&shims_folder).unwrap();
+        for shim in Shim::from_install_info(&package.install_info, &shims_folder) {
+            shim.create().unwrap();
+        }
+        shims_folder
+    }
+
+    /// A zip package installed in its versioned folder with a shim for its executable, returns
+    /// the test folder and the shims folder along with it
+    fn install_fixture(test_name: &str) -> (Package, PathBuf, PathBuf) {
+        let test_folder = create_test_folder(test_name);
+        let installation_folder = test_folder.join("packages").join("package").join("1.0.0");
+        let package = create_package(&installation_folder, "1.0.0");
+        let shims_folder = create_shims(&package, &test_folder);
+        (package, test_folder, shims_folder)
+    }
+
+    fn shim_launches(shims_folder: &Path, executable_path: &Path) -> bool {
+        fs::read_to_string(shims_folder.join("package.cmd"))
+            .unwrap()
+            .contains(&executable_path.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn moves_the_package_and_its_shims_to_the_location() {
+        let (package, test_folder, shims_folder) = install_fixture("relocate");
+        let location = test_folder.join("Apps").join("package");
+        let (moved_package, transaction) = package
+            .relocate(&location, &test_folder, &shims_folder)
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        let moved_executable_path = location.join("1.0.0").join("package.exe");
+        assert!(moved_executable_path.is_file());
+        assert!(!test_folder.join("packages").join("package").exists());
+        assert_eq!(
+            moved_package.install_info.executable_path,
+            Some(moved_executable_path.clone())
+        );
+        assert_eq!(moved_package.install_info.location, Some(location));
+        assert!(shim_launches(&shims_folder, &moved_executable_path));
+    }
+
+    #[test]
+    fn rolls_back_a_moved_package_into_its_new_location() {
+        let test_folder = create_test_folder("relocate-then-rollback");
+        let package_folder = test_folder.join("packages").join("package");
+        let previous_package = create_package(&package_folder.join("1.0.0"), "1.0.0");
+        // Updating deleted the previous version's files
+        fs::remove_dir_all(package_folder.join("1.0.0")).unwrap();
+        let mut package = create_package(&package_folder.join("2.0.0"), "2.0.0");
+        package.record_previous_version(&previous_package);
+        let shims_folder = create_shims(&package, &test_folder);
+        let location = test_folder.join("Apps").join("package");
+        let (moved_package, transaction) = package
+            .relocate(&location, &test_folder, &shims_folder)
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(
+            moved_package.previous_versions[0]
+                .install_info
+                .installation_folder,
+            Some(location.join("1.0.0"))
+        );
+
+        let (dist, zip_path) = create_zip_dist(&test_folder, "1.0.0");
+        let startmenu_folders = StartmenuFolders {
+            appdata: test_folder.clone(),
+            programdata: test_folder.clone(),
+        };
+        let uninstall_reg_key = RegKey::predef(HKEY_CURRENT_USER);
+        let (rolled_back_package, transaction) = moved_package
+            .install_previous_version(
+                dist,
+                &zip_path,
+                &test_folder.join("packages"),
+                &startmenu_folders,
+                &uninstall_reg_key,
+                &uninstall_reg_key,
+            )
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        let executable_path = location.join("1.0.0").join("package.exe");
+        assert_eq!(rolled_back_package.version, "1.0.0");
+        assert_eq!(
+            rolled_back_package.install_info.executable_path,
+            Some(executable_path.clone())
+        );
+        assert!(executable_path.is_file());
+        assert!(!location.join("2.0.0").exists());
+        assert!(!package_folder.exists());
+        assert!(rolled_back_package.previous_versions.is_empty());
+    }
+
+    #[test]
+    fn moves_the_package_back_if_not_committed() {
+        let (package, test_folder, shims_folder) = install_fixture("relocate-rollback");
+        let location = test_folder.join("Apps").join("package");
+        let moved = package.relocate(&location, &test_folder, &shims_folder);
+        drop(moved);
+        let executable_path = package.install_info.executable_path.unwrap();
+        assert!(executable_path.is_file());
+        assert!(!location.exists());
+        assert!(shim_launches(&shims_folder, &executable_path));
+    }
+
+    #[test]
+    fn refuses_to_move_the_package_inside_itself_or_into_a_used_folder() {
+        let (package, test_folder, shims_folder) = install_fixture("relocate-refused");
+        let package_folder = package.package_folder().unwrap();
+        for location in [package_folder.clone(), package_folder.join("Apps")] {
+            assert!(matches!(
+                package.relocate(&location, &test_folder, &shims_folder),
+                Err(SengetErrors::LocationInsidePackageError(_))
+            ));
+        }
+        let location = test_folder.join("Apps");
+        fs::create_dir_all(&location).unwrap();
+        fs::write(location.join("notes.txt"), "notes").unwrap();
+        assert!(matches!(
+            package.relocate(&location, &test_folder, &shims_folder),
+            Err(SengetErrors::LocationNotEmptyError(_))
+        ));
+        assert!(package.install_info.executable_path.unwrap().is_file());
+    }
+
+    #[test]
+    fn refuses_to_move_or_migrate_a_package_without_an_installation_folder() {
+        let (mut package, test_folder, shims_folder) = install_fixture("relocate-no-folder");
+        package.install_info.installation_folder = None;
+        assert!(matches!(
+            package.relocate(&test_folder.join("Apps"), &test_folder, &shims_folder),
+            Err(SengetErrors::NoInstallationFolderError(_))
+        ));
+        assert!(matches!(
+            package.migrate_to_versioned_folder(&test_folder, &shims_folder),
+            Err(SengetErrors::NoInstallationFolderError(_))
+        ));
+    }
+
+    /// A package installed directly in its package folder like before versioned folders
+    fn install_unversioned_fixture(test_name: &str) -> (Package, PathBuf, PathBuf) {
+        let test_folder = create_test_folder(test_name);
+        let package = create_package(&test_folder.join("packages").join("package"), "1.0.0");
+        let shims_folder = create_shims(&package, &test_folder);
+        (package, test_folder, shims_folder)
+    }
+
+    #[test]
+    fn migrates_the_package_into_its_versioned_folder() {
+        let (package, test_folder, shims_folder) = install_unversioned_fixture("migrate");
+        assert!(package.needs_versioned_folder_migration());
+        let (migrated_package, transaction) = package
+            .migrate_to_versioned_folder(&test_folder, &shims_folder)
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        let package_folder = test_folder.join("packages").join("package");
+        let executable_path = package_folder.join("1.0.0").join("package.exe");
+        assert!(executable_path.is_file());
+        assert!(!package_folder.join("package.exe").exists());
+        assert_eq!(
+            migrated_package.install_info.executable_path,
+            Some(executable_path.clone())
+        );
+        assert!(!migrated_package.needs_versioned_folder_migration());
+        assert_eq!(migrated_package.package_folder(), Some(package_folder));
+        assert!(shim_launches(&shims_folder, &executable_path));
+    }
+
+    #[test]
+    fn undoes_the_migration_if_not_committed() {
+        let (package, test_folder, shims_folder) = install_unversioned_fixture("migrate-rollback");
+        let migrated = package.migrate_to_versioned_folder(&test_folder, &shims_folder);
+        assert!(migrated.is_ok());
+        drop(migrated);
+        let packages_folder = test_folder.join("packages");
+        let executable_path = package.install_info.executable_path.unwrap();
+        assert!(executable_path.is_file());
+        assert!(!packages_folder.join("package").join("1.0.0").exists());
+        assert_eq!(packages_folder.folder_items().unwrap().len(), 1);
+        assert!(shim_launches(&shims_folder, &executable_path));
+    }
+
+    #[test]
+    fn switches_to_a_version_installed_side_by_side() {
+        let (mut package, test_folder, shims_folder) = install_fixture("switch-version");
+        let other_version = create_package(
+            &test_folder.join("packages").join("package").join("2.0.0"),
+            "2.0.0",
+        );
+        package.other_versions.push(InstalledVersion {
+            version: other_version.version,
+            install_info: other_version.install_info.clone(),
+        });
+        assert!(matches!(
+            package.switch_version("3.0.0", &test_folder, &shims_folder),
+            Err(SengetErrors::NoInstalledVersionError(_))
+        ));
+        let (switched_package, transaction) = package
+            .switch_version("2.0.0", &test_folder, &shims_folder)
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(switched_package.version, "2.0.0");
+        assert_eq!(
+            switched_package.install_info.executable_path,
+            other_version.install_info.executable_path
+        );
+        assert_eq!(
+            switched_package.version_install_info("1.0.0"),
+            Some(&package.install_info)
+        );
+        assert!(shim_launches(
+            &shims_folder,
+            &other_version.install_info.executable_path.unwrap()
+        ));
+    }
+
+    #[test]
+    fn installs_side_by_side_with_the_options_passed_for_the_version() {
+        let mut package = Package::test_fixture(
+            "SenZmaKi/package",
+            "1.0.0",
+            Path::new("C:\\senget\\packages\\package\\1.0.0"),
+        );
+        package.install_info.create_shortcut_file = true;
+        package.install_info.persist = vec![PathBuf::from("config.json")];
+        let unchanged_options = package.side_by_side_install_options(&InstallOptions::default());
+        assert!(unchanged_options.create_shortcut_file);
+        assert_eq!(unchanged_options.location, None);
+        assert_eq!(unchanged_options.persist, [PathBuf::from("config.json")]);
+
+        let install_options = InstallOptions {
+            location: Some(PathBuf::from("D:\\Apps\\package")),
+            executable: Some(PathBuf::from("bin\\package.exe")),
+            ..InstallOptions::default()
+        };
+        let merged_options = package.side_by_side_install_options(&install_options);
+        assert!(merged_options.create_shortcut_file);
+        assert_eq!(merged_options.location, install_options.location);
+        assert_eq!(merged_options.executable, install_options.executable);
+        assert_eq!(merged_options.persist, [PathBuf::from("config.json")]);
+    }
+
+    /// A zip distributable of the version with the package's executable in it
+    fn create_zip_dist(test_folder: &Path, version: &str) -> (Dist, PathBuf) {
+        let zip_path = test_folder.join(format!("package-{}.zip", version));
+        let mut writer = ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        writer
+            .start_file("package.exe", FileOptions::default())
+            .unwrap();
+        writer.write_all(version.as_bytes()).unwrap();
+        writer.finish().unwrap();
+        let dist = Dist::Zip(ZipDist {
+            package_info: PackageInfo::new(
+                "package".to_owned(),
+                format!(
+                    "https://github.com/SenZmaKi/package/package-{}.zip",
+                    version
+                ),
+                version.to_owned(),
+                format!("package-{}.zip", version),
+                1024,
+            ),
+        });
+        (dist, zip_path)
+    }
+
+    #[test]
+    fn leaves_an_adopted_folder_in_place_when_switching_versions() {
+        let test_folder = create_test_folder("adopt-switch");
+        let adopted_folder = test_folder.join("Downloads").join("package-1.0.0");
+        fs::create_dir_all(&adopted_folder).unwrap();
+        fs::write(adopted_folder.join("package.exe"), "1.0.0").unwrap();
+        let mut package = Package::test_fixture("SenZmaKi/package", "1.0.0", &adopted_folder);
+        package.install_info =
+            ZipDist::adopt("package", &adopted_folder, &InstallOptions::default())
+                .ok()
+                .unwrap();
+        let shims_folder = create_shims(&package, &test_folder);
+        assert!(!package.needs_versioned_folder_migration());
+        assert_eq!(package.package_folder(), Some(adopted_folder.clone()));
+
+        let (dist, zip_path) = create_zip_dist(&test_folder, "2.0.0");
+        let packages_folder = test_folder.join("packages");
+        let (package, transaction) = package
+            .install_side_by_side(
+                dist,
+                &zip_path,
+                &packages_folder,
+                &InstallOptions::default(),
+            )
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+        let (switched_package, transaction) = package
+            .switch_version("2.0.0", &test_folder, &shims_folder)
+            .ok()
+            .unwrap();
+        transaction.commit().unwrap();
+
+        let executable_path = packages_folder
+            .join("package")
+            .join("2.0.0")
+            .join("package.exe");
+        assert_eq!(
+            switched_package.install_info.executable_path,
+            Some(executable_path.clone())
+        );
+        assert!(shim_launches(&shims_folder, &executable_path));
+        assert_eq!(
+            switched_package.version_install_info("1.0.0"),
+            Some(&package.install_info)
+        );
+        assert!(adopted_folder.join("package.exe").is_file());
+        assert_eq!(adopted_folder.folder_items().unwrap().len(), 1);
+    }
+
+    fn previous_version(version: &str, file_title: &str, file_size: u64) -> InstalledVersion {
+        let mut install_info = InstallInfo::test_fixture(Path::new(version));
+        install_info.source = Some(DistSource {
+            file_title: file_title.to_owned(),
+            file_size,
+            download_url: format!("https://github.com/SenZmaKi/package/{}", file_title),
+            sha256: None,
+        });
+        InstalledVersion {
+            version: version.to_owned(),
+            install_info,
+        }
+    }
+
+    #[test]
+    fn keeps_the_dists_of_the_previous_versions_only() {
+        let dists_folder = create_test_folder("keep-dist");
+        let kept_dists_folder = dists_folder.join("package");
+        for version in ["0.8.0", "0.9.0", "1.0.0"] {
+            fs::create_dir_all(kept_dists_folder.join(version)).unwrap();
+        }
+        let mut package = Package::test_fixture("SenZmaKi/package", "2.0.0", Path::new("2.0.0"));
+        package.previous_versions = vec![
+            previous_version("0.9.0", "package-0.9.0.zip", 3),
+            previous_version("1.0.0", "package-1.0.0.zip", 3),
+        ];
+        let downloaded_dist_path = dists_folder.join("package-2.0.0.zip");
+        fs::write(&downloaded_dist_path, "zip").unwrap();
+        package
+            .keep_dist(&downloaded_dist_path, &dists_folder)
+            .unwrap();
+        assert!(!downloaded_dist_path.exists());
+        assert!(kept_dists_folder
+            .join("2.0.0")
+            .join("package-2.0.0.zip")
+            .is_file());
+        let mut kept_versions = kept_dists_folder
+            .folder_items()
+            .unwrap()
+            .iter()
+            .map(|item| item.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        kept_versions.sort();
+        assert_eq!(kept_versions, ["0.9.0", "1.0.0", "2.0.0"]);
+    }
+
+    #[test]
+    fn only_uses_a_kept_dist_of_the_recorded_size() {
+        let dists_folder = create_test_folder("kept-previous-dist");
+        let mut package = Package::test_fixture("SenZmaKi/package", "2.0.0", Path::new("2.0.0"));
+        assert_eq!(package.kept_previous_dist_path(&dists_folder), None);
+        package.previous_versions = vec![previous_version("1.0.0", "package-1.0.0.zip", 3)];
+        assert_eq!(package.kept_previous_dist_path(&dists_folder), None);
+        let kept_dist_path = dists_folder
+            .join("package")
+            .join("1.0.0")
+            .join("package-1.0.0.zip");
+        fs::create_dir_all(kept_dist_path.parent().unwrap()).unwrap();
+        // Like a download that was cut off
+        fs::write(&kept_dist_path, "zi").unwrap();
+        assert_eq!(package.kept_previous_dist_path(&dists_folder), None);
+        fs::write(&kept_dist_path, "zip").unwrap();
+        assert_eq!(
+            package.kept_previous_dist_path(&dists_folder),
+            Some(kept_dist_path)
+        );
     }
 }
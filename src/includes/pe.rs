@@ -0,0 +1,413 @@
+//!Parses PE headers to identify the technology an installer was built with
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const MSI_SIGNATURE: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const DOS_SIGNATURE: &[u8] = b"MZ";
+const PE_SIGNATURE: &[u8] = b"PE\0\0";
+const DOS_HEADER_PE_OFFSET: u64 = 0x3C;
+const COFF_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+// The resource data directory is the third entry in the optional header data directories
+const RESOURCE_DATA_DIRECTORY_INDEX: usize = 2;
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+const PE32_DATA_DIRECTORIES_OFFSET: usize = 96;
+const PE32_PLUS_DATA_DIRECTORIES_OFFSET: usize = 112;
+const RT_VERSION: u32 = 16;
+const RT_MANIFEST: u32 = 24;
+const RESOURCE_SUBDIRECTORY_FLAG: u32 = 0x8000_0000;
+// Anything bigger than this is definitely not a version info or manifest resource
+const MAX_RESOURCE_SIZE: u32 = 1024 * 1024;
+// Installer payload signatures are always right at the start of the overlay, at most
+// aligned to a 512 byte boundary
+const OVERLAY_HEAD_SIZE: u64 = 4096;
+const WIX_BURN_SECTION_NAME: &[u8] = b".wixburn";
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstallerKind {
+    Inno,
+    Nsis,
+    WixBurn,
+    InstallShield,
+    Squirrel,
+    Msi,
+}
+
+struct Section {
+    name: [u8; 8],
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_data_offset: u32,
+    raw_data_size: u32,
+}
+
+struct PeFile {
+    file: File,
+    sections: Vec<Section>,
+    resource_rva: u32,
+}
+
+impl InstallerKind {
+    /// Detects the kind of installer at the path, returns None if it's a plain executable
+    pub fn detect(path: &Path) -> Result<Option<InstallerKind>, io::Error> {
+        let mut file = File::open(path)?;
+        let mut magic = [0; 8];
+        if read_at(&mut file, 0, &mut magic).is_err() {
+            return Ok(None);
+        }
+        if magic == MSI_SIGNATURE {
+            return Ok(Some(InstallerKind::Msi));
+        }
+        match PeFile::parse(file)? {
+            Some(mut pe) => pe.detect_installer_kind(),
+            None => Ok(None),
+        }
+    }
+
+    fn from_text(text: &str) -> Option<InstallerKind> {
+        if text.contains("Inno Setup") {
+            Some(InstallerKind::Inno)
+        } else if text.contains("Nullsoft") {
+            Some(InstallerKind::Nsis)
+        } else if text.contains("InstallShield") {
+            Some(InstallerKind::InstallShield)
+        } else if text.contains("Squirrel") {
+            Some(InstallerKind::Squirrel)
+        } else {
+            None
+        }
+    }
+}
+
+fn read_at(file: &mut File, offset: u64, buffer: &mut [u8]) -> Result<(), io::Error> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buffer)
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    let b = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+}
+
+impl PeFile {
+    /// Returns None if the file isn't a valid PE file
+    fn parse(mut file: File) -> Result<Option<PeFile>, io::Error> {
+        let mut dos_signature = [0; 2];
+        let mut pe_offset = [0; 4];
+        if read_at(&mut file, 0, &mut dos_signature).is_err()
+            || dos_signature != DOS_SIGNATURE
+            || read_at(&mut file, DOS_HEADER_PE_OFFSET, &mut pe_offset).is_err()
+        {
+            return Ok(None);
+        }
+        let pe_offset = u32::from_le_bytes(pe_offset) as u64;
+        let mut headers = [0; 4 + COFF_HEADER_SIZE];
+        if read_at(&mut file, pe_offset, &mut headers).is_err() || &headers[..4] != PE_SIGNATURE {
+            return Ok(None);
+        }
+        let coff_header = &headers[4..];
+        let number_of_sections = u16_at(coff_header, 2).unwrap_or_default() as usize;
+        let optional_header_size = u16_at(coff_header, 16).unwrap_or_default() as usize;
+        let optional_header_offset = pe_offset + 4 + COFF_HEADER_SIZE as u64;
+        let mut optional_header = vec![0; optional_header_size];
+        if read_at(&mut file, optional_header_offset, &mut optional_header).is_err() {
+            return Ok(None);
+        }
+        let data_directories_offset = match u16_at(&optional_header, 0) {
+            Some(PE32_MAGIC) => PE32_DATA_DIRECTORIES_OFFSET,
+            Some(PE32_PLUS_MAGIC) => PE32_PLUS_DATA_DIRECTORIES_OFFSET,
+            _ => return Ok(None),
+        };
+        let resource_rva = u32_at(
+            &optional_header,
+            data_directories_offset + RESOURCE_DATA_DIRECTORY_INDEX * 8,
+        )
+        .unwrap_or_default();
+
+        let mut section_table = vec![0; number_of_sections * SECTION_HEADER_SIZE];
+        let section_table_offset = optional_header_offset + optional_header_size as u64;
+        if read_at(&mut file, section_table_offset, &mut section_table).is_err() {
+            return Ok(None);
+        }
+        let sections = section_table
+            .chunks_exact(SECTION_HEADER_SIZE)
+            .map(|s| {
+                let mut name = [0; 8];
+                name.copy_from_slice(&s[..8]);
+                Section {
+                    name,
+                    virtual_size: u32_at(s, 8).unwrap_or_default(),
+                    virtual_address: u32_at(s, 12).unwrap_or_default(),
+                    raw_data_size: u32_at(s, 16).unwrap_or_default(),
+                    raw_data_offset: u32_at(s, 20).unwrap_or_default(),
+                }
+            })
+            .collect();
+        Ok(Some(PeFile {
+            file,
+            sections,
+            resource_rva,
+        }))
+    }
+
+    fn detect_installer_kind(&mut self) -> Result<Option<InstallerKind>, io::Error> {
+        // WiX Burn bundles always have their own section so it's the most reliable check
+        if self
+            .sections
+            .iter()
+            .any(|s| s.name.starts_with(WIX_BURN_SECTION_NAME))
+        {
+            return Ok(Some(InstallerKind::WixBurn));
+        }
+        let overlay_head = self.read_overlay_head()?;
+        if contains(&overlay_head, b"NullsoftInst") {
+            return Ok(Some(InstallerKind::Nsis));
+        }
+        if contains(&overlay_head, b"rDlPtS") || contains(&overlay_head, b"idska32\x1a") {
+            return Ok(Some(InstallerKind::Inno));
+        }
+        if contains(&overlay_head, b"InstallShield") {
+            return Ok(Some(InstallerKind::InstallShield));
+        }
+        for resource_type in [RT_VERSION, RT_MANIFEST] {
+            if let Some(resource) = self.read_resource(resource_type)? {
+                let text = match resource_type {
+                    RT_VERSION => utf16_text(&resource),
+                    _ => String::from_utf8_lossy(&resource).into_owned(),
+                };
+                if let Some(kind) = InstallerKind::from_text(&text) {
+                    return Ok(Some(kind));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_overlay_head(&mut self) -> Result<Vec<u8>, io::Error> {
+        let overlay_offset = self
+            .sections
+            .iter()
+            .map(|s| s.raw_data_offset as u64 + s.raw_data_size as u64)
+            .max()
+            .unwrap_or_default();
+        let file_size = self.file.metadata()?.len();
+        if overlay_offset == 0 || overlay_offset >= file_size {
+            return Ok(Vec::new());
+        }
+        let mut head = vec![0; OVERLAY_HEAD_SIZE.min(file_size - overlay_offset) as usize];
+        read_at(&mut self.file, overlay_offset, &mut head)?;
+        Ok(head)
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<u64> {
+        self.sections
+            .iter()
+            .find(|s| {
+                let size = s.virtual_size.max(s.raw_data_size);
+                rva >= s.virtual_address && rva < s.virtual_address.saturating_add(size)
+            })
+            .map(|s| (rva - s.virtual_address) as u64 + s.raw_data_offset as u64)
+    }
+
+    /// Reads the first resource entry of the given type by walking the type, name and language
+    /// levels of the resource directory
+    fn read_resource(&mut self, resource_type: u32) -> Result<Option<Vec<u8>>, io::Error> {
+        let resource_offset = match self.rva_to_offset(self.resource_rva) {
+            Some(o) if self.resource_rva != 0 => o,
+            _ => return Ok(None),
+        };
+        let mut entry = match self.find_directory_entry(resource_offset, 0, Some(resource_type))? {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+        // Name and language levels, we just take the first entry in each
+        for _ in 0..2 {
+            if entry & RESOURCE_SUBDIRECTORY_FLAG == 0 {
+                return Ok(None);
+            }
+            let directory = entry & !RESOURCE_SUBDIRECTORY_FLAG;
+            entry = match self.find_directory_entry(resource_offset, directory, None)? {
+                Some(e) => e,
+                None => return Ok(None),
+            };
+        }
+        let mut data_entry = [0; 8];
+        if read_at(
+            &mut self.file,
+            resource_offset + entry as u64,
+            &mut data_entry,
+        )
+        .is_err()
+        {
+            return Ok(None);
+        }
+        let data_rva = u32_at(&data_entry, 0).unwrap_or_default();
+        let data_size = u32_at(&data_entry, 4).unwrap_or_default();
+        let data_offset = match self.rva_to_offset(data_rva) {
+            Some(o) if data_size <= MAX_RESOURCE_SIZE => o,
+            _ => return Ok(None),
+        };
+        let mut data = vec![0; data_size as usize];
+        if read_at(&mut self.file, data_offset, &mut data).is_err() {
+            return Ok(None);
+        }
+        Ok(Some(data))
+    }
+
+    /// Returns the offset stored in the directory entry matching the id, or the first entry
+    /// if no id is given
+    fn find_directory_entry(
+        &mut self,
+        resource_offset: u64,
+        directory: u32,
+        id: Option<u32>,
+    ) -> Result<Option<u32>, io::Error> {
+        let directory_offset = resource_offset + directory as u64;
+        let mut header = [0; 16];
+        if read_at(&mut self.file, directory_offset, &mut header).is_err() {
+            return Ok(None);
+        }
+        let number_of_entries = u16_at(&header, 12).unwrap_or_default() as usize
+            + u16_at(&header, 14).unwrap_or_default() as usize;
+        let mut entries = vec![0; number_of_entries * 8];
+        if read_at(&mut self.file, directory_offset + 16, &mut entries).is_err() {
+            return Ok(None);
+        }
+        let entry = entries
+            .chunks_exact(8)
+            .find(|e| id.is_none() || u32_at(e, 0) == id)
+            .and_then(|e| u32_at(e, 4));
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+    use std::fs;
+
+    const PE_OFFSET: usize = 0x40;
+    const SECTION_OFFSET: u32 = 0x200;
+    const SECTION_SIZE: u32 = 0x200;
+
+    /// A minimal PE32 file with a single section, followed by the overlay
+    fn craft_pe(section_name: &[u8], overlay: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0; (SECTION_OFFSET + SECTION_SIZE) as usize];
+        bytes[..2].copy_from_slice(DOS_SIGNATURE);
+        bytes[0x3C..0x40].copy_from_slice(&(PE_OFFSET as u32).to_le_bytes());
+        bytes[PE_OFFSET..PE_OFFSET + 4].copy_from_slice(PE_SIGNATURE);
+        let coff_header = PE_OFFSET + 4;
+        let optional_header_size = PE32_DATA_DIRECTORIES_OFFSET as u16 + 16 * 8;
+        bytes[coff_header + 2..coff_header + 4].copy_from_slice(&1u16.to_le_bytes());
+        bytes[coff_header + 16..coff_header + 18]
+            .copy_from_slice(&optional_header_size.to_le_bytes());
+        let optional_header = coff_header + COFF_HEADER_SIZE;
+        bytes[optional_header..optional_header + 2].copy_from_slice(&PE32_MAGIC.to_le_bytes());
+        let section = optional_header + optional_header_size as usize;
+        bytes[section..section + section_name.len()].copy_from_slice(section_name);
+        for (offset, value) in [
+            (8, SECTION_SIZE),
+            (12, 0x1000),
+            (16, SECTION_SIZE),
+            (20, SECTION_OFFSET),
+        ] {
+            bytes[section + offset..section + offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(overlay);
+        bytes
+    }
+
+    /// Writes the fixture to a fresh file and detects its installer kind
+    fn detect_fixture(test_name: &str, bytes: &[u8]) -> Option<InstallerKind> {
+        let fixture_path = create_test_folder(test_name).join("fixture.exe");
+        fs::write(&fixture_path, bytes).unwrap();
+        InstallerKind::detect(&fixture_path).unwrap()
+    }
+
+    #[test]
+    fn detects_installers_by_their_overlay() {
+        let inno = craft_pe(b".text", b"rDlPtS02\x87eVx");
+        let nsis = craft_pe(b".text", b"\xef\xbe\xad\xdeNullsoftInst");
+        let plain = craft_pe(b".text", b"");
+        assert_eq!(detect_fixture("pe-inno", &inno), Some(InstallerKind::Inno));
+        assert_eq!(detect_fixture("pe-nsis", &nsis), Some(InstallerKind::Nsis));
+        assert_eq!(detect_fixture("pe-plain", &plain), None);
+    }
+
+    #[test]
+    fn detects_wix_burn_by_its_section() {
+        let burn = craft_pe(WIX_BURN_SECTION_NAME, b"");
+        assert_eq!(
+            detect_fixture("pe-burn", &burn),
+            Some(InstallerKind::WixBurn)
+        );
+    }
+
+    #[test]
+    fn detects_msi_by_its_signature() {
+        let msi = [MSI_SIGNATURE, &[0; 512]].concat();
+        assert_eq!(detect_fixture("pe-msi", &msi), Some(InstallerKind::Msi));
+    }
+
+    #[test]
+    fn ignores_truncated_files() {
+        let inno = craft_pe(b".text", b"rDlPtS02\x87eVx");
+        let overlay_offset = (SECTION_OFFSET + SECTION_SIZE) as usize;
+        for length in 0..overlay_offset {
+            assert_eq!(detect_fixture("pe-truncated", &inno[..length]), None);
+        }
+    }
+
+    #[test]
+    fn ignores_pe_offset_beyond_the_end_of_the_file() {
+        let mut bogus = craft_pe(b".text", b"NullsoftInst");
+        bogus[0x3C..0x40].copy_from_slice(&0xFFFF_FF00u32.to_le_bytes());
+        assert_eq!(detect_fixture("pe-bogus-offset", &bogus), None);
+    }
+
+    #[test]
+    fn ignores_corrupt_resource_directories() {
+        let mut corrupt = craft_pe(b".text", b"");
+        let resource_directory = PE_OFFSET
+            + 4
+            + COFF_HEADER_SIZE
+            + PE32_DATA_DIRECTORIES_OFFSET
+            + RESOURCE_DATA_DIRECTORY_INDEX * 8;
+        corrupt[resource_directory..resource_directory + 4]
+            .copy_from_slice(&0x1000u32.to_le_bytes());
+        let section = SECTION_OFFSET as usize..(SECTION_OFFSET + SECTION_SIZE) as usize;
+        corrupt[section].fill(0xFF);
+        assert_eq!(detect_fixture("pe-corrupt-resources", &corrupt), None);
+    }
+
+    #[test]
+    fn ignores_files_that_are_not_pe() {
+        let text = b"MZ isn't enough to make this an executable";
+        assert_eq!(detect_fixture("pe-not-pe", text), None);
+        assert_eq!(detect_fixture("pe-empty", b""), None);
+    }
+}
@@ -0,0 +1,265 @@
+//!Keeps the user data of portable packages in a store outside their installation folders so it
+//!survives updates and uninstalls
+
+use crate::includes::{
+    dist::{DistType, InstallInfo},
+    error::{InvalidPersistRuleError, SengetErrors},
+    utils::{Cmd, CopyDirAll},
+};
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Folder in the persist folder holding the persisted files and folders of a package
+pub struct PersistStore {
+    folder: PathBuf,
+}
+
+pub fn generate_persist_folder_path(config_dir: &Path) -> Result<PathBuf, io::Error> {
+    let path = config_dir.join("persist");
+    if !path.is_dir() {
+        fs::create_dir(&path)?;
+    }
+    Ok(path)
+}
+
+fn is_valid_rule(rule: &Path) -> bool {
+    rule.components().next().is_some()
+        && rule.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn remove_item(path: &Path) -> Result<(), io::Error> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    // Removing a junction with remove_dir only removes the link, never the store it points to
+    match (metadata.is_symlink(), metadata.is_dir()) {
+        (true, true) => fs::remove_dir(path),
+        (false, true) => fs::remove_dir_all(path),
+        _ => fs::remove_file(path),
+    }
+}
+
+fn is_link(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.is_symlink())
+}
+
+/// Junctions don't need admin rights or developer mode unlike symlinks, false if cmd couldn't
+/// make one or couldn't be run at all
+fn create_junction(link: &Path, target: &Path) -> bool {
+    Command::cmd()
+        .arg("mklink")
+        .arg("/J")
+        .arg(link)
+        .arg(target)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Removes the persisted items from the installation folder without touching the store
+pub fn unlink_persisted(install_info: &InstallInfo) -> Result<(), io::Error> {
+    if install_info.dist_type == DistType::Installer {
+        return Ok(());
+    }
+    if let Some(folder) = install_info.installation_folder.as_ref() {
+        for rule in install_info.persist.iter().filter(|r| is_valid_rule(r)) {
+            remove_item(&folder.join(rule))?;
+        }
+    }
+    Ok(())
+}
+
+impl PersistStore {
+    pub fn new(persist_folder: &Path, package_name: &str) -> PersistStore {
+        PersistStore {
+            folder: persist_folder.join(package_name),
+        }
+    }
+
+    /// Copies the persisted items that were copied instead of linked back into the store
+    pub fn save(&self, install_info: &InstallInfo) -> Result<(), io::Error> {
+        let installation_folder = match install_info.installation_folder.as_ref() {
+            Some(folder) => folder,
+            None => return Ok(()),
+        };
+        for rule in install_info.persist.iter().filter(|r| is_valid_rule(r)) {
+            let item = installation_folder.join(rule);
+            // Linked items are already in the store
+            if !item.exists() || is_link(&item) {
+                continue;
+            }
+            let stored_item = self.folder.join(rule);
+            remove_item(&stored_item)?;
+            if item.is_dir() {
+                item.copy_dir_all(&stored_item)?;
+            } else {
+                if let Some(parent) = stored_item.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(&item, &stored_item)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the persisted items in a freshly installed version with links to the store, or
+    /// copies if linking fails. Items the store doesn't have yet are seeded from the ones the
+    /// package ships with
+    pub fn link_into(&self, install_info: &mut InstallInfo) -> Result<(), SengetErrors> {
+        if install_info.persist.iter().any(|r| !is_valid_rule(r)) {
+            return Err(InvalidPersistRuleError.into());
+        }
+        let installation_folder = match install_info.installation_folder.as_ref() {
+            Some(folder) if install_info.dist_type != DistType::Installer => folder,
+            _ => return Ok(()),
+        };
+        for rule in install_info.persist.iter() {
+            let item = installation_folder.join(rule);
+            let stored_item = self.folder.join(rule);
+            if !stored_item.exists() {
+                if !item.exists() {
+                    // Saved into the store once the package creates it
+                    continue;
+                }
+                if let Some(parent) = stored_item.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                match item.is_dir() {
+                    true => item.copy_dir_all(&stored_item)?,
+                    false => fs::copy(&item, &stored_item).map(|_| ())?,
+                }
+            }
+            remove_item(&item)?;
+            if let Some(parent) = item.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match stored_item.is_dir() {
+                true => {
+                    if !create_junction(&item, &stored_item) {
+                        stored_item.copy_dir_all(&item)?;
+                    }
+                }
+                false => fs::copy(&stored_item, &item).map(|_| ())?,
+            }
+        }
+        // The persisted items no longer belong to this version so uninstalling it leaves them be
+        if let Some(manifest) = install_info.manifest.as_mut() {
+            let persist = &install_info.persist;
+            manifest
+                .files
+                .retain(|e| !persist.iter().any(|r| e.path.starts_with(r)));
+        }
+        Ok(())
+    }
+
+    pub fn purge(&self) -> Result<(), io::Error> {
+        if self.folder.is_dir() {
+            fs::remove_dir_all(&self.folder)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::{manifest::Manifest, utils::create_test_folder};
+
+    /// A zip package shipping a config file and a data folder, returns its install info and the
+    /// store
+    fn install_fixture(test_name: &str) -> (InstallInfo, PersistStore) {
+        let test_folder = create_test_folder(test_name);
+        let installation_folder = test_folder.join("packages").join("package").join("1.0.0");
+        fs::create_dir_all(installation_folder.join("data")).unwrap();
+        fs::write(installation_folder.join("config.json"), "shipped").unwrap();
+        fs::write(installation_folder.join("data").join("saves.db"), "shipped").unwrap();
+        fs::write(installation_folder.join("package.exe"), "exe").unwrap();
+        let mut install_info = InstallInfo::test_fixture(&installation_folder);
+        install_info.persist = vec![PathBuf::from("config.json"), PathBuf::from("data")];
+        install_info.manifest = Some(Manifest::record(&installation_folder).unwrap());
+        let store = PersistStore::new(&test_folder.join("persist"), "package");
+        (install_info, store)
+    }
+
+    #[test]
+    fn only_accepts_relative_rules_inside_the_folder() {
+        assert!(is_valid_rule(Path::new("config.json")));
+        assert!(is_valid_rule(&Path::new("data").join("saves")));
+        assert!(!is_valid_rule(Path::new("")));
+        assert!(!is_valid_rule(&Path::new("..").join("config.json")));
+        assert!(!is_valid_rule(&Path::new("data").join("..").join("..")));
+        assert!(!is_valid_rule(&std::env::temp_dir().join("config.json")));
+    }
+
+    #[test]
+    fn refuses_rules_outside_the_installation_folder() {
+        let (mut install_info, store) = install_fixture("persist-invalid-rule");
+        install_info
+            .persist
+            .push(Path::new("..").join("config.json"));
+        assert!(matches!(
+            store.link_into(&mut install_info),
+            Err(SengetErrors::InvalidPersistRuleError(_))
+        ));
+        assert!(!store.folder.exists());
+    }
+
+    #[test]
+    fn seeds_the_store_from_the_shipped_items() {
+        let (mut install_info, store) = install_fixture("persist-seed");
+        store.link_into(&mut install_info).ok().unwrap();
+        let installation_folder = install_info.installation_folder.clone().unwrap();
+        for rule in [
+            Path::new("config.json"),
+            &Path::new("data").join("saves.db"),
+        ] {
+            assert_eq!(
+                fs::read_to_string(store.folder.join(rule)).unwrap(),
+                "shipped"
+            );
+            assert_eq!(
+                fs::read_to_string(installation_folder.join(rule)).unwrap(),
+                "shipped"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_the_stored_items_over_the_shipped_ones() {
+        let (mut install_info, store) = install_fixture("persist-stored");
+        fs::create_dir_all(store.folder.join("data")).unwrap();
+        fs::write(store.folder.join("config.json"), "stored").unwrap();
+        fs::write(store.folder.join("data").join("saves.db"), "stored").unwrap();
+        store.link_into(&mut install_info).ok().unwrap();
+        let installation_folder = install_info.installation_folder.clone().unwrap();
+        for rule in [
+            Path::new("config.json"),
+            &Path::new("data").join("saves.db"),
+        ] {
+            assert_eq!(
+                fs::read_to_string(installation_folder.join(rule)).unwrap(),
+                "stored"
+            );
+        }
+    }
+
+    #[test]
+    fn drops_the_persisted_items_from_the_manifest() {
+        let (mut install_info, store) = install_fixture("persist-manifest");
+        store.link_into(&mut install_info).ok().unwrap();
+        let recorded_paths = install_info
+            .manifest
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|e| e.path)
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(recorded_paths, [PathBuf::from("package.exe")]);
+    }
+}
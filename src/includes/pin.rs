@@ -0,0 +1,114 @@
+//!Keeps pinned packages on the versions their pin allows when updating every package
+
+use crate::includes::{
+    constraint::{compare_versions, VersionConstraint},
+    error::InvalidVersionConstraintError,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A pin holds a package on its installed version, or on the versions its constraint allows
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pin {
+    pub constraint: Option<VersionConstraint>,
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.constraint {
+            Some(constraint) => write!(f, "{}", constraint),
+            None => write!(f, "installed version"),
+        }
+    }
+}
+
+impl Pin {
+    pub fn new(constraint: Option<&str>) -> Result<Pin, InvalidVersionConstraintError> {
+        Ok(Pin {
+            constraint: constraint.map(VersionConstraint::parse).transpose()?,
+        })
+    }
+
+    /// Whether the pin's constraint and the package's own constraint both allow the version, a pin
+    /// without a constraint allows none
+    pub fn allows(&self, version: &str, package_constraint: Option<&VersionConstraint>) -> bool {
+        self.constraint.as_ref().is_some_and(|c| c.allows(version))
+            && package_constraint.is_none_or(|c| c.allows(version))
+    }
+
+    /// Newest of the versions the pin allows if it's newer than the installed version, the pin
+    /// can't take the package outside its own version constraint
+    pub fn pick_update(
+        &self,
+        installed_version: &str,
+        versions: &[String],
+        package_constraint: Option<&VersionConstraint>,
+    ) -> Option<String> {
+        let versions = versions
+            .iter()
+            .filter(|v| self.allows(v, package_constraint))
+            .cloned()
+            .collect::<Vec<String>>();
+        self.constraint
+            .as_ref()?
+            .newest_allowed(&versions)
+            .filter(|v| compare_versions(v, installed_version).is_gt())
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_only_newer_versions_the_pin_allows() {
+        let versions = ["2.0.0", "1.5.0", "1.4.2", "1.4.0"].map(String::from);
+        let cases = [
+            // Pinned to the installed version
+            (None, "1.4.0", None),
+            (Some("^1.4"), "1.4.0", Some("1.5.0")),
+            // The newest allowed version is the installed one
+            (Some("^1.4"), "1.5.0", None),
+            // The newest allowed version is older than the installed one
+            (Some("~1.4"), "1.5.0", None),
+            (Some("~1.4"), "1.4.0", Some("1.4.2")),
+            (Some("<1"), "0.9.0", None),
+        ];
+        for (constraint, installed_version, expected) in cases {
+            let pin = Pin::new(constraint).ok().unwrap();
+            assert_eq!(
+                pin.pick_update(installed_version, &versions, None)
+                    .as_deref(),
+                expected,
+                "{} {}",
+                pin,
+                installed_version
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_pinned_updates_inside_the_package_constraint() {
+        let versions = ["2.0.0", "1.5.0", "1.4.2"].map(String::from);
+        let pin = Pin::new(Some(">=1")).ok().unwrap();
+        let package_constraint = VersionConstraint::parse("~1.4").ok();
+        let picked = pin.pick_update("1.4.0", &versions, package_constraint.as_ref());
+        assert_eq!(picked.as_deref(), Some("1.4.2"));
+        assert_eq!(
+            pin.pick_update("1.4.0", &versions, None).as_deref(),
+            Some("2.0.0")
+        );
+    }
+
+    #[test]
+    fn allows_versions_both_constraints_allow() {
+        let package_constraint = VersionConstraint::parse("~1.4").ok();
+        let pin = Pin::new(Some(">=1")).ok().unwrap();
+        assert!(pin.allows("1.4.2", package_constraint.as_ref()));
+        assert!(!pin.allows("1.5.0", package_constraint.as_ref()));
+        assert!(pin.allows("1.5.0", None));
+        // Releases are only looked up for pins with a constraint
+        assert!(!Pin::new(None).ok().unwrap().allows("1.4.2", None));
+    }
+}
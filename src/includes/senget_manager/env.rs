@@ -1,57 +1,129 @@
-//!Manages the SENGET_PACKAGES environment variable
+//!Manages the Path environment variable entry for the shims folder
 
-use std::io;
-use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+use std::{collections::HashSet, io};
+use winreg::{
+    enums::{RegType, HKEY_CURRENT_USER, REG_EXPAND_SZ},
+    types::{FromRegValue, ToRegValue},
+    RegKey,
+};
 
+// Older versions added every package folder to this variable and referenced it from Path
 const SENGET_PACKAGES_ENV_VAR: &str = "SENGET_PACKAGES";
 
-pub fn setup_senget_packages_path_env_var(
-    senget_installation_folder: &str,
-) -> Result<(), io::Error> {
+/// Adds the shims folder to Path, returns true if Path had to be updated meaning the shims need to
+/// be rebuilt e.g., on the first run or when migrating from the SENGET_PACKAGES variable
+pub fn setup_shims_path_env_var(shims_folder: &str) -> Result<bool, io::Error> {
     let env_var = open_env_var()?;
-    let path_value = env_var.get_value::<String, _>("Path")?;
-    if !path_value.contains(SENGET_PACKAGES_ENV_VAR) {
-        let updated_value = format!(
-            "{};%{}%",
-            env_var.get_value::<String, _>("Path")?,
-            SENGET_PACKAGES_ENV_VAR
-        );
-        env_var.set_value("Path", &updated_value)?;
+    let (path_value, path_type) = read_path_value(&env_var)?;
+    let senget_packages_entry = format!("%{}%", SENGET_PACKAGES_ENV_VAR);
+    let mut folders = path_value
+        .split(';')
+        .filter(|folder| !folder.is_empty() && *folder != senget_packages_entry)
+        .collect::<Vec<&str>>();
+    let has_shims_folder = folders.contains(&shims_folder);
+    if has_shims_folder && !path_value.contains(&senget_packages_entry) {
+        return Ok(false);
     }
-    if let Err(err) = env_var.get_value::<String, _>(SENGET_PACKAGES_ENV_VAR) {
-        if err.kind() == io::ErrorKind::NotFound {
-            set_senget_env_var_value(&env_var, senget_installation_folder)?;
-        }
-        return Err(err);
+    if !has_shims_folder {
+        folders.push(shims_folder);
     }
-    Ok(())
+    write_path_value(&env_var, &folders, path_type)?;
+    delete_senget_packages_env_var(&env_var)?;
+    Ok(true)
 }
-pub fn add_package_folder_to_senget_env_var(
-    package_installation_folder: &str,
-) -> Result<(), io::Error> {
-    add_senget_env_var_value(&open_env_var()?, package_installation_folder)
+
+/// Descriptions of what's wrong with the Path entries, empty if nothing is
+pub fn inspect_path_env_var(shims_folder: &str) -> Result<Vec<String>, io::Error> {
+    let env_var = open_env_var()?;
+    let (path_value, _) = read_path_value(&env_var)?;
+    let senget_packages_entry = format!("%{}%", SENGET_PACKAGES_ENV_VAR);
+    let folders = path_value
+        .split(';')
+        .filter(|folder| !folder.is_empty())
+        .collect::<Vec<&str>>();
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+    for folder in folders.iter() {
+        let key = path_entry_key(folder);
+        if !seen.insert(key.clone()) && reported.insert(key) {
+            problems.push(format!("Path contains {} more than once", folder));
+        }
+    }
+    if !seen.contains(&path_entry_key(shims_folder)) {
+        problems.push(format!(
+            "Path doesn't contain the shims folder {}",
+            shims_folder
+        ));
+    }
+    if folders.contains(&senget_packages_entry.as_str()) {
+        problems.push(format!("Path still contains {}", senget_packages_entry));
+    }
+    if env_var
+        .get_value::<String, _>(SENGET_PACKAGES_ENV_VAR)
+        .is_ok()
+    {
+        problems.push(format!(
+            "The old {} variable still exists",
+            SENGET_PACKAGES_ENV_VAR
+        ));
+    }
+    Ok(problems)
 }
 
-pub fn remove_package_folder_from_senget_env_var(
-    package_installation_folder: &str,
-) -> Result<(), io::Error> {
+/// Removes duplicate entries and what's left of SENGET_PACKAGES from Path and makes sure it
+/// contains the shims folder, the first of duplicate entries is kept
+pub fn repair_path_env_var(shims_folder: &str) -> Result<(), io::Error> {
     let env_var = open_env_var()?;
-    let initial_value = get_senget_env_var_value(&env_var)?;
-    let to_replace = format!(";{}", package_installation_folder);
-    let new_value = initial_value.replace(&to_replace, "");
-    set_senget_env_var_value(&env_var, &new_value)
+    let (path_value, path_type) = read_path_value(&env_var)?;
+    let senget_packages_entry = format!("%{}%", SENGET_PACKAGES_ENV_VAR);
+    let mut seen = HashSet::new();
+    let mut folders = path_value
+        .split(';')
+        .filter(|folder| {
+            !folder.is_empty()
+                && *folder != senget_packages_entry
+                && seen.insert(path_entry_key(folder))
+        })
+        .collect::<Vec<&str>>();
+    if seen.insert(path_entry_key(shims_folder)) {
+        folders.push(shims_folder);
+    }
+    write_path_value(&env_var, &folders, path_type)?;
+    delete_senget_packages_env_var(&env_var)
+}
+
+// Windows paths are case insensitive and a trailing backslash doesn't change the folder
+fn path_entry_key(folder: &str) -> String {
+    folder.trim_end_matches('\\').to_lowercase()
 }
-fn get_senget_env_var_value(env_var: &RegKey) -> Result<String, io::Error> {
-    env_var.get_value(SENGET_PACKAGES_ENV_VAR)
+
+/// The value as it's stored along with its type, Path is usually REG_EXPAND_SZ so that entries
+/// like %USERPROFILE%\bin expand
+fn read_path_value(env_var: &RegKey) -> Result<(String, RegType), io::Error> {
+    match env_var.get_raw_value("Path") {
+        Ok(value) => Ok((String::from_reg_value(&value)?, value.vtype)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok((String::new(), REG_EXPAND_SZ)),
+        Err(err) => Err(err),
+    }
 }
 
-fn set_senget_env_var_value(env_var: &RegKey, value: &str) -> Result<(), io::Error> {
-    env_var.set_value(SENGET_PACKAGES_ENV_VAR, &value)
+/// Writes the folders back with the type Path had, set_value would always make it REG_SZ
+fn write_path_value(
+    env_var: &RegKey,
+    folders: &[&str],
+    path_type: RegType,
+) -> Result<(), io::Error> {
+    let mut value = folders.join(";").to_reg_value();
+    value.vtype = path_type;
+    env_var.set_raw_value("Path", &value)
 }
 
-fn add_senget_env_var_value(env_var: &RegKey, new_value: &str) -> Result<(), io::Error> {
-    let updated_value = format!("{};{}", get_senget_env_var_value(env_var)?, new_value);
-    set_senget_env_var_value(env_var, &updated_value)
+fn delete_senget_packages_env_var(env_var: &RegKey) -> Result<(), io::Error> {
+    match env_var.delete_value(SENGET_PACKAGES_ENV_VAR) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
 }
 
 fn open_env_var() -> Result<RegKey, io::Error> {
@@ -2,10 +2,11 @@
 
 use crate::includes::{
     database::PackageDatabase,
-    dist::{DistType, InstallInfo, InstallerDist},
+    dist::{DistPreference, DistType, InstallInfo, InstallerDist},
     error::SengetErrors,
     github::api::Repo,
     package::Package,
+    pe::InstallerKind,
     utils::{DESCRIPTION, REPO_URL, VERSION},
 };
 use regex::Regex;
@@ -32,7 +33,19 @@ pub fn generate_senget_package() -> Result<Package, io::Error> {
         installation_folder,
         uninstall_command,
         dist_type: DistType::Installer,
+        installer_kind: Some(InstallerKind::Inno),
         create_shortcut_file: false,
+        installer_args: None,
+        interactive: false,
+        location: None,
+        adopted: false,
+        binaries: Vec::new(),
+        custom_binaries: false,
+        executable: None,
+        manifest: None,
+        persist: Vec::new(),
+        shortcuts: Vec::new(),
+        source: None,
     };
     Ok(Package::new(VERSION.to_owned(), repo, install_info))
 }
@@ -48,7 +61,9 @@ pub fn setup_senget_package(
     match db.find_package("Senget")? {
         Some(old_senget_package) => {
             if old_senget_package.version != VERSION {
-                db.update_package(&old_senget_package, senget_package.clone())?;
+                let mut updated_senget_package = senget_package.clone();
+                updated_senget_package.pin = old_senget_package.pin.clone();
+                db.update_package(&old_senget_package, updated_senget_package)?;
             };
         }
         None => {
@@ -65,7 +80,11 @@ pub async fn check_if_senget_update_available(
 ) -> Result<bool, reqwest::Error> {
     let latest_dist = senget_package
         .repo
-        .get_latest_dist(client, version_regex, &Some(DistType::Installer))
+        .get_latest_dist(
+            client,
+            version_regex,
+            &DistPreference::Only(DistType::Installer),
+        )
         .await?;
     if let Some(dist) = latest_dist {
         return Ok(dist.version() != senget_package.version);
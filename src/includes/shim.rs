@@ -0,0 +1,213 @@
+//!Generates launcher shims so only package executables end up on Path instead of their whole folders
+
+use crate::includes::{dist::InstallInfo, package::Package, transaction::Transaction};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const CMD_EXTENSION: &str = "cmd";
+
+/// A `.cmd` launcher in the shims folder that runs the executable with the arguments it's given
+pub struct Shim {
+    executable_path: PathBuf,
+    cmd_path: PathBuf,
+}
+
+pub fn generate_shims_folder_path(config_dir: &Path) -> Result<PathBuf, io::Error> {
+    let path = config_dir.join("shims");
+    if !path.is_dir() {
+        fs::create_dir(&path)?;
+    }
+    Ok(path)
+}
+
+impl Shim {
+    pub fn new(name: &str, executable_path: &Path, shims_folder: &Path) -> Shim {
+        Shim {
+            executable_path: executable_path.to_owned(),
+            cmd_path: shims_folder.join(name).with_extension(CMD_EXTENSION),
+        }
+    }
+
+    /// Shims for every binary the package exposes
+    pub fn from_install_info(install_info: &InstallInfo, shims_folder: &Path) -> Vec<Shim> {
+        install_info
+            .exposed_binaries()
+            .into_iter()
+            .map(|(name, ep)| Shim::new(&name, &ep, shims_folder))
+            .collect()
+    }
+
+    pub fn has_same_name(&self, other: &Shim) -> bool {
+        self.cmd_path == other.cmd_path
+    }
+
+    fn cmd_contents(&self) -> String {
+        // Not canonicalized cause the executable may have been moved or deleted already
+        let executable_path = self
+            .executable_path
+            .to_string_lossy()
+            .replace("\\\\?\\", "");
+        format!("@echo off\r\n\"{}\" %*\r\n", executable_path)
+    }
+
+    pub fn create(&self) -> Result<(), io::Error> {
+        fs::write(&self.cmd_path, self.cmd_contents())
+    }
+
+    /// Creates the shim and restores whatever shim it replaced if the transaction is rolled back
+    pub fn create_in_transaction(&self, transaction: &mut Transaction) -> Result<(), io::Error> {
+        let cmd_path = self.cmd_path.clone();
+        let previous_contents = fs::read(&cmd_path).ok();
+        self.create()?;
+        transaction.on_rollback(move || match previous_contents {
+            Some(contents) => fs::write(cmd_path, contents),
+            None => fs::remove_file(cmd_path),
+        });
+        Ok(())
+    }
+
+    /// Whether the launcher exists and runs this executable
+    pub fn is_up_to_date(&self) -> bool {
+        fs::read_to_string(&self.cmd_path).is_ok_and(|contents| contents == self.cmd_contents())
+    }
+
+    /// Only removes the shim if it still launches this executable since another package with an
+    /// executable of the same name may have replaced it
+    pub fn remove(&self) -> Result<(), io::Error> {
+        match self.is_up_to_date() {
+            true => fs::remove_file(&self.cmd_path),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Shim files in the shims folder that no package exposes a binary through
+pub fn find_stray_shims(
+    packages: &[Package],
+    shims_folder: &Path,
+) -> Result<Vec<PathBuf>, io::Error> {
+    let shims = packages
+        .iter()
+        .flat_map(|p| Shim::from_install_info(&p.install_info, shims_folder))
+        .map(|s| s.cmd_path)
+        .collect::<Vec<PathBuf>>();
+    let mut stray_shims = Vec::new();
+    for entry in fs::read_dir(shims_folder)? {
+        let path = entry?.path();
+        if path.is_file() && is_shim_file(&path) && !shims.contains(&path) {
+            stray_shims.push(path);
+        }
+    }
+    stray_shims.sort();
+    Ok(stray_shims)
+}
+
+fn is_shim_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    extension == CMD_EXTENSION
+}
+
+/// Deletes every shim in the shims folder and recreates them for the active version of each
+/// package, returns the number of shims created
+pub fn rebuild_shims(packages: &[Package], shims_folder: &Path) -> Result<usize, io::Error> {
+    for entry in fs::read_dir(shims_folder)? {
+        let path = entry?.path();
+        if path.is_file() && is_shim_file(&path) {
+            fs::remove_file(path)?;
+        }
+    }
+    let mut created = 0;
+    for package in packages {
+        for shim in Shim::from_install_info(&package.install_info, shims_folder) {
+            shim.create()?;
+            created += 1;
+        }
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+
+    /// A package exposing its tool.exe through a shim named tool
+    fn package(full_name: &str, installation_folder: &Path) -> Package {
+        let mut package = Package::test_fixture(full_name, "1.0.0", installation_folder);
+        package.install_info.executable_path = Some(installation_folder.join("tool.exe"));
+        package
+    }
+
+    fn shim_file_names(shims_folder: &Path) -> Vec<String> {
+        let mut file_names = fs::read_dir(shims_folder)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        file_names.sort();
+        file_names
+    }
+
+    #[test]
+    fn writes_a_cmd_launcher() {
+        let shims_folder = create_test_folder("shim-create");
+        let executable_path = Path::new("C:\\senget\\packages\\tool\\1.0.0\\tool.exe");
+        let shim = Shim::new("tool", executable_path, &shims_folder);
+        shim.create().unwrap();
+        assert_eq!(
+            fs::read_to_string(shims_folder.join("tool.cmd")).unwrap(),
+            "@echo off\r\n\"C:\\senget\\packages\\tool\\1.0.0\\tool.exe\" %*\r\n"
+        );
+        assert!(shim.is_up_to_date());
+        let other_shim = Shim::new("tool", Path::new("C:\\Apps\\tool.exe"), &shims_folder);
+        assert!(!other_shim.is_up_to_date());
+    }
+
+    #[test]
+    fn leaves_a_shim_another_package_replaced_alone() {
+        let shims_folder = create_test_folder("shim-remove");
+        let shim = Shim::new("tool", Path::new("C:\\Apps\\a\\tool.exe"), &shims_folder);
+        let other_shim = Shim::new("tool", Path::new("C:\\Apps\\b\\tool.exe"), &shims_folder);
+        shim.create().unwrap();
+        other_shim.create().unwrap();
+        shim.remove().unwrap();
+        assert!(other_shim.is_up_to_date());
+        other_shim.remove().unwrap();
+        assert!(shim_file_names(&shims_folder).is_empty());
+    }
+
+    #[test]
+    fn finds_shims_no_package_exposes() {
+        let shims_folder = create_test_folder("shim-stray");
+        let packages = [package("owner/tool", Path::new("C:\\Apps\\tool"))];
+        rebuild_shims(&packages, &shims_folder).unwrap();
+        Shim::new("removed", Path::new("C:\\Apps\\removed.exe"), &shims_folder)
+            .create()
+            .unwrap();
+        fs::write(shims_folder.join("notes.txt"), "notes").unwrap();
+        assert_eq!(
+            find_stray_shims(&packages, &shims_folder).unwrap(),
+            [shims_folder.join("removed.cmd")]
+        );
+    }
+
+    #[test]
+    fn rebuilds_the_shims_of_every_package() {
+        let shims_folder = create_test_folder("shim-rebuild");
+        Shim::new("removed", Path::new("C:\\Apps\\removed.exe"), &shims_folder)
+            .create()
+            .unwrap();
+        fs::write(shims_folder.join("notes.txt"), "notes").unwrap();
+        let mut packages = vec![
+            package("owner/tool", Path::new("C:\\Apps\\tool")),
+            package("owner/data", Path::new("C:\\Apps\\data")),
+        ];
+        packages[1].install_info.executable_path = None;
+        assert_eq!(rebuild_shims(&packages, &shims_folder).unwrap(), 1);
+        assert_eq!(shim_file_names(&shims_folder), ["notes.txt", "tool.cmd"]);
+    }
+}
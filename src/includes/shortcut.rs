@@ -0,0 +1,229 @@
+//!Creates, reads and removes the shortcut files of packages
+
+use crate::includes::{
+    transaction::Transaction,
+    utils::{FolderItems, PathStr},
+};
+use mslnk::MSLinkError;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A shortcut file senget made for a package, recorded so uninstalling removes exactly it
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShortcutFile {
+    pub path: PathBuf,
+    pub arguments: Option<String>,
+}
+
+/// What a shortcut file on disk launches
+#[derive(Debug, PartialEq)]
+pub struct ShortcutTarget {
+    pub target: PathBuf,
+    pub arguments: Option<String>,
+}
+
+pub fn generate_desktop_folder_path() -> PathBuf {
+    PathBuf::from(env::var("USERPROFILE").unwrap_or_default()).join("Desktop")
+}
+
+fn to_io_error(err: MSLinkError) -> io::Error {
+    io::Error::other(format!("{:?}", err))
+}
+
+/// Installers fill in the link info while mslnk only stores the working directory and the
+/// relative path of the target
+pub fn read_shortcut(shortcut_path: &Path) -> Option<ShortcutTarget> {
+    let lnk = lnk::ShellLink::open(shortcut_path).ok()?;
+    let local_base_path = lnk
+        .link_info()
+        .as_ref()
+        .and_then(|info| info.local_base_path().clone());
+    let target = match local_base_path {
+        Some(local_base_path) => PathBuf::from(local_base_path),
+        None => {
+            let relative_path = lnk.relative_path().as_ref()?;
+            let file_name = relative_path
+                .trim_start_matches("./")
+                .trim_start_matches(".\\");
+            PathBuf::from(lnk.working_dir().as_ref()?).join(file_name)
+        }
+    };
+    Some(ShortcutTarget {
+        target,
+        arguments: lnk.arguments().clone(),
+    })
+}
+
+/// Every shortcut file in the folder and its subfolders
+pub fn find_shortcut_files(folder: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut shortcut_files = Vec::new();
+    if !folder.is_dir() {
+        return Ok(shortcut_files);
+    }
+    let mut folders = vec![folder.to_owned()];
+    while let Some(current_folder) = folders.pop() {
+        for item in current_folder.folder_items()? {
+            let path = item.path();
+            if path.is_dir() {
+                folders.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("lnk"))
+            {
+                shortcut_files.push(path);
+            }
+        }
+    }
+    shortcut_files.sort();
+    Ok(shortcut_files)
+}
+
+impl ShortcutFile {
+    pub fn new(label: &str, folder: &Path, arguments: Option<String>) -> ShortcutFile {
+        ShortcutFile {
+            path: folder.join(format!("{}.lnk", label)),
+            arguments,
+        }
+    }
+
+    /// Overwrites any shortcut file that's already at the path
+    pub fn create(&self, executable_path: &Path) -> Result<(), MSLinkError> {
+        // For whatever reason mslnk doesn't work with a normal Path struct, only a String
+        let mut lnk = mslnk::ShellLink::new(executable_path.path_str()?)?;
+        lnk.set_arguments(self.arguments.clone());
+        if self.path.is_file() {
+            fs::remove_file(&self.path)?
+        }
+        lnk.create_lnk(&self.path)?;
+        Ok(())
+    }
+
+    /// Rolling back points the shortcut file back at the previous executable, or removes it if
+    /// it didn't exist before
+    pub fn create_in_transaction(
+        &self,
+        executable_path: &Path,
+        previous_executable_path: Option<PathBuf>,
+        transaction: &mut Transaction,
+    ) -> Result<(), MSLinkError> {
+        let existed = self.path.is_file();
+        self.create(executable_path)?;
+        let shortcut = self.clone();
+        match (existed, previous_executable_path) {
+            (false, _) => transaction.on_rollback(move || shortcut.remove()),
+            (true, Some(previous_executable_path)) => transaction.on_rollback(move || {
+                shortcut
+                    .create(&previous_executable_path)
+                    .map_err(to_io_error)
+            }),
+            (true, None) => {}
+        }
+        Ok(())
+    }
+
+    /// Creates the shortcut file again when the transaction is committed if something like a
+    /// previous installer's uninstaller deleted it
+    pub fn restore_on_commit(&self, executable_path: &Path, transaction: &mut Transaction) {
+        let shortcut = self.clone();
+        let executable_path = executable_path.to_owned();
+        transaction.on_commit(move || match shortcut.path.is_file() {
+            true => Ok(()),
+            false => shortcut.create(&executable_path).map_err(to_io_error),
+        });
+    }
+
+    pub fn remove(&self) -> Result<(), io::Error> {
+        if self.path.is_file() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A minimal shell link with only a relative path, working directory and arguments like the
+/// ones mslnk creates
+#[cfg(test)]
+fn lnk_bytes(working_dir: &str, relative_path: &str, arguments: &str) -> Vec<u8> {
+    // HAS_RELATIVE_PATH | HAS_WORKING_DIR | HAS_ARGUMENTS | IS_UNICODE
+    let link_flags: u32 = 0x08 | 0x10 | 0x20 | 0x80;
+    let mut bytes = Vec::new();
+    bytes.extend(0x4Cu32.to_le_bytes());
+    // Shell link CLSID 00021401-0000-0000-C000-000000000046
+    bytes.extend([
+        0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x46,
+    ]);
+    bytes.extend(link_flags.to_le_bytes());
+    // File attributes, timestamps, file size and icon index
+    bytes.extend([0u8; 4 + 24 + 4 + 4]);
+    // SW_SHOWNORMAL
+    bytes.extend(1u32.to_le_bytes());
+    // Hotkey and reserved fields
+    bytes.extend([0u8; 2 + 2 + 4 + 4]);
+    for string in [relative_path, working_dir, arguments] {
+        let units = string.encode_utf16().collect::<Vec<u16>>();
+        bytes.extend((units.len() as u16).to_le_bytes());
+        units.iter().for_each(|u| bytes.extend(u.to_le_bytes()));
+    }
+    // Terminal extra data block
+    bytes.extend(0u32.to_le_bytes());
+    bytes
+}
+
+/// Shortcut file launching the target the way the ones mslnk creates do
+#[cfg(test)]
+pub fn create_test_shortcut(shortcut_path: &Path, target: &Path) {
+    let working_dir = target.parent().unwrap().to_string_lossy();
+    let relative_path = format!(".\\{}", target.file_name().unwrap().to_string_lossy());
+    fs::write(shortcut_path, lnk_bytes(&working_dir, &relative_path, "")).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+
+    fn create_startmenu_folder(test_name: &str) -> PathBuf {
+        let test_folder = create_test_folder(test_name);
+        fs::create_dir_all(test_folder.join("Programs")).unwrap();
+        test_folder
+    }
+
+    #[test]
+    fn reads_target_and_arguments_of_shortcut_without_link_info() {
+        let test_folder = create_startmenu_folder("read-shortcut");
+        let shortcut_path = test_folder.join("App.lnk");
+        let working_dir = "C:\\Senget\\packages\\App\\1.0.0";
+        fs::write(
+            &shortcut_path,
+            lnk_bytes(working_dir, ".\\App.exe", "--profile work"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_shortcut(&shortcut_path),
+            Some(ShortcutTarget {
+                target: Path::new(working_dir).join("App.exe"),
+                arguments: Some("--profile work".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn finds_shortcut_files_in_subfolders() {
+        let test_folder = create_startmenu_folder("find-shortcut-files");
+        let nested_shortcut_path = test_folder.join("Programs").join("App.LNK");
+        fs::write(test_folder.join("App.lnk"), []).unwrap();
+        fs::write(&nested_shortcut_path, []).unwrap();
+        fs::write(test_folder.join("App.txt"), []).unwrap();
+
+        assert_eq!(
+            find_shortcut_files(&test_folder).unwrap(),
+            vec![test_folder.join("App.lnk"), nested_shortcut_path]
+        );
+        assert!(read_shortcut(&test_folder.join("App.txt")).is_none());
+    }
+}
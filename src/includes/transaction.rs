@@ -0,0 +1,337 @@
+//!Makes package installation and updates all or nothing
+
+use crate::eprintln_pretty;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const BACKUP_FOLDER_SUFFIX: &str = "senget-backup";
+const STAGING_FOLDER_SUFFIX: &str = "senget-staging";
+
+struct FolderSwap {
+    installation_folder: PathBuf,
+    backup_folder: Option<PathBuf>,
+}
+
+/// Keeps the previous installation of a package around until the caller commits, if the
+/// transaction is dropped without being committed everything it did is rolled back
+#[derive(Default)]
+pub struct Transaction {
+    swaps: Vec<FolderSwap>,
+    rollback_actions: Vec<Box<dyn FnOnce() -> Result<(), io::Error>>>,
+    commit_actions: Vec<Box<dyn FnOnce() -> Result<(), io::Error>>>,
+    committed: bool,
+}
+
+fn sibling_folder_path(folder: &Path, suffix: &str) -> PathBuf {
+    let name = folder
+        .file_name()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default();
+    // Sibling so that renaming between them never crosses drives
+    folder.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Empty folder next to the installation folder to unpack a package into before it's swapped in
+pub fn generate_staging_folder_path(installation_folder: &Path) -> Result<PathBuf, io::Error> {
+    let staging_folder = sibling_folder_path(installation_folder, STAGING_FOLDER_SUFFIX);
+    if staging_folder.is_dir() {
+        fs::remove_dir_all(&staging_folder)?;
+    }
+    fs::create_dir_all(&staging_folder)?;
+    Ok(staging_folder)
+}
+
+/// Staging and backup folders in the folder that a run which crashed midway left behind
+pub fn find_leftover_folders(folder: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut leftover_folders = Vec::new();
+    if !folder.is_dir() {
+        return Ok(leftover_folders);
+    }
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let is_leftover = name.starts_with('.')
+            && [STAGING_FOLDER_SUFFIX, BACKUP_FOLDER_SUFFIX]
+                .iter()
+                .any(|suffix| name.ends_with(&format!(".{}", suffix)));
+        if is_leftover && path.is_dir() {
+            leftover_folders.push(path);
+        }
+    }
+    leftover_folders.sort();
+    Ok(leftover_folders)
+}
+
+/// Deletes a leftover staging folder, a leftover backup folder is moved back if the installation
+/// folder it was backing up is missing
+pub fn clean_up_leftover_folder(leftover_folder: &Path) -> Result<(), io::Error> {
+    let name = leftover_folder
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let backup_suffix = format!(".{}", BACKUP_FOLDER_SUFFIX);
+    match name.strip_suffix(&backup_suffix) {
+        Some(installation_folder_name) => recover_backup_folder(
+            leftover_folder,
+            &leftover_folder.with_file_name(
+                installation_folder_name
+                    .strip_prefix('.')
+                    .unwrap_or_default(),
+            ),
+        ),
+        None => fs::remove_dir_all(leftover_folder),
+    }
+}
+
+fn recover_backup_folder(
+    backup_folder: &Path,
+    installation_folder: &Path,
+) -> Result<(), io::Error> {
+    match installation_folder.is_dir() {
+        true => fs::remove_dir_all(backup_folder),
+        false => fs::rename(backup_folder, installation_folder),
+    }
+}
+
+/// Every step of a commit or rollback runs even if an earlier one failed, so the errors are
+/// merged into one
+fn merge_errors(mut errors: Vec<io::Error>) -> Result<(), io::Error> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(io::Error::other(
+            errors
+                .iter()
+                .map(io::Error::to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+        )),
+    }
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction::default()
+    }
+
+    /// Replaces the installation folder with the staging folder, the previous installation folder
+    /// is kept as a backup until the transaction is committed
+    pub fn swap_in_folder(
+        &mut self,
+        staging_folder: &Path,
+        installation_folder: &Path,
+    ) -> Result<(), io::Error> {
+        let backup_folder = sibling_folder_path(installation_folder, BACKUP_FOLDER_SUFFIX);
+        if backup_folder.is_dir() {
+            // Left behind by a run that crashed midway through a swap
+            recover_backup_folder(&backup_folder, installation_folder)?;
+        }
+        let backup_folder = match installation_folder.is_dir() {
+            true => {
+                fs::rename(installation_folder, &backup_folder)?;
+                Some(backup_folder)
+            }
+            false => None,
+        };
+        if let Some(parent) = installation_folder.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Err(err) = fs::rename(staging_folder, installation_folder) {
+            if let Some(backup_folder) = backup_folder {
+                fs::rename(backup_folder, installation_folder)?;
+            }
+            return Err(err);
+        }
+        self.swaps.push(FolderSwap {
+            installation_folder: installation_folder.to_owned(),
+            backup_folder,
+        });
+        Ok(())
+    }
+
+    /// Registers something to undo if the transaction is rolled back
+    pub fn on_rollback<F>(&mut self, action: F)
+    where
+        F: FnOnce() -> Result<(), io::Error> + 'static,
+    {
+        self.rollback_actions.push(Box::new(action));
+    }
+
+    /// Registers something to only do once the transaction is committed, e.g., deleting the
+    /// previous version of a package
+    pub fn on_commit<F>(&mut self, action: F)
+    where
+        F: FnOnce() -> Result<(), io::Error> + 'static,
+    {
+        self.commit_actions.push(Box::new(action));
+    }
+
+    /// Leftover backup folders are cleaned up later by the doctor, so a failed step doesn't stop
+    /// the ones after it
+    pub fn commit(mut self) -> Result<(), io::Error> {
+        self.committed = true;
+        let mut errors = Vec::new();
+        for swap in self.swaps.drain(..) {
+            if let Some(backup_folder) = swap.backup_folder {
+                errors.extend(fs::remove_dir_all(backup_folder).err());
+            }
+        }
+        for action in self.commit_actions.drain(..) {
+            errors.extend(action().err());
+        }
+        merge_errors(errors)
+    }
+
+    /// Undoes as much as possible even if some of the steps fail
+    fn rollback(&mut self) -> Result<(), io::Error> {
+        let mut errors = Vec::new();
+        // Undo in the reverse order the changes were made
+        for action in self.rollback_actions.drain(..).rev() {
+            errors.extend(action().err());
+        }
+        for swap in self.swaps.drain(..).rev() {
+            if swap.installation_folder.is_dir() {
+                if let Err(err) = fs::remove_dir_all(&swap.installation_folder) {
+                    // Renaming the backup folder onto a folder that's still there would fail
+                    errors.push(err);
+                    continue;
+                }
+            }
+            if let Some(backup_folder) = swap.backup_folder {
+                errors.extend(fs::rename(backup_folder, swap.installation_folder).err());
+            }
+        }
+        merge_errors(errors)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            // The caller is already handling another error so this one can only be reported
+            if let Err(err) = self.rollback() {
+                eprintln_pretty!("Failed to roll back: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::includes::utils::create_test_folder;
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Creates a fresh folder with an installation folder and a staging folder holding a file each
+    fn create_fixture(test_name: &str) -> (PathBuf, PathBuf) {
+        let installation_folder = create_test_folder(test_name).join("package");
+        fs::create_dir_all(&installation_folder).unwrap();
+        fs::write(installation_folder.join("version.txt"), "old").unwrap();
+        let staging_folder = generate_staging_folder_path(&installation_folder).unwrap();
+        fs::write(staging_folder.join("version.txt"), "new").unwrap();
+        (staging_folder, installation_folder)
+    }
+
+    fn installed_version(installation_folder: &Path) -> String {
+        fs::read_to_string(installation_folder.join("version.txt")).unwrap()
+    }
+
+    fn failing_action() -> Result<(), io::Error> {
+        Err(io::Error::other("failed"))
+    }
+
+    #[test]
+    fn swaps_in_the_staging_folder_and_keeps_a_backup() {
+        let (staging_folder, installation_folder) = create_fixture("transaction-swap");
+        let mut transaction = Transaction::new();
+        transaction
+            .swap_in_folder(&staging_folder, &installation_folder)
+            .unwrap();
+        assert_eq!(installed_version(&installation_folder), "new");
+        assert!(!staging_folder.exists());
+        let leftover_folders = find_leftover_folders(installation_folder.parent().unwrap());
+        assert_eq!(leftover_folders.unwrap().len(), 1);
+        transaction.commit().unwrap();
+    }
+
+    #[test]
+    fn commit_deletes_the_backup_and_runs_every_action() {
+        let (staging_folder, installation_folder) = create_fixture("transaction-commit");
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let mut transaction = Transaction::new();
+        transaction
+            .swap_in_folder(&staging_folder, &installation_folder)
+            .unwrap();
+        transaction.on_commit(failing_action);
+        let ran_clone = ran.clone();
+        transaction.on_commit(move || {
+            ran_clone.borrow_mut().push("commit");
+            Ok(())
+        });
+        assert!(transaction.commit().is_err());
+        assert_eq!(*ran.borrow(), vec!["commit"]);
+        assert_eq!(installed_version(&installation_folder), "new");
+        let leftover_folders = find_leftover_folders(installation_folder.parent().unwrap());
+        assert!(leftover_folders.unwrap().is_empty());
+    }
+
+    #[test]
+    fn rollback_restores_the_previous_installation() {
+        let (staging_folder, installation_folder) = create_fixture("transaction-rollback");
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let mut transaction = Transaction::new();
+        transaction
+            .swap_in_folder(&staging_folder, &installation_folder)
+            .unwrap();
+        let ran_clone = ran.clone();
+        transaction.on_rollback(move || {
+            ran_clone.borrow_mut().push("rollback");
+            Ok(())
+        });
+        let ran_clone = ran.clone();
+        transaction.on_commit(move || {
+            ran_clone.borrow_mut().push("commit");
+            Ok(())
+        });
+        drop(transaction);
+        assert_eq!(*ran.borrow(), vec!["rollback"]);
+        assert_eq!(installed_version(&installation_folder), "old");
+        let leftover_folders = find_leftover_folders(installation_folder.parent().unwrap());
+        assert!(leftover_folders.unwrap().is_empty());
+    }
+
+    #[test]
+    fn rollback_runs_every_step_when_one_fails() {
+        let (staging_folder, installation_folder) = create_fixture("transaction-failed-rollback");
+        let ran = Rc::new(RefCell::new(Vec::new()));
+        let mut transaction = Transaction::new();
+        let ran_clone = ran.clone();
+        transaction.on_rollback(move || {
+            ran_clone.borrow_mut().push("rollback");
+            Ok(())
+        });
+        transaction
+            .swap_in_folder(&staging_folder, &installation_folder)
+            .unwrap();
+        transaction.on_rollback(failing_action);
+        assert!(transaction.rollback().is_err());
+        assert_eq!(*ran.borrow(), vec!["rollback"]);
+        assert_eq!(installed_version(&installation_folder), "old");
+    }
+
+    #[test]
+    fn rollback_removes_a_fresh_installation() {
+        let (staging_folder, installation_folder) = create_fixture("transaction-fresh");
+        fs::remove_dir_all(&installation_folder).unwrap();
+        let mut transaction = Transaction::new();
+        transaction
+            .swap_in_folder(&staging_folder, &installation_folder)
+            .unwrap();
+        assert_eq!(installed_version(&installation_folder), "new");
+        drop(transaction);
+        assert!(!installation_folder.exists());
+    }
+}
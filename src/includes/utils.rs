@@ -1,13 +1,15 @@
 //!Global variables and utility traits, structs, enums and functions
 
+use crate::includes::error::{AmbiguousNameError, SengetErrors};
 use reqwest::{header, Client};
 use spinners::{Spinner, Spinners};
 use std::{
     env,
     fs::{self, DirEntry},
-    io,
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
     process::Command,
+    sync::Mutex,
 };
 
 #[macro_export]
@@ -31,6 +33,8 @@ pub const REPO_URL: &str = env!("CARGO_PKG_REPOSITORY");
 pub const IBYTES_TO_MBS_DIVISOR: u64 = 1024 * 1024;
 pub const DEBUG: bool = cfg!(debug_assertions);
 pub const EXPORTED_PACKAGES_FILENAME: &str = "senget-packages.json";
+// Spinner of the running loading animation and its title, prompts stop it while they wait
+static ACTIVE_SPINNER: Mutex<Option<(Spinner, String)>> = Mutex::new(None);
 
 pub trait Cmd {
     fn cmd() -> Command;
@@ -52,6 +56,37 @@ impl<T> Take<T> for Vec<T> {
         self.into_iter().nth(index)
     }
 }
+/// In the largest unit the size is at least one of e.g., 512 B, 3.2 KB or 12.5 MB
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Total size of the files in the folder and its subfolders
+pub fn folder_size(folder: &Path) -> Result<u64, io::Error> {
+    let mut size = 0;
+    let mut folders = vec![folder.to_owned()];
+    while let Some(current_folder) = folders.pop() {
+        for item in current_folder.folder_items()? {
+            let metadata = item.metadata()?;
+            match metadata.is_dir() {
+                true => folders.push(item.path()),
+                false => size += metadata.len(),
+            }
+        }
+    }
+    Ok(size)
+}
+
 pub trait MoveDirAll {
     fn move_dir_all(&self, to: &Path) -> Result<(), io::Error>;
 }
@@ -73,7 +108,6 @@ impl MoveDirAll for Path {
                     helper(&item_path, item_to)?;
                 } else {
                     if item_to.is_file() {
-                        dbg!(item_to);
                         fs::remove_file(item_to)?;
                     } else if item_to.is_dir() {
                         fs::remove_dir_all(item_to)?;
@@ -87,6 +121,28 @@ impl MoveDirAll for Path {
     }
 }
 
+pub trait CopyDirAll {
+    fn copy_dir_all(&self, to: &Path) -> Result<(), io::Error>;
+}
+
+impl CopyDirAll for Path {
+    fn copy_dir_all(&self, to: &Path) -> Result<(), io::Error> {
+        if !to.is_dir() {
+            fs::create_dir_all(to)?;
+        }
+        for item in self.folder_items()? {
+            let item_to = to.join(item.file_name());
+            let item_path = item.path();
+            if item_path.is_dir() {
+                item_path.copy_dir_all(&item_to)?;
+            } else {
+                fs::copy(item_path, item_to)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub trait FolderItems {
     fn folder_items(&self) -> Result<Vec<DirEntry>, io::Error>;
 }
@@ -145,19 +201,149 @@ pub fn config_dir() -> PathBuf {
     config_path
 }
 
+/// Resolves a path relative to the current directory without requiring it to exist
+pub fn absolute_path(path: &Path) -> PathBuf {
+    env::current_dir()
+        .map(|cwd| cwd.join(path))
+        .unwrap_or_else(|_| path.to_owned())
+}
+
 pub fn loading_animation<T, E, F>(task_title: String, task: F) -> Result<T, E>
 where
     F: FnOnce() -> Result<T, E>,
 {
-    let mut spinner = Spinner::new(Spinners::Dots, task_title);
-    match task() {
-        Ok(ok) => {
-            spinner.stop_and_persist("✔", "\x1b[32mFinished\x1b[0m".to_owned());
-            Ok(ok)
+    let spinner = Spinner::new(Spinners::Dots, task_title.clone());
+    *ACTIVE_SPINNER.lock().unwrap() = Some((spinner, task_title));
+    let result = task();
+    if let Some((mut spinner, _)) = ACTIVE_SPINNER.lock().unwrap().take() {
+        match result {
+            Ok(_) => spinner.stop_and_persist("✔", "\x1b[32mFinished\x1b[0m".to_owned()),
+            Err(_) => spinner.stop_and_persist("x", "\x1b[31mFailed\x1b[0m".to_owned()),
+        }
+    }
+    result
+}
+
+/// Whether a user is around to answer prompts
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Levenshtein distance between the names ignoring case
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect::<Vec<char>>();
+    let b = b.to_lowercase().chars().collect::<Vec<char>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != b_char);
+            row.push(substitution.min(previous_row[j + 1] + 1).min(row[j] + 1));
+        }
+        previous_row = row;
+    }
+    previous_row[b.len()]
+}
+
+/// The candidate closest to the name if it's close enough to be a typo of it, up to a third of
+/// the name's characters can differ. Ties go to the earlier candidate
+pub fn closest_name<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let max_distance = name.chars().count().div_ceil(3);
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(distance, _)| (1..=max_distance).contains(distance))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+/// The item whose full name matches or the only one whose name does, names are matched case
+/// insensitively. When several names match the user picks one, if they can't be asked the error
+/// lists the full names to use instead
+pub fn pick_by_name<T, F>(name: &str, items: Vec<T>, names: F) -> Result<Option<T>, SengetErrors>
+where
+    F: Fn(&T) -> (&str, &str),
+{
+    pick_by_name_with_prompt(name, items, names, is_interactive())
+}
+
+/// Errors out on ambiguous names instead of asking when it can't prompt
+fn pick_by_name_with_prompt<T, F>(
+    name: &str,
+    items: Vec<T>,
+    names: F,
+    can_prompt: bool,
+) -> Result<Option<T>, SengetErrors>
+where
+    F: Fn(&T) -> (&str, &str),
+{
+    let name_lower = name.to_lowercase();
+    let mut matches = Vec::new();
+    for item in items {
+        let (short_name, full_name) = names(&item);
+        if full_name.to_lowercase() == name_lower {
+            return Ok(Some(item));
+        }
+        if short_name.to_lowercase() == name_lower {
+            matches.push(item);
         }
-        Err(err) => {
-            spinner.stop_and_persist("x", "\x1b[31mFailed\x1b[0m".to_owned());
-            Err(err)
+    }
+    if matches.len() < 2 {
+        return Ok(matches.pop());
+    }
+    let candidates = matches
+        .iter()
+        .map(|m| names(m).1.to_owned())
+        .collect::<Vec<String>>();
+    if !can_prompt {
+        return Err(AmbiguousNameError {
+            name: name.to_owned(),
+            candidates,
+        }
+        .into());
+    }
+    let question = format!(
+        "Multiple packages are named {}, which one did you mean?",
+        name
+    );
+    let choice = prompt_choice(&question, &candidates)?;
+    Ok(matches.take(choice))
+}
+
+/// Asks the user to pick one of the options and returns its index, the first one is the default
+pub fn prompt_choice(question: &str, options: &[String]) -> Result<usize, io::Error> {
+    // The spinner of a task that asks midway would draw over the question
+    let mut active_spinner = ACTIVE_SPINNER.lock().unwrap();
+    if let Some((spinner, _)) = active_spinner.as_mut() {
+        spinner.stop_with_newline();
+    }
+    let choice = ask_choice(question, options);
+    if let Some((spinner, task_title)) = active_spinner.as_mut() {
+        *spinner = Spinner::new(Spinners::Dots, task_title.clone());
+    }
+    choice
+}
+
+fn ask_choice(question: &str, options: &[String]) -> Result<usize, io::Error> {
+    println!("{}", question);
+    for (idx, option) in options.iter().enumerate() {
+        println!("  {}. {}", idx + 1, option);
+    }
+    loop {
+        print!("Choice [1]: ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(0);
+        }
+        match answer.parse::<usize>() {
+            Ok(choice) if (1..=options.len()).contains(&choice) => return Ok(choice - 1),
+            _ => println!("Enter a number between 1 and {}", options.len()),
         }
     }
 }
@@ -170,3 +356,82 @@ pub fn setup_client() -> Result<Client, reqwest::Error> {
     );
     Client::builder().default_headers(headers).build()
 }
+
+/// Empty folder for a test to work in, whatever an earlier run left in it is deleted
+#[cfg(test)]
+pub fn create_test_folder(test_name: &str) -> PathBuf {
+    let test_folder = env::temp_dir().join(format!("senget-test-{}", test_name));
+    if test_folder.is_dir() {
+        fs::remove_dir_all(&test_folder).unwrap();
+    }
+    fs::create_dir_all(&test_folder).unwrap();
+    test_folder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_edit_distance_ignoring_case() {
+        assert_eq!(edit_distance("senpwai", "Senpwai"), 0);
+        assert_eq!(edit_distance("senpwia", "senpwai"), 2);
+        assert_eq!(edit_distance("senget", "sengets"), 1);
+        assert_eq!(edit_distance("", "cli"), 3);
+    }
+
+    #[test]
+    fn formats_size_in_the_largest_whole_unit() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KB");
+        assert_eq!(format_size(1024 * 1024), "1.0 MB");
+        assert_eq!(format_size(12 * 1024 * 1024 + 512 * 1024), "12.5 MB");
+        assert_eq!(format_size(1024 * 1024 * 1024), "1.0 GB");
+    }
+
+    #[test]
+    fn suggests_closest_name_within_a_third_of_its_length() {
+        let names = ["Senget", "Senpwai", "ripgrep"];
+        assert_eq!(closest_name("senpwia", names), Some("Senpwai"));
+        assert_eq!(closest_name("ripgrap", names), Some("ripgrep"));
+        assert_eq!(closest_name("senget", names), None);
+        assert_eq!(closest_name("cli", names), None);
+    }
+
+    fn pick(name: &str, full_names: &[&'static str]) -> Result<Option<&'static str>, SengetErrors> {
+        let short_name = |full_name: &&'static str| full_name.rsplit('/').next().unwrap();
+        pick_by_name_with_prompt(name, full_names.to_vec(), |n| (short_name(n), *n), false)
+    }
+
+    #[test]
+    fn picks_the_full_name_match_over_short_names() {
+        let full_names = ["SenZmaKi/Senpwai", "Senpwai/SenZmaKi", "Forks/SenZmaKi"];
+        assert_eq!(
+            pick("senzmaki/senpwai", &full_names).ok().unwrap(),
+            Some("SenZmaKi/Senpwai")
+        );
+    }
+
+    #[test]
+    fn picks_the_only_short_name_match() {
+        let full_names = ["SenZmaKi/Senpwai", "SenZmaKi/Senget"];
+        assert_eq!(
+            pick("SENGET", &full_names).ok().unwrap(),
+            Some("SenZmaKi/Senget")
+        );
+        assert_eq!(pick("ripgrep", &full_names).ok().unwrap(), None);
+    }
+
+    #[test]
+    fn lists_the_full_names_of_ambiguous_matches_without_a_prompt() {
+        let full_names = ["SenZmaKi/Senget", "Forks/Senget", "SenZmaKi/Senpwai"];
+        match pick("senget", &full_names) {
+            Err(SengetErrors::AmbiguousNameError(err)) => {
+                assert_eq!(err.name, "senget");
+                assert_eq!(err.candidates, ["SenZmaKi/Senget", "Forks/Senget"]);
+            }
+            _ => panic!("expected an AmbiguousNameError"),
+        }
+    }
+}
@@ -10,16 +10,15 @@ use includes::{
     error::{print_error, SengetErrors},
     github,
     senget_manager::{
-        env::setup_senget_packages_path_env_var,
+        env::setup_shims_path_env_var,
         package::{
             check_if_senget_update_available, generate_senget_package, setup_senget_package,
         },
     },
+    shim::rebuild_shims,
     utils::{config_dir, PathStr},
 };
-use std::{sync::Arc, env};
-
-
+use std::{env, sync::Arc};
 
 fn init() -> Result<
     (
@@ -34,17 +33,15 @@ fn init() -> Result<
     let config_path = config_dir();
     let statics = Statics::new(&config_path)?;
     let db = PackageDatabase::new(&config_path)?;
-    let senget_package =
-        generate_senget_package()?;
-    setup_senget_package(&db, &senget_package)?;
-    setup_senget_packages_path_env_var(
-        &senget_package
-            .install_info
-            .installation_folder
-            .as_ref()
-            .unwrap()
-            .path_str()?,
-    )?;
+    let senget_package = generate_senget_package()?;
+    // Left for "senget db restore" to fix instead of blocking every command
+    match setup_senget_package(&db, &senget_package) {
+        Err(SengetErrors::CorruptDatabaseError(_)) => {}
+        result => result?,
+    }
+    if setup_shims_path_env_var(&statics.shims_folder_path.path_str()?)? {
+        rebuild_shims(&db.fetch_all_packages()?, &statics.shims_folder_path)?;
+    }
     Ok((commands, statics, db, senget_package))
 }
 